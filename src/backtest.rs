@@ -0,0 +1,288 @@
+use crate::config::Config;
+use crate::metadata::{ FakeMetadataFetcher, OffChainMetadata };
+use crate::quote::quote_pool;
+use crate::rpc::FakeRpc;
+use crate::rug_rules::RugVerdict;
+use crate::rugcheck::pool_reserves;
+use crate::swap::{ screen_new_pool, PoolError, PoolScreen, ScreenedPool };
+use crate::utils::PoolInfo;
+use serde::Deserialize;
+use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_response::RpcTokenAccountBalance;
+use solana_sdk::account::Account;
+use solana_sdk::native_token::{ lamports_to_sol, sol_to_lamports };
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{ EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction };
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Recorded chain state the backtest answers RPC lookups from. Accounts, token balances,
+/// largest accounts and supplies use the same JSON shape as `getAccountInfo` (base64),
+/// `getTokenAccountBalance`, `getTokenLargestAccounts` and `getTokenSupply` results.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FixtureFile {
+    sol_price: f64,
+    accounts: HashMap<String, UiAccount>,
+    token_balances: HashMap<String, UiTokenAmount>,
+    /// Keyed by mint
+    largest_accounts: HashMap<String, Vec<RpcTokenAccountBalance>>,
+    /// Keyed by mint
+    token_supplies: HashMap<String, UiTokenAmount>,
+    /// Off-chain metadata documents, keyed by URI
+    metadata_documents: HashMap<String, OffChainMetadata>,
+    /// Vault reserves to simulate the exit against, keyed by pool id
+    exits: HashMap<String, ExitReserves>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExitReserves {
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+}
+
+/// Fixtures loaded into a `FakeRpc`, plus the data that has no RPC equivalent.
+pub struct FixtureStore {
+    pub rpc: Arc<FakeRpc>,
+    pub metadata_fetcher: FakeMetadataFetcher,
+    pub sol_price: f64,
+    pub exits: HashMap<String, ExitReserves>,
+}
+
+impl Default for FixtureStore {
+    fn default() -> Self {
+        FixtureStore {
+            rpc: FakeRpc::new(),
+            metadata_fetcher: FakeMetadataFetcher::default(),
+            sol_price: 0.0,
+            exits: HashMap::new(),
+        }
+    }
+}
+
+impl FixtureStore {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs
+            ::read_to_string(path)
+            .map_err(|e| format!("Failed to read fixtures {}: {}", path, e))?;
+        let file: FixtureFile = serde_json::from_str(&contents)?;
+
+        let rpc = FakeRpc::new();
+        for (pubkey, account) in file.accounts {
+            let account = account
+                .decode::<Account>()
+                .ok_or_else(|| format!("Fixture account {} is not base58/base64 encoded", pubkey))?;
+            rpc.insert_account(Pubkey::from_str(&pubkey)?, account);
+        }
+        for (pubkey, balance) in file.token_balances {
+            rpc.insert_token_balance(Pubkey::from_str(&pubkey)?, balance);
+        }
+        for (mint, accounts) in file.largest_accounts {
+            rpc.insert_largest_accounts(Pubkey::from_str(&mint)?, accounts);
+        }
+        for (mint, supply) in file.token_supplies {
+            rpc.insert_token_supply(Pubkey::from_str(&mint)?, supply);
+        }
+
+        Ok(FixtureStore {
+            rpc,
+            metadata_fetcher: FakeMetadataFetcher::new(file.metadata_documents),
+            sol_price: file.sol_price,
+            exits: file.exits,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BacktestResult {
+    pub signature: String,
+    pub pool_info: Option<PoolInfo>,
+    pub verdict: Option<RugVerdict>,
+    pub outcome: Result<(), PoolError>,
+    /// Simulated profit in SOL, `None` when no exit reserves were recorded
+    pub pnl_sol: Option<f64>,
+}
+
+/// `backtest [--fixtures <file>] <tx.json | txs.jsonl>...`
+pub async fn run(args: &[String], config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut fixtures = FixtureStore::default();
+    let mut paths: Vec<&String> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--fixtures" {
+            let path = iter.next().ok_or("--fixtures needs a file path")?;
+            fixtures = FixtureStore::load(path)?;
+        } else {
+            paths.push(arg);
+        }
+    }
+
+    if paths.is_empty() {
+        return Err("Usage: backtest [--fixtures <file>] <tx.json | txs.jsonl>...".into());
+    }
+
+    let mut results: Vec<BacktestResult> = Vec::new();
+    for path in paths {
+        for tx in load_transactions(path)? {
+            results.push(backtest_transaction(tx, &fixtures, config).await);
+        }
+    }
+
+    print_report(&results);
+
+    Ok(())
+}
+
+fn load_transactions(
+    path: &str
+) -> Result<Vec<EncodedConfirmedTransactionWithStatusMeta>, Box<dyn Error>> {
+    let contents = std::fs
+        ::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    if path.ends_with(".jsonl") {
+        return contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| e.into()))
+            .collect();
+    }
+
+    match serde_json::from_str::<Vec<EncodedConfirmedTransactionWithStatusMeta>>(&contents) {
+        Ok(txs) => Ok(txs),
+        Err(_) => Ok(vec![serde_json::from_str(&contents)?]),
+    }
+}
+
+pub async fn backtest_transaction(
+    tx: EncodedConfirmedTransactionWithStatusMeta,
+    fixtures: &FixtureStore,
+    config: &Config
+) -> BacktestResult {
+    let signature = match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => ui_tx.signatures.first().cloned().unwrap_or_default(),
+        _ => String::new(),
+    };
+
+    // Fixtures are a single snapshot, waiting for the LP burn or liquidity changes nothing
+    let screen = PoolScreen {
+        rpc: fixtures.rpc.as_ref(),
+        config,
+        pubsub_clients: Vec::new(),
+        mongo: None,
+        metadata_fetcher: Some(&fixtures.metadata_fetcher),
+        sol_price: Some(fixtures.sol_price),
+        wait: false,
+    };
+    let ScreenedPool { pool_info, verdict, .. } = match screen_new_pool(tx, &screen).await {
+        Ok(screened) => screened,
+        Err(err) => {
+            return BacktestResult {
+                signature,
+                pool_info: None,
+                verdict: None,
+                outcome: Err(err),
+                pnl_sol: None,
+            };
+        }
+    };
+
+    let (outcome, pnl_sol) = if verdict.passed {
+        (Ok(()), simulate_pnl(&pool_info, fixtures, config).await)
+    } else {
+        (Err(PoolError::Rejected(verdict.clone())), None)
+    };
+
+    BacktestResult {
+        signature,
+        pool_info: Some(pool_info),
+        verdict: Some(verdict),
+        outcome,
+        pnl_sol,
+    }
+}
+
+async fn simulate_pnl(
+    pool_info: &PoolInfo,
+    fixtures: &FixtureStore,
+    config: &Config
+) -> Option<f64> {
+    let exit = fixtures.exits.get(&pool_info.id.to_string())?;
+    let wsol_mint = spl_token::native_mint::id();
+    let token_mint = if pool_info.base_mint == wsol_mint {
+        pool_info.quote_mint
+    } else {
+        pool_info.base_mint
+    };
+
+    let amount_in = sol_to_lamports(config.buy.amount_sol);
+    let (base_reserve, quote_reserve) = pool_reserves(fixtures.rpc.as_ref(), pool_info).await;
+    let entry = quote_pool(
+        pool_info,
+        base_reserve,
+        quote_reserve,
+        &wsol_mint,
+        amount_in,
+        config.buy.slippage_bps
+    ).ok()?;
+    let exit = quote_pool(
+        pool_info,
+        exit.base_reserve,
+        exit.quote_reserve,
+        &token_mint,
+        entry.amount_out,
+        config.sell.slippage_bps
+    ).ok()?;
+
+    Some(lamports_to_sol(exit.amount_out) - lamports_to_sol(amount_in))
+}
+
+fn print_report(results: &[BacktestResult]) {
+    let mut bought = 0;
+    let mut total_pnl = 0.0;
+
+    for result in results {
+        let pool = result.pool_info
+            .as_ref()
+            .map(|info| format!("pool {} ({})", info.id, info.base_mint))
+            .unwrap_or_else(|| "no pool".to_string());
+        let risk = result.verdict.as_ref().map_or(0.0, |verdict| verdict.score);
+
+        match (&result.outcome, result.pnl_sol) {
+            (Ok(()), Some(pnl)) => {
+                bought += 1;
+                total_pnl += pnl;
+                println!(
+                    "BUY   {} {} (risk {:.2}): simulated PnL {:+.6} SOL",
+                    result.signature,
+                    pool,
+                    risk,
+                    pnl
+                );
+            }
+            (Ok(()), None) => {
+                bought += 1;
+                println!(
+                    "BUY   {} {} (risk {:.2}): no exit reserves recorded",
+                    result.signature,
+                    pool,
+                    risk
+                );
+            }
+            (Err(err), _) => {
+                println!("SKIP  {} {}: {}", result.signature, pool, err);
+            }
+        }
+    }
+
+    println!(
+        "{} transactions, {} pools bought, simulated PnL {:+.6} SOL",
+        results.len(),
+        bought,
+        total_pnl
+    );
+}
@@ -1,97 +1,521 @@
-use crate::utils::find_log_entry;
-use crate::swap::check_for_new_pool;
-use crate::mongo::MongoHandler;
-use std::sync::Arc;
-use solana_client::rpc_client::RpcClient;
+use crate::detector::{ is_pool_creation_log, pool_detectors };
+use crate::swap::{ check_for_new_pool, check_for_pump_launch };
+use crate::context::AppContext;
+use crate::config::{ Config, DetectionSource, PumpTrigger };
+use crate::pump_sdk::{ self, CreateEvent, PumpEvent };
+use crate::dedup::SignatureDedup;
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use crate::rpc::{ connect_all, SolanaRpc };
 use solana_client::{
     nonblocking::pubsub_client::PubsubClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
     rpc_config::{ RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter },
     rpc_response::RpcLogsResponse,
 };
-use std::time::Duration;
+use std::time::{ Duration, Instant };
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
-use tokio::time::sleep;
-use futures::stream::StreamExt;
+use chrono::Utc;
+use tokio::sync::Semaphore;
+use tokio::time::{ sleep, timeout };
+use futures::stream::{ select_all, StreamExt };
 use solana_transaction_status::UiTransactionEncoding;
 use std::str::FromStr;
+#[cfg(feature = "geyser")]
+use crate::geyser::{ GeyserSubscription, GeyserUpdate };
 
+/// Keeps the pool detection source alive, either logs subscriptions on the AMM program on
+/// every WebSocket endpoint or a Yellowstone gRPC transaction stream, reconnecting with
+/// exponential backoff whenever it ends or goes silent, and backfilling pools created during
+/// the gap.
 pub async fn listen_for_buys(
-    rpc_client: Arc<RpcClient>,
-    pub_subclient: PubsubClient,
-    program_address: &str,
-    sol_amount: f64
+    ctx: Arc<AppContext>,
+    ws_urls: &[String]
 ) -> Result<(), Box<dyn std::error::Error + 'static>> {
-    const MAX_RETRIES: usize = 3;
-    const INITIAL_RETRY_DELAY: u64 = 2;
-    const TOKEN_THRESHOLD: usize = 3;
-    const THRESHOLD_SLEEP_DURATION: u64 = 300; // 5 minutes
-
-    let (mut stream, _) = pub_subclient.logs_subscribe(
-        RpcTransactionLogsFilter::Mentions(vec![program_address.to_string()]),
-        RpcTransactionLogsConfig {
-            commitment: Some(CommitmentConfig::processed()),
+    let config = &ctx.config;
+    let listener = &config.listener;
+    if config.dedup.redis && !ctx.redis.is_configured() {
+        return Err("dedup.redis needs the REDIS_URL environment variable".into());
+    }
+    let mut seen_transactions = SignatureDedup::new(&config.dedup, &ctx.redis);
+    let workers = PoolWorkers::new(ctx.clone());
+    let mut reconnect_delay = listener.reconnect_initial_delay_secs;
+    // Unix time of the last notification, where the backfill picks up after a reconnect
+    let mut last_message_at: Option<i64> = None;
+
+    loop {
+        let result = match listener.source {
+            DetectionSource::Logs => {
+                listen_to_logs(
+                    ws_urls,
+                    &ctx,
+                    &workers,
+                    &mut seen_transactions,
+                    &mut last_message_at
+                ).await
+            }
+            DetectionSource::Geyser => {
+                listen_to_geyser(&ctx, &workers, &mut seen_transactions, &mut last_message_at).await
+            }
+        };
+        match result {
+            Ok(delivered) => {
+                if delivered {
+                    reconnect_delay = listener.reconnect_initial_delay_secs;
+                }
+            }
+            Err(err) => {
+                eprintln!("Pool subscription failed: {}", err);
+            }
+        }
+
+        println!("Reconnecting in {}s", reconnect_delay);
+        sleep(Duration::from_secs(reconnect_delay)).await;
+        reconnect_delay = (reconnect_delay * 2).min(listener.reconnect_max_delay_secs);
+    }
+}
+
+/// One connection of the logs source: connects to every WebSocket endpoint and runs the
+/// raced subscriptions, which backfill the gap since `last_message_at` once they are open.
+async fn listen_to_logs(
+    ws_urls: &[String],
+    ctx: &AppContext,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup,
+    last_message_at: &mut Option<i64>
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let pub_subclients = connect_all(ws_urls).await;
+    if pub_subclients.is_empty() {
+        return Err("Failed to connect to any WebSocket endpoint".into());
+    }
+
+    subscribe_to_pools(&pub_subclients, ctx, workers, seen_transactions, last_message_at).await
+}
+
+/// One connection of the gRPC source: subscribes to transactions mentioning the AMM program
+/// and hands every pool creation to the workers with the transaction attached, skipping the
+/// `getTransaction` round trip.
+#[cfg(feature = "geyser")]
+async fn listen_to_geyser(
+    ctx: &AppContext,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup,
+    last_message_at: &mut Option<i64>
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let config = &ctx.config;
+    let mut subscription = GeyserSubscription::subscribe(
+        &config.geyser,
+        watched_programs(config),
+        pool_detectors(config)
+    ).await.map_err(|err| format!("Failed to subscribe to {}: {}", config.geyser.endpoint, err))?;
+    backfill_after_reconnect(ctx, workers, seen_transactions, *last_message_at).await;
+
+    let heartbeat = Duration::from_secs(config.listener.heartbeat_timeout_secs);
+    let mut delivered = false;
+
+    loop {
+        let update = match timeout(heartbeat, subscription.next()).await {
+            Ok(Some(Ok(update))) => update,
+            Ok(Some(Err(err))) => {
+                eprintln!("gRPC stream failed: {}", err);
+                break;
+            }
+            Ok(None) => {
+                println!("gRPC stream ended");
+                break;
+            }
+            Err(_) => {
+                eprintln!("No gRPC updates for {}s, stream stalled", heartbeat.as_secs());
+                break;
+            }
+        };
+        delivered = true;
+        *last_message_at = Some(Utc::now().timestamp());
+
+        match update {
+            GeyserUpdate::NewPool(pool) => {
+                if !seen_transactions.insert(&pool.signature).await {
+                    continue;
+                }
+                workers.dispatch_transaction(pool.signature, pool.transaction);
+            }
+            GeyserUpdate::Pump { signature, events } => {
+                handle_pump_events(signature, events, config, workers, seen_transactions).await;
+            }
+            GeyserUpdate::Other => {}
+        }
+    }
+
+    Ok(delivered)
+}
+
+#[cfg(not(feature = "geyser"))]
+async fn listen_to_geyser(
+    _ctx: &AppContext,
+    _workers: &PoolWorkers,
+    _seen_transactions: &mut SignatureDedup,
+    _last_message_at: &mut Option<i64>
+) -> Result<bool, Box<dyn std::error::Error>> {
+    Err("listener.source = \"geyser\" needs a build with --features geyser".into())
+}
+
+async fn backfill_after_reconnect(
+    ctx: &AppContext,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup,
+    last_message_at: Option<i64>
+) {
+    let Some(since) = last_message_at else {
+        return;
+    };
+    if
+        let Err(err) = backfill_missed_pools(
+            ctx.rpc_client.as_ref(),
+            &ctx.config,
+            workers,
+            seen_transactions,
+            since
+        ).await
+    {
+        eprintln!("Failed to backfill missed pools: {}", err);
+    }
+}
+
+/// Subscribes on every connected endpoint and races the streams, so whichever endpoint
+/// delivers a notification first wins and the de-duplication drops the rest. The backfill
+/// runs once the subscriptions are open, so pools created meanwhile queue up in the streams
+/// instead of falling between the two. Runs until all subscriptions end or none delivers
+/// anything within the heartbeat timeout. Returns whether any notification arrived.
+async fn subscribe_to_pools(
+    pub_subclients: &[PubsubClient],
+    ctx: &AppContext,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup,
+    last_message_at: &mut Option<i64>
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let config = ctx.config.as_ref();
+    let listener = &config.listener;
+    let detectors = pool_detectors(config);
+
+    let mut streams = Vec::new();
+    let mut unsubscribes = Vec::new();
+    // `mentions` takes a single address, so every watched program gets its own subscription
+    for program_id in watched_programs(config) {
+        for pub_subclient in pub_subclients {
+            let subscription = pub_subclient.logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![program_id.clone()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(CommitmentConfig::processed()),
+                }
+            ).await;
+            match subscription {
+                Ok((stream, unsubscribe)) => {
+                    streams.push(stream);
+                    unsubscribes.push(unsubscribe);
+                }
+                Err(err) => {
+                    eprintln!("Failed to subscribe to logs of {}: {}", program_id, err);
+                }
+            }
+        }
+    }
+    if streams.is_empty() {
+        return Err("No logs subscription succeeded".into());
+    }
+    let mut stream = select_all(streams);
+    backfill_after_reconnect(ctx, workers, seen_transactions, *last_message_at).await;
+
+    let heartbeat = Duration::from_secs(listener.heartbeat_timeout_secs);
+    let mut delivered = false;
+
+    loop {
+        let response = match timeout(heartbeat, stream.next()).await {
+            Ok(Some(response)) => response,
+            Ok(None) => {
+                println!("All logs subscriptions ended");
+                break;
+            }
+            Err(_) => {
+                eprintln!("No logs for {}s, subscription stalled", heartbeat.as_secs());
+                break;
+            }
+        };
+        delivered = true;
+        *last_message_at = Some(Utc::now().timestamp());
+
+        let logs: RpcLogsResponse = response.value;
+        if config.pump.enabled && logs.err.is_none() {
+            let events = pump_sdk::parse_events(&logs.logs);
+            if !events.is_empty() {
+                handle_pump_events(logs.signature, events, config, workers, seen_transactions).await;
+                continue;
+            }
+        }
+        if !is_pool_creation_log(&logs.logs, &detectors) {
+            continue;
+        }
+        if !seen_transactions.insert(&logs.signature).await {
+            continue;
+        }
+
+        workers.dispatch(logs.signature);
+    }
+
+    drop(stream);
+    for unsubscribe in unsubscribes {
+        unsubscribe().await;
+    }
+
+    Ok(delivered)
+}
+
+/// The programs of every enabled pool type, plus pump.fun when enabled.
+fn watched_programs(config: &Config) -> Vec<String> {
+    let mut programs: Vec<String> = config
+        .pool_programs()
+        .iter()
+        .map(|(_, program_id)| program_id.to_string())
+        .collect();
+    if config.pump.enabled {
+        programs.push(config.pump.program_id.clone());
+    }
+    programs
+}
+
+/// Hands new tokens to the workers when `pump.trigger` is launch. Completions are only
+/// announced, the Raydium pool pump.fun creates next goes through the pool detector.
+async fn handle_pump_events(
+    signature: String,
+    events: Vec<PumpEvent>,
+    config: &Config,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup
+) {
+    for event in events {
+        match event {
+            PumpEvent::Create(create) => {
+                if config.pump.trigger != PumpTrigger::Launch {
+                    continue;
+                }
+                if !seen_transactions.insert(&signature).await {
+                    continue;
+                }
+                println!("pump.fun token {} ({}) created", create.mint, create.symbol);
+                workers.dispatch_pump_launch(signature.clone(), create);
+            }
+            PumpEvent::Complete(complete) => {
+                println!(
+                    "pump.fun bonding curve of {} complete, migration to Raydium pending",
+                    complete.mint
+                );
+            }
+        }
+    }
+}
+
+/// Replays pool creations since `since` (unix seconds) that the subscription missed,
+/// oldest first, ignoring anything older than `backfill_max_age_secs`.
+async fn backfill_missed_pools(
+    rpc_client: &dyn SolanaRpc,
+    config: &Config,
+    workers: &PoolWorkers,
+    seen_transactions: &mut SignatureDedup,
+    since: i64
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = &config.listener;
+    let cutoff = since.max(Utc::now().timestamp() - (listener.backfill_max_age_secs as i64));
+
+    let statuses = rpc_client.get_signatures_for_address_with_config(
+        &Pubkey::from_str(&listener.backfill_address)?,
+        GetConfirmedSignaturesForAddress2Config {
+            limit: Some(listener.backfill_limit),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
         }
     ).await?;
 
-    let mut seen_transactions: Vec<String> = vec![];
+    // Newest first, so stop at the first one from before the gap
+    let missed: Vec<String> = statuses
+        .into_iter()
+        .take_while(|status| status.block_time.is_none_or(|block_time| block_time >= cutoff))
+        .filter(|status| status.err.is_none())
+        .map(|status| status.signature)
+        .collect();
+    println!("Backfilling {} pool creations since {}", missed.len(), cutoff);
+
+    for signature in missed.into_iter().rev() {
+        if seen_transactions.insert(&signature).await {
+            workers.dispatch(signature);
+        }
+    }
+
+    Ok(())
+}
+
+/// What the listener hands a worker.
+enum Detection {
+    /// A Raydium pool creation known by signature, fetched with `getTransaction`
+    Signature,
+    /// A Raydium pool creation delivered with its full transaction
+    Transaction(Box<EncodedConfirmedTransactionWithStatusMeta>),
+    /// A new pump.fun token, bought on its bonding curve
+    PumpLaunch(CreateEvent),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PoolStage {
+    /// Waiting for a free worker
+    Queued,
+    /// Fetching, rug checking and buying
+    Processing,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PoolState {
+    stage: PoolStage,
+    since: Instant,
+}
+
+/// Runs detected pools on their own tasks, at most `max_concurrent_pools` at a time, so a
+/// slow LP-burn check never holds up the listener.
+struct PoolWorkers {
+    ctx: Arc<AppContext>,
+    semaphore: Arc<Semaphore>,
+    in_flight: Arc<Mutex<HashMap<String, PoolState>>>,
+}
+
+impl PoolWorkers {
+    fn new(ctx: Arc<AppContext>) -> Self {
+        let semaphore = Arc::new(Semaphore::new(ctx.config.listener.max_concurrent_pools));
+        PoolWorkers {
+            ctx,
+            semaphore,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn dispatch(&self, tx_signature: String) {
+        self.spawn(tx_signature, Detection::Signature);
+    }
+
+    /// Like `dispatch`, for sources that already delivered the full transaction.
+    #[cfg_attr(not(feature = "geyser"), allow(dead_code))]
+    fn dispatch_transaction(
+        &self,
+        tx_signature: String,
+        tx: EncodedConfirmedTransactionWithStatusMeta
+    ) {
+        self.spawn(tx_signature, Detection::Transaction(Box::new(tx)));
+    }
+
+    fn dispatch_pump_launch(&self, tx_signature: String, event: CreateEvent) {
+        self.spawn(tx_signature, Detection::PumpLaunch(event));
+    }
+
+    fn spawn(&self, tx_signature: String, detection: Detection) {
+        let ctx = self.ctx.clone();
+        let semaphore = self.semaphore.clone();
+        let in_flight = self.in_flight.clone();
+
+        set_stage(&in_flight, &tx_signature, PoolStage::Queued);
+        if self.semaphore.available_permits() == 0 {
+            println!("All workers busy, {} queued behind {}", tx_signature, self.summary());
+        }
+
+        tokio::spawn(async move {
+            // Checked before queueing for a worker, skipped pools don't hold one up
+            match threshold_reached(&ctx).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    println!("Position limit reached, skipping {}", tx_signature);
+                    in_flight.lock().unwrap().remove(&tx_signature);
+                    return;
+                }
+                Err(err) => {
+                    eprintln!("Failed to count open positions for {}: {}", tx_signature, err);
+                    in_flight.lock().unwrap().remove(&tx_signature);
+                    return;
+                }
+            }
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            set_stage(&in_flight, &tx_signature, PoolStage::Processing);
+
+            if let Err(err) = handle_pool_signature(&tx_signature, detection, &ctx).await {
+                eprintln!("Failed to handle {}: {}", tx_signature, err);
+            }
+
+            in_flight.lock().unwrap().remove(&tx_signature);
+        });
+    }
+
+    /// One line overview of the pools currently in flight.
+    fn summary(&self) -> String {
+        let in_flight = self.in_flight.lock().unwrap();
+        in_flight
+            .iter()
+            .map(|(signature, state)| {
+                format!("{} {:?} {}s", signature, state.stage, state.since.elapsed().as_secs())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn set_stage(in_flight: &Mutex<HashMap<String, PoolState>>, tx_signature: &str, stage: PoolStage) {
+    in_flight.lock().unwrap().insert(tx_signature.to_string(), PoolState {
+        stage,
+        since: Instant::now(),
+    });
+}
+
+async fn handle_pool_signature(
+    tx_signature: &str,
+    detection: Detection,
+    ctx: &AppContext
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = &ctx.config.listener;
+    let mut retry_count = 0;
+    let mut retry_delay = listener.initial_retry_delay_secs;
+
+    let mut tx = match detection {
+        Detection::Signature => None,
+        Detection::Transaction(tx) => Some(*tx),
+        Detection::PumpLaunch(event) => {
+            if let Err(err) = check_for_pump_launch(&event, ctx).await {
+                eprintln!("Skipping pump.fun token {}: {}", event.mint, err);
+            }
+            return Ok(());
+        }
+    };
 
     loop {
-        match stream.next().await {
-            Some(response) => {
-                let logs: RpcLogsResponse = response.value;
-                let log_entries = &logs.logs;
-
-                if let Some(_found_entry) = find_log_entry("init_pc_amount", log_entries) {
-                    let tx_signature = logs.signature.clone();
-                    if seen_transactions.contains(&tx_signature) {
-                        continue;
-                    }
-                    seen_transactions.push(tx_signature.clone());
-
-                    let mut retry_count = 0;
-                    let mut retry_delay = INITIAL_RETRY_DELAY;
-
-                    loop {
-                        let mongo_handler = MongoHandler::new().await.expect(
-                            "Failed to create MongoHandler"
-                        );
-                        let tokens = mongo_handler.fetch_all_tokens("solsniper", "tokens").await?;
-                        if tokens.len() > TOKEN_THRESHOLD {
-                            sleep(Duration::from_secs(THRESHOLD_SLEEP_DURATION)).await;
-                            dbg!("Threshold reached, sleeping for 5 minutes");
-                            break; // Exit the retry loop
-                        }
-
-                        match try_get_transaction(&rpc_client, &tx_signature).await {
-                            Ok(tx) => {
-                                let _signature = check_for_new_pool(
-                                    tx,
-                                    &rpc_client,
-                                    sol_amount
-                                ).await;
-                                break; // Exit the retry loop if transaction successful
-                            }
-                            Err(err) => {
-                                retry_count += 1;
-
-                                if retry_count > MAX_RETRIES {
-                                    eprintln!("Failed to get transaction: {}", err);
-                                    break; // Exit the retry loop if max retries exceeded
-                                }
-
-                                // Retry with exponential backoff delay
-                                sleep(Duration::from_secs(retry_delay)).await;
-                                retry_delay *= 2;
-                            }
-                        }
-                    }
+        let fetched = match tx.take() {
+            Some(tx) => Ok(tx),
+            None => try_get_transaction(&ctx.rpc_client, tx_signature).await,
+        };
+        match fetched {
+            Ok(tx) => {
+                if let Err(err) = check_for_new_pool(tx, ctx).await {
+                    eprintln!("Skipping pool from {}: {}", tx_signature, err);
                 }
+                break; // Exit the retry loop if transaction successful
             }
-            None => {
-                println!("End of stream");
-                break; // Exit the loop when the stream ends
+            Err(err) => {
+                retry_count += 1;
+
+                if retry_count > listener.max_retries {
+                    eprintln!("Failed to get transaction: {}", err);
+                    break; // Exit the retry loop if max retries exceeded
+                }
+
+                // Retry with exponential backoff delay
+                sleep(Duration::from_secs(retry_delay)).await;
+                retry_delay *= 2;
             }
         }
     }
@@ -99,11 +523,17 @@ pub async fn listen_for_buys(
     Ok(())
 }
 
+/// Whether more than `token_threshold` positions are open, in which case new pools are dropped.
+async fn threshold_reached(ctx: &AppContext) -> Result<bool, Box<dyn std::error::Error>> {
+    let tokens = ctx.mongo.fetch_all_tokens("solsniper", "tokens").await?;
+    Ok(tokens.len() > ctx.config.listener.token_threshold)
+}
+
 async fn try_get_transaction(
-    rpc_client: &Arc<RpcClient>,
+    rpc_client: &Arc<dyn SolanaRpc>,
     tx_signature: &str
-) -> Result<EncodedConfirmedTransactionWithStatusMeta, Box<dyn std::error::Error>> {
-    let signature = Signature::from_str(&tx_signature)?;
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, Box<dyn std::error::Error + Send + Sync>> {
+    let signature = Signature::from_str(tx_signature)?;
     let config = RpcTransactionConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
         commitment: Some(CommitmentConfig::confirmed()),
@@ -112,6 +542,7 @@ async fn try_get_transaction(
     let tx: EncodedConfirmedTransactionWithStatusMeta = rpc_client.get_transaction_with_config(
         &signature,
         config
-    )?;
+    ).await?;
     Ok(tx)
 }
+
@@ -0,0 +1,925 @@
+use crate::utils::PoolType;
+use serde::{ Deserialize, Serialize };
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")] Read(String, std::io::Error),
+    #[error("Failed to parse config file: {0}")] Parse(#[from] toml::de::Error),
+    #[error("Invalid value for {0}: {1}")] InvalidValue(String, String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub listener: ListenerConfig,
+    pub buy: BuyConfig,
+    pub wallet: WalletConfig,
+    pub rugcheck: RugCheckConfig,
+    pub sell: SellConfig,
+    pub dedup: DedupConfig,
+    pub rpc: RpcConfig,
+    pub geyser: GeyserConfig,
+    pub pump: PumpConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListenerConfig {
+    /// Raydium AMM v4 program
+    pub program_id: String,
+    pub cpmm_program_id: String,
+    pub clmm_program_id: String,
+    pub meteora_dlmm_program_id: String,
+    pub meteora_dynamic_program_id: String,
+    pub orca_whirlpool_program_id: String,
+    /// Pool types detected and bought
+    pub pool_types: Vec<PoolType>,
+    pub source: DetectionSource,
+    pub max_retries: usize,
+    pub initial_retry_delay_secs: u64,
+    /// Pools detected while more positions than this are open are skipped
+    pub token_threshold: usize,
+    /// Pools processed at the same time, further pools queue for a free worker
+    pub max_concurrent_pools: usize,
+    /// Reconnect when the subscription delivers nothing for this long
+    pub heartbeat_timeout_secs: u64,
+    pub reconnect_initial_delay_secs: u64,
+    pub reconnect_max_delay_secs: u64,
+    /// Account touched only by pool creations, scanned to backfill pools missed while disconnected
+    pub backfill_address: String,
+    pub backfill_limit: usize,
+    /// Pools created longer ago than this are not worth buying after a reconnect
+    pub backfill_max_age_secs: u64,
+}
+
+/// Where new pools are detected.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionSource {
+    /// `logsSubscribe` on the WebSocket endpoints, then `getTransaction` for each new pool
+    Logs,
+    /// Full transactions streamed from a Yellowstone gRPC endpoint (`geyser` feature)
+    Geyser,
+}
+
+impl FromStr for DetectionSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logs" => Ok(DetectionSource::Logs),
+            "geyser" => Ok(DetectionSource::Geyser),
+            other => Err(format!("unknown detection source {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BuyConfig {
+    pub amount_sol: f64,
+    pub slippage_bps: u64,
+    pub executor: Executor,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    pub wrap_sol: bool,
+    /// How long native buys and sells wait for their transaction to be confirmed
+    pub confirm_timeout_secs: u64,
+    /// Pools that open for trading within this are waited for, later ones are skipped
+    pub max_open_wait_secs: u64,
+}
+
+/// Where buy orders go once a pool passes the rug checks.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Executor {
+    /// Publish a `BuyTransaction` on the Redis "trading" channel for an external executor
+    Redis,
+    /// Build, sign and send the Raydium swap from this process
+    Native,
+}
+
+impl FromStr for Executor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "redis" => Ok(Executor::Redis),
+            "native" => Ok(Executor::Native),
+            other => Err(format!("unknown executor {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WalletConfig {
+    pub keypair_path: String,
+    pub min_sol_for_fees: f64,
+    pub balance_check_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RugCheckConfig {
+    pub min_lp_burn_pct: f64,
+    pub min_liquidity_usd: f64,
+    /// Deadline for the LP burn, and for the liquidity to be added
+    pub lp_burn_timeout_secs: u64,
+    /// Polling interval, also re-reads the LP mint next to the subscription
+    pub lp_burn_retry_secs: u64,
+    /// Watch the LP mint with accountSubscribe on every WebSocket endpoint and re-check as
+    /// soon as its supply changes
+    pub lp_burn_subscribe: bool,
+    pub policy: RugPolicy,
+    /// Highest weighted risk, from 0 to 1, the weighted policy still buys at
+    pub max_risk_score: f64,
+    /// Rules run in this order, see `rug_rules`
+    pub rules: Vec<RugRuleConfig>,
+    /// How many of the largest holders count towards `max_top_holders_pct`, at most 20
+    pub top_holders: usize,
+    pub max_top_holders_pct: f64,
+    pub max_single_holder_pct: f64,
+    /// Owner wallets left out of the holder limits on top of the pool's own vaults, like AMM
+    /// authorities and the incinerator
+    pub excluded_holders: Vec<String>,
+    /// Earlier pools in the verdict history from which a creator counts as a serial launcher
+    pub max_creator_pools: u64,
+    /// Count LP held by `lp_lockers` towards `min_lp_burn_pct`, for locks that last at least
+    /// `min_lp_lock_secs`
+    pub accept_locked_lp: bool,
+    /// Locks with an unknown unlock time only pass while this is 0
+    pub min_lp_lock_secs: u64,
+    pub lp_lockers: Vec<LpLockerConfig>,
+    /// Fetch the JSON document behind the metadata URI, needed by the `socials` rule
+    pub fetch_metadata_uri: bool,
+    pub metadata_timeout_secs: u64,
+}
+
+impl RugCheckConfig {
+    /// Whether a configured rule reads the token metadata, which costs a lookup per pool.
+    pub fn needs_metadata(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| matches!(rule.rule, RugRuleKind::Metadata | RugRuleKind::Socials))
+    }
+}
+
+/// A program that holds LP tokens until an unlock time. LP counts as locked when the owner
+/// of its token account is an account of `program_id`, or one of `authorities` for lockers
+/// that keep every lock under the same PDA.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LpLockerConfig {
+    pub name: String,
+    pub program_id: String,
+    #[serde(default)]
+    pub authorities: Vec<String>,
+    /// Offset of the little-endian unix unlock time in the lock account, when known
+    #[serde(default)]
+    pub unlock_time_offset: Option<usize>,
+    /// Locks of this program never unlock
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+/// How the rule findings of a pool combine into a verdict.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RugPolicy {
+    /// Any rule crossing its limit rejects the pool, the remaining rules are skipped
+    Strict,
+    /// Rejects once the weight-averaged risk of all rules exceeds `max_risk_score`
+    Weighted,
+}
+
+impl FromStr for RugPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(RugPolicy::Strict),
+            "weighted" => Ok(RugPolicy::Weighted),
+            other => Err(format!("unknown rug policy {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RugRuleKind {
+    /// Mint or freeze authority, and dangerous Token-2022 extensions
+    Authorities,
+    /// Share of the LP burnt, `min_lp_burn_pct`
+    LpBurn,
+    /// USD value in the pool, `min_liquidity_usd`
+    Liquidity,
+    /// Supply held by the largest wallets, `max_top_holders_pct` and `max_single_holder_pct`
+    Holders,
+    /// Metaplex or Token-2022 metadata that can still be changed, worst while the pool
+    /// creator holds the update authority
+    Metadata,
+    /// Off-chain metadata without a twitter, telegram or website
+    Socials,
+    /// Creators with `max_creator_pools` earlier pools
+    CreatorHistory,
+}
+
+impl FromStr for RugRuleKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "authorities" => Ok(RugRuleKind::Authorities),
+            "lp_burn" => Ok(RugRuleKind::LpBurn),
+            "liquidity" => Ok(RugRuleKind::Liquidity),
+            "holders" => Ok(RugRuleKind::Holders),
+            "metadata" => Ok(RugRuleKind::Metadata),
+            "socials" => Ok(RugRuleKind::Socials),
+            "creator_history" => Ok(RugRuleKind::CreatorHistory),
+            other => Err(format!("unknown rug rule {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RugRuleConfig {
+    pub rule: RugRuleKind,
+    /// Share of this rule in the weighted score
+    #[serde(default = "default_rule_weight")]
+    pub weight: f64,
+}
+
+fn default_rule_weight() -> f64 {
+    1.0
+}
+
+impl RugRuleConfig {
+    fn new(rule: RugRuleKind) -> Self {
+        RugRuleConfig { rule, weight: default_rule_weight() }
+    }
+}
+
+/// `rule` or `rule:weight`, for `SNIPER_RUG_RULES`.
+impl FromStr for RugRuleConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((rule, weight)) =>
+                Ok(RugRuleConfig {
+                    rule: rule.parse()?,
+                    weight: weight
+                        .parse()
+                        .map_err(|_| format!("invalid weight {} for rug rule {}", weight, rule))?,
+                }),
+            None => Ok(RugRuleConfig::new(s.parse()?)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SellConfig {
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    pub slippage_bps: u64,
+    /// Sell everything once the price drops this many percent below the entry price
+    pub stop_loss_pct: f64,
+    /// Sell everything once the price drops this many percent below its peak, 0 disables it
+    pub trailing_stop_pct: f64,
+    /// Sell everything once the position is older than this, 0 disables it
+    pub max_hold_secs: u64,
+    /// Levels in ascending `gain_pct` order
+    pub take_profit: Vec<TakeProfitLevel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TakeProfitLevel {
+    /// Gain over the entry price, in percent, that triggers this level
+    pub gain_pct: f64,
+    /// Share of the original position to sell at this level, in percent
+    pub sell_pct: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DedupConfig {
+    /// Most recent signatures remembered in memory
+    pub capacity: usize,
+    /// How long a signature counts as seen
+    pub ttl_secs: u64,
+    /// Share seen signatures through Redis (`REDIS_URL`) so restarts and replicas skip them too
+    pub redis: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    /// HTTP endpoints in priority order, empty falls back to the comma separated `RPC_URL`
+    pub http_urls: Vec<String>,
+    /// WebSocket endpoints subscribed in parallel, empty falls back to the comma separated
+    /// `WSS_URL`
+    pub ws_urls: Vec<String>,
+    /// Head start the fastest endpoint gets before a hedged read also asks the next one
+    pub hedge_delay_ms: u64,
+    /// Endpoints a hedged read may fan out to
+    pub hedge_fanout: usize,
+    /// How long an endpoint is skipped after a transport error
+    pub failure_cooldown_secs: u64,
+    /// How long an endpoint is skipped after answering 429 Too Many Requests
+    pub rate_limit_cooldown_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GeyserConfig {
+    pub endpoint: String,
+    /// Sent as the `x-token` header, empty sends none
+    pub x_token: String,
+    pub commitment: GeyserCommitment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeyserCommitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl FromStr for GeyserCommitment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "processed" => Ok(GeyserCommitment::Processed),
+            "confirmed" => Ok(GeyserCommitment::Confirmed),
+            "finalized" => Ok(GeyserCommitment::Finalized),
+            other => Err(format!("unknown commitment {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PumpConfig {
+    /// Also watch the pump.fun program next to the Raydium AMM
+    pub enabled: bool,
+    pub program_id: String,
+    /// Signer of the Raydium `initialize2` that pump.fun sends when a bonding curve completes
+    pub migration_authority: String,
+    pub trigger: PumpTrigger,
+}
+
+/// When a pump.fun token is bought.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PumpTrigger {
+    /// On the bonding curve, as soon as the token is created
+    Launch,
+    /// On Raydium, once the completed curve migrates; other Raydium pools are skipped
+    Migration,
+}
+
+impl FromStr for PumpTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "launch" => Ok(PumpTrigger::Launch),
+            "migration" => Ok(PumpTrigger::Migration),
+            other => Err(format!("unknown pump trigger {}", other)),
+        }
+    }
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        ListenerConfig {
+            program_id: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string(), // RAYDIUM_PUBLIC_KEY
+            cpmm_program_id: "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C".to_string(),
+            clmm_program_id: "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK".to_string(),
+            meteora_dlmm_program_id: "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo".to_string(),
+            meteora_dynamic_program_id: "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB".to_string(),
+            orca_whirlpool_program_id: "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(),
+            pool_types: vec![PoolType::AmmV4],
+            source: DetectionSource::Logs,
+            max_retries: 3,
+            initial_retry_delay_secs: 2,
+            token_threshold: 3,
+            max_concurrent_pools: 8,
+            heartbeat_timeout_secs: 30,
+            reconnect_initial_delay_secs: 1,
+            reconnect_max_delay_secs: 60,
+            // Raydium pool creation fee account
+            backfill_address: "7YttLkHDoNj9wyDur5pM1ejNaAvT9X4eqaYcHQqtj2G5".to_string(),
+            backfill_limit: 100,
+            backfill_max_age_secs: 120,
+        }
+    }
+}
+
+impl Default for BuyConfig {
+    fn default() -> Self {
+        BuyConfig {
+            amount_sol: 0.024,
+            slippage_bps: 500, // 5%
+            executor: Executor::Redis,
+            compute_unit_limit: 200_000,
+            compute_unit_price: 100_000, // micro lamports
+            wrap_sol: false,
+            confirm_timeout_secs: 60,
+            max_open_wait_secs: 300,
+        }
+    }
+}
+
+impl Default for WalletConfig {
+    fn default() -> Self {
+        WalletConfig {
+            keypair_path: String::new(), // empty falls back to the PRIVATE_KEY env var
+            min_sol_for_fees: 0.01,
+            balance_check_interval_secs: 3600, // 1 hour
+        }
+    }
+}
+
+impl Default for RugCheckConfig {
+    fn default() -> Self {
+        RugCheckConfig {
+            min_lp_burn_pct: 80.0,
+            min_liquidity_usd: 1000.0,
+            lp_burn_timeout_secs: 220,
+            lp_burn_retry_secs: 15,
+            lp_burn_subscribe: true,
+            policy: RugPolicy::Strict,
+            max_risk_score: 0.5,
+            rules: vec![
+                RugRuleConfig::new(RugRuleKind::Authorities),
+                RugRuleConfig::new(RugRuleKind::LpBurn),
+                RugRuleConfig::new(RugRuleKind::Liquidity),
+                RugRuleConfig::new(RugRuleKind::Holders)
+            ],
+            top_holders: 10,
+            max_top_holders_pct: 50.0,
+            max_single_holder_pct: 20.0,
+            excluded_holders: vec![
+                // Raydium AMM v4 authority
+                "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1".to_string(),
+                // Raydium CPMM authority
+                "GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL".to_string(),
+                "1nc1nerator11111111111111111111111111111111".to_string()
+            ],
+            max_creator_pools: 3,
+            accept_locked_lp: true,
+            min_lp_lock_secs: 0,
+            lp_lockers: vec![
+                LpLockerConfig {
+                    name: "streamflow".to_string(),
+                    program_id: "strmRqUCoQUgGUan5YhzUZa6KqdzwX5L6FpUxfmKg5m".to_string(),
+                    authorities: Vec::new(),
+                    unlock_time_offset: None,
+                    permanent: false,
+                },
+                LpLockerConfig {
+                    name: "raydium_burn_and_earn".to_string(),
+                    program_id: "LockrWmn6K5twhz3y9w1dQERbmgSaRkfnTeTKbpofwE".to_string(),
+                    authorities: Vec::new(),
+                    unlock_time_offset: None,
+                    permanent: true,
+                }
+            ],
+            fetch_metadata_uri: true,
+            metadata_timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for SellConfig {
+    fn default() -> Self {
+        SellConfig {
+            enabled: false,
+            poll_interval_secs: 5,
+            slippage_bps: 1000, // 10%
+            stop_loss_pct: 30.0,
+            trailing_stop_pct: 0.0,
+            max_hold_secs: 0,
+            take_profit: vec![
+                TakeProfitLevel { gain_pct: 50.0, sell_pct: 50.0 },
+                TakeProfitLevel { gain_pct: 100.0, sell_pct: 50.0 }
+            ],
+        }
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            capacity: 10_000,
+            ttl_secs: 3600, // 1 hour
+            redis: false,
+        }
+    }
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        RpcConfig {
+            http_urls: Vec::new(),
+            ws_urls: Vec::new(),
+            hedge_delay_ms: 150,
+            hedge_fanout: 2,
+            failure_cooldown_secs: 10,
+            rate_limit_cooldown_secs: 30,
+        }
+    }
+}
+
+impl Default for GeyserConfig {
+    fn default() -> Self {
+        GeyserConfig {
+            endpoint: "http://127.0.0.1:10000".to_string(),
+            x_token: String::new(),
+            commitment: GeyserCommitment::Processed,
+        }
+    }
+}
+
+impl Default for PumpConfig {
+    fn default() -> Self {
+        PumpConfig {
+            enabled: false,
+            program_id: "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P".to_string(),
+            migration_authority: "39azUYFWPz3VHgKCf3VChUwbpURdCHRxjWVowf5jUJjg".to_string(),
+            trigger: PumpTrigger::Launch,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from `CONFIG_PATH` (default `config.toml`), falling back to the
+    /// built-in defaults when the file does not exist, then applies `SNIPER_*` env overrides.
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str::<Config>(&contents)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(err) => {
+                return Err(ConfigError::Read(path, err));
+            }
+        };
+
+        config.apply_env_overrides()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        override_from_env("SNIPER_PROGRAM_ID", &mut self.listener.program_id)?;
+        override_from_env("SNIPER_CPMM_PROGRAM_ID", &mut self.listener.cpmm_program_id)?;
+        override_from_env("SNIPER_CLMM_PROGRAM_ID", &mut self.listener.clmm_program_id)?;
+        override_from_env(
+            "SNIPER_METEORA_DLMM_PROGRAM_ID",
+            &mut self.listener.meteora_dlmm_program_id
+        )?;
+        override_from_env(
+            "SNIPER_METEORA_DYNAMIC_PROGRAM_ID",
+            &mut self.listener.meteora_dynamic_program_id
+        )?;
+        override_from_env(
+            "SNIPER_ORCA_WHIRLPOOL_PROGRAM_ID",
+            &mut self.listener.orca_whirlpool_program_id
+        )?;
+        override_list_from_env("SNIPER_POOL_TYPES", &mut self.listener.pool_types)?;
+        override_from_env("SNIPER_DETECTION_SOURCE", &mut self.listener.source)?;
+        override_from_env("SNIPER_MAX_RETRIES", &mut self.listener.max_retries)?;
+        override_from_env(
+            "SNIPER_INITIAL_RETRY_DELAY_SECS",
+            &mut self.listener.initial_retry_delay_secs
+        )?;
+        override_from_env("SNIPER_TOKEN_THRESHOLD", &mut self.listener.token_threshold)?;
+        override_from_env("SNIPER_MAX_CONCURRENT_POOLS", &mut self.listener.max_concurrent_pools)?;
+        override_from_env(
+            "SNIPER_HEARTBEAT_TIMEOUT_SECS",
+            &mut self.listener.heartbeat_timeout_secs
+        )?;
+        override_from_env(
+            "SNIPER_RECONNECT_INITIAL_DELAY_SECS",
+            &mut self.listener.reconnect_initial_delay_secs
+        )?;
+        override_from_env(
+            "SNIPER_RECONNECT_MAX_DELAY_SECS",
+            &mut self.listener.reconnect_max_delay_secs
+        )?;
+        override_from_env("SNIPER_BACKFILL_ADDRESS", &mut self.listener.backfill_address)?;
+        override_from_env("SNIPER_BACKFILL_LIMIT", &mut self.listener.backfill_limit)?;
+        override_from_env(
+            "SNIPER_BACKFILL_MAX_AGE_SECS",
+            &mut self.listener.backfill_max_age_secs
+        )?;
+        override_from_env("SNIPER_BUY_AMOUNT_SOL", &mut self.buy.amount_sol)?;
+        override_from_env("SNIPER_SLIPPAGE_BPS", &mut self.buy.slippage_bps)?;
+        override_from_env("SNIPER_EXECUTOR", &mut self.buy.executor)?;
+        override_from_env("SNIPER_COMPUTE_UNIT_LIMIT", &mut self.buy.compute_unit_limit)?;
+        override_from_env("SNIPER_COMPUTE_UNIT_PRICE", &mut self.buy.compute_unit_price)?;
+        override_from_env("SNIPER_WRAP_SOL", &mut self.buy.wrap_sol)?;
+        override_from_env("SNIPER_CONFIRM_TIMEOUT_SECS", &mut self.buy.confirm_timeout_secs)?;
+        override_from_env("SNIPER_MAX_OPEN_WAIT_SECS", &mut self.buy.max_open_wait_secs)?;
+        override_from_env("SNIPER_KEYPAIR_PATH", &mut self.wallet.keypair_path)?;
+        override_from_env("SNIPER_MIN_SOL_FOR_FEES", &mut self.wallet.min_sol_for_fees)?;
+        override_from_env(
+            "SNIPER_BALANCE_CHECK_INTERVAL_SECS",
+            &mut self.wallet.balance_check_interval_secs
+        )?;
+        override_from_env("SNIPER_MIN_LP_BURN_PCT", &mut self.rugcheck.min_lp_burn_pct)?;
+        override_from_env("SNIPER_MIN_LIQUIDITY_USD", &mut self.rugcheck.min_liquidity_usd)?;
+        override_from_env("SNIPER_LP_BURN_TIMEOUT_SECS", &mut self.rugcheck.lp_burn_timeout_secs)?;
+        override_from_env("SNIPER_LP_BURN_RETRY_SECS", &mut self.rugcheck.lp_burn_retry_secs)?;
+        override_from_env("SNIPER_LP_BURN_SUBSCRIBE", &mut self.rugcheck.lp_burn_subscribe)?;
+        override_from_env("SNIPER_RUG_POLICY", &mut self.rugcheck.policy)?;
+        override_from_env("SNIPER_MAX_RISK_SCORE", &mut self.rugcheck.max_risk_score)?;
+        override_list_from_env("SNIPER_RUG_RULES", &mut self.rugcheck.rules)?;
+        override_from_env("SNIPER_TOP_HOLDERS", &mut self.rugcheck.top_holders)?;
+        override_from_env("SNIPER_MAX_TOP_HOLDERS_PCT", &mut self.rugcheck.max_top_holders_pct)?;
+        override_from_env(
+            "SNIPER_MAX_SINGLE_HOLDER_PCT",
+            &mut self.rugcheck.max_single_holder_pct
+        )?;
+        override_list_from_env("SNIPER_EXCLUDED_HOLDERS", &mut self.rugcheck.excluded_holders)?;
+        override_from_env("SNIPER_MAX_CREATOR_POOLS", &mut self.rugcheck.max_creator_pools)?;
+        override_from_env("SNIPER_ACCEPT_LOCKED_LP", &mut self.rugcheck.accept_locked_lp)?;
+        override_from_env("SNIPER_MIN_LP_LOCK_SECS", &mut self.rugcheck.min_lp_lock_secs)?;
+        override_from_env("SNIPER_FETCH_METADATA_URI", &mut self.rugcheck.fetch_metadata_uri)?;
+        override_from_env(
+            "SNIPER_METADATA_TIMEOUT_SECS",
+            &mut self.rugcheck.metadata_timeout_secs
+        )?;
+        override_from_env("SNIPER_SELL_ENABLED", &mut self.sell.enabled)?;
+        override_from_env("SNIPER_SELL_POLL_INTERVAL_SECS", &mut self.sell.poll_interval_secs)?;
+        override_from_env("SNIPER_SELL_SLIPPAGE_BPS", &mut self.sell.slippage_bps)?;
+        override_from_env("SNIPER_STOP_LOSS_PCT", &mut self.sell.stop_loss_pct)?;
+        override_from_env("SNIPER_TRAILING_STOP_PCT", &mut self.sell.trailing_stop_pct)?;
+        override_from_env("SNIPER_MAX_HOLD_SECS", &mut self.sell.max_hold_secs)?;
+        override_from_env("SNIPER_DEDUP_CAPACITY", &mut self.dedup.capacity)?;
+        override_from_env("SNIPER_DEDUP_TTL_SECS", &mut self.dedup.ttl_secs)?;
+        override_from_env("SNIPER_DEDUP_REDIS", &mut self.dedup.redis)?;
+        override_from_env("SNIPER_GEYSER_ENDPOINT", &mut self.geyser.endpoint)?;
+        override_from_env("SNIPER_GEYSER_X_TOKEN", &mut self.geyser.x_token)?;
+        override_from_env("SNIPER_GEYSER_COMMITMENT", &mut self.geyser.commitment)?;
+        override_from_env("SNIPER_PUMP_ENABLED", &mut self.pump.enabled)?;
+        override_from_env("SNIPER_PUMP_PROGRAM_ID", &mut self.pump.program_id)?;
+        override_from_env("SNIPER_PUMP_MIGRATION_AUTHORITY", &mut self.pump.migration_authority)?;
+        override_from_env("SNIPER_PUMP_TRIGGER", &mut self.pump.trigger)?;
+        override_from_env("SNIPER_HEDGE_DELAY_MS", &mut self.rpc.hedge_delay_ms)?;
+        override_from_env("SNIPER_HEDGE_FANOUT", &mut self.rpc.hedge_fanout)?;
+        override_from_env("SNIPER_FAILURE_COOLDOWN_SECS", &mut self.rpc.failure_cooldown_secs)?;
+        override_from_env(
+            "SNIPER_RATE_LIMIT_COOLDOWN_SECS",
+            &mut self.rpc.rate_limit_cooldown_secs
+        )?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if Pubkey::from_str(&self.listener.program_id).is_err() {
+            return Err(invalid("listener.program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.listener.cpmm_program_id).is_err() {
+            return Err(invalid("listener.cpmm_program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.listener.clmm_program_id).is_err() {
+            return Err(invalid("listener.clmm_program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.listener.meteora_dlmm_program_id).is_err() {
+            return Err(invalid("listener.meteora_dlmm_program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.listener.meteora_dynamic_program_id).is_err() {
+            return Err(invalid("listener.meteora_dynamic_program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.listener.orca_whirlpool_program_id).is_err() {
+            return Err(invalid("listener.orca_whirlpool_program_id", "not a valid pubkey"));
+        }
+        if self.listener.pool_types.is_empty() {
+            return Err(invalid("listener.pool_types", "must name at least one pool type"));
+        }
+        if Pubkey::from_str(&self.listener.backfill_address).is_err() {
+            return Err(invalid("listener.backfill_address", "not a valid pubkey"));
+        }
+        if self.listener.max_concurrent_pools == 0 {
+            return Err(invalid("listener.max_concurrent_pools", "must be greater than 0"));
+        }
+        if self.listener.heartbeat_timeout_secs == 0 {
+            return Err(invalid("listener.heartbeat_timeout_secs", "must be greater than 0"));
+        }
+        if self.listener.reconnect_initial_delay_secs == 0 {
+            return Err(invalid("listener.reconnect_initial_delay_secs", "must be greater than 0"));
+        }
+        if self.listener.reconnect_max_delay_secs < self.listener.reconnect_initial_delay_secs {
+            return Err(
+                invalid("listener.reconnect_max_delay_secs", "must be at least the initial delay")
+            );
+        }
+        if self.wallet.min_sol_for_fees < 0.0 {
+            return Err(invalid("wallet.min_sol_for_fees", "must not be negative"));
+        }
+        if self.buy.amount_sol <= 0.0 {
+            return Err(invalid("buy.amount_sol", "must be greater than 0"));
+        }
+        if self.buy.slippage_bps > 10_000 {
+            return Err(invalid("buy.slippage_bps", "must be at most 10000"));
+        }
+        if self.buy.confirm_timeout_secs == 0 {
+            return Err(invalid("buy.confirm_timeout_secs", "must be greater than 0"));
+        }
+        if !(0.0..=100.0).contains(&self.rugcheck.min_lp_burn_pct) {
+            return Err(invalid("rugcheck.min_lp_burn_pct", "must be between 0 and 100"));
+        }
+        if self.rugcheck.min_liquidity_usd < 0.0 {
+            return Err(invalid("rugcheck.min_liquidity_usd", "must not be negative"));
+        }
+        if self.rugcheck.lp_burn_retry_secs == 0 {
+            return Err(invalid("rugcheck.lp_burn_retry_secs", "must be greater than 0"));
+        }
+        if !(0.0..=1.0).contains(&self.rugcheck.max_risk_score) {
+            return Err(invalid("rugcheck.max_risk_score", "must be between 0 and 1"));
+        }
+        if self.rugcheck.rules.is_empty() {
+            return Err(invalid("rugcheck.rules", "must name at least one rule"));
+        }
+        if self.rugcheck.rules.iter().any(|rule| rule.weight < 0.0) {
+            return Err(invalid("rugcheck.rules", "weights must not be negative"));
+        }
+        if self.rugcheck.max_creator_pools == 0 {
+            return Err(invalid("rugcheck.max_creator_pools", "must be greater than 0"));
+        }
+        for locker in &self.rugcheck.lp_lockers {
+            let field = format!("rugcheck.lp_lockers.{}", locker.name);
+            if Pubkey::from_str(&locker.program_id).is_err() {
+                return Err(invalid(&field, "program_id is not a valid pubkey"));
+            }
+            if locker.authorities.iter().any(|authority| Pubkey::from_str(authority).is_err()) {
+                return Err(invalid(&field, "authorities must be valid pubkeys"));
+            }
+        }
+        if self.rugcheck.metadata_timeout_secs == 0 {
+            return Err(invalid("rugcheck.metadata_timeout_secs", "must be greater than 0"));
+        }
+        if
+            !self.rugcheck.fetch_metadata_uri &&
+            self.rugcheck.rules.iter().any(|rule| rule.rule == RugRuleKind::Socials)
+        {
+            return Err(
+                invalid("rugcheck.fetch_metadata_uri", "must be enabled for the socials rule")
+            );
+        }
+        if !(1..=20).contains(&self.rugcheck.top_holders) {
+            return Err(invalid("rugcheck.top_holders", "must be between 1 and 20"));
+        }
+        if !(0.0..=100.0).contains(&self.rugcheck.max_top_holders_pct) {
+            return Err(invalid("rugcheck.max_top_holders_pct", "must be between 0 and 100"));
+        }
+        if !(0.0..=100.0).contains(&self.rugcheck.max_single_holder_pct) {
+            return Err(invalid("rugcheck.max_single_holder_pct", "must be between 0 and 100"));
+        }
+        if self.rugcheck.excluded_holders.iter().any(|owner| Pubkey::from_str(owner).is_err()) {
+            return Err(invalid("rugcheck.excluded_holders", "not a valid pubkey"));
+        }
+        if self.listener.initial_retry_delay_secs == 0 {
+            return Err(invalid("listener.initial_retry_delay_secs", "must be greater than 0"));
+        }
+        if self.sell.poll_interval_secs == 0 {
+            return Err(invalid("sell.poll_interval_secs", "must be greater than 0"));
+        }
+        if self.sell.slippage_bps > 10_000 {
+            return Err(invalid("sell.slippage_bps", "must be at most 10000"));
+        }
+        // 0 would sell every position that opens flat
+        if self.sell.stop_loss_pct <= 0.0 || self.sell.stop_loss_pct > 100.0 {
+            return Err(invalid("sell.stop_loss_pct", "must be greater than 0 and at most 100"));
+        }
+        if !(0.0..100.0).contains(&self.sell.trailing_stop_pct) {
+            return Err(invalid("sell.trailing_stop_pct", "must be between 0 and 100"));
+        }
+        for level in &self.sell.take_profit {
+            if level.gain_pct <= 0.0 {
+                return Err(invalid("sell.take_profit.gain_pct", "must be greater than 0"));
+            }
+            if level.sell_pct <= 0.0 || level.sell_pct > 100.0 {
+                return Err(invalid("sell.take_profit.sell_pct", "must be between 0 and 100"));
+            }
+        }
+        // `evaluate_position` finds the highest level reached by walking the ladder in order
+        if self.sell.take_profit.windows(2).any(|levels| levels[1].gain_pct <= levels[0].gain_pct) {
+            return Err(invalid("sell.take_profit", "gain_pct levels must be in ascending order"));
+        }
+        if self.sell.take_profit.iter().map(|level| level.sell_pct).sum::<f64>() > 100.0 {
+            return Err(invalid("sell.take_profit", "sell_pct levels add up to more than 100"));
+        }
+        if self.listener.source == DetectionSource::Geyser && !cfg!(feature = "geyser") {
+            return Err(invalid("listener.source", "geyser needs a build with --features geyser"));
+        }
+        if self.listener.source == DetectionSource::Geyser && self.geyser.endpoint.is_empty() {
+            return Err(invalid("geyser.endpoint", "must be set when listener.source is geyser"));
+        }
+        if Pubkey::from_str(&self.pump.program_id).is_err() {
+            return Err(invalid("pump.program_id", "not a valid pubkey"));
+        }
+        if Pubkey::from_str(&self.pump.migration_authority).is_err() {
+            return Err(invalid("pump.migration_authority", "not a valid pubkey"));
+        }
+        if self.rpc.hedge_fanout == 0 {
+            return Err(invalid("rpc.hedge_fanout", "must be greater than 0"));
+        }
+        if self.dedup.capacity == 0 {
+            return Err(invalid("dedup.capacity", "must be greater than 0"));
+        }
+        if self.dedup.ttl_secs == 0 {
+            return Err(invalid("dedup.ttl_secs", "must be greater than 0"));
+        }
+        Ok(())
+    }
+
+    /// The program of every enabled pool type.
+    pub fn pool_programs(&self) -> Vec<(PoolType, Pubkey)> {
+        self.listener.pool_types
+            .iter()
+            .map(|pool_type| {
+                let program_id = match pool_type {
+                    PoolType::AmmV4 => &self.listener.program_id,
+                    PoolType::Cpmm => &self.listener.cpmm_program_id,
+                    PoolType::Clmm => &self.listener.clmm_program_id,
+                    PoolType::MeteoraDlmm => &self.listener.meteora_dlmm_program_id,
+                    PoolType::MeteoraDynamic => &self.listener.meteora_dynamic_program_id,
+                    PoolType::OrcaWhirlpool => &self.listener.orca_whirlpool_program_id,
+                };
+                (*pool_type, Pubkey::from_str(program_id).unwrap())
+            })
+            .collect()
+    }
+
+    pub fn pump_program_id(&self) -> Pubkey {
+        Pubkey::from_str(&self.pump.program_id).unwrap()
+    }
+
+    /// `rpc.http_urls`, or the comma separated `RPC_URL` when none are configured.
+    pub fn http_urls(&self) -> Result<Vec<String>, ConfigError> {
+        endpoints_or_env(&self.rpc.http_urls, "RPC_URL")
+    }
+
+    /// `rpc.ws_urls`, or the comma separated `WSS_URL` when none are configured.
+    pub fn ws_urls(&self) -> Result<Vec<String>, ConfigError> {
+        endpoints_or_env(&self.rpc.ws_urls, "WSS_URL")
+    }
+}
+
+fn override_from_env<T: FromStr>(name: &str, target: &mut T) -> Result<(), ConfigError>
+    where T::Err: std::fmt::Display
+{
+    if let Ok(value) = std::env::var(name) {
+        *target = value.parse().map_err(|e: T::Err| invalid(name, &e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Like `override_from_env` for a comma separated list.
+fn override_list_from_env<T: FromStr>(name: &str, target: &mut Vec<T>) -> Result<(), ConfigError>
+    where T::Err: std::fmt::Display
+{
+    if let Ok(value) = std::env::var(name) {
+        *target = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().map_err(|e: T::Err| invalid(name, &e.to_string())))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(())
+}
+
+fn endpoints_or_env(configured: &[String], env_name: &str) -> Result<Vec<String>, ConfigError> {
+    if !configured.is_empty() {
+        return Ok(configured.to_vec());
+    }
+
+    let urls: Vec<String> = std::env
+        ::var(env_name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .collect();
+    if urls.is_empty() {
+        return Err(invalid(env_name, "set it or configure the rpc endpoint list"));
+    }
+    Ok(urls)
+}
+
+fn invalid(field: &str, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue(field.to_string(), reason.to_string())
+}
@@ -0,0 +1,129 @@
+use crate::config::Config;
+use crate::metadata::{ HttpMetadataFetcher, MetadataFetcher };
+use crate::mongo::MongoHandler;
+use crate::redis::RedisHandler;
+use crate::rpc::SolanaRpc;
+use futures::future::join_all;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_sdk::signature::Keypair;
+use std::error::Error;
+use std::sync::{ Arc, RwLock };
+use std::time::Duration;
+
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+// A live connection answers a slotSubscribe well within this
+const PUBSUB_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Long-lived handles shared by the listener, the pool workers and the position monitor,
+/// created once at startup instead of per pool or per order.
+pub struct AppContext {
+    pub rpc_client: Arc<dyn SolanaRpc>,
+    pub config: Arc<Config>,
+    pub keypair: Arc<Keypair>,
+    pub mongo: MongoHandler,
+    pub redis: RedisHandler,
+    /// WebSocket endpoints the LP burn subscriptions of every pool share a connection to,
+    /// empty when `rugcheck.lp_burn_subscribe` is off
+    pubsub_urls: Vec<String>,
+    /// The connection to each of `pubsub_urls`, `None` while it is down
+    pubsub_clients: RwLock<Vec<Option<Arc<PubsubClient>>>>,
+    /// `None` when `rugcheck.fetch_metadata_uri` is off
+    pub metadata_fetcher: Option<Arc<dyn MetadataFetcher>>,
+}
+
+impl AppContext {
+    pub async fn new(
+        rpc_client: Arc<dyn SolanaRpc>,
+        config: Arc<Config>,
+        keypair: Arc<Keypair>,
+        ws_urls: Vec<String>
+    ) -> Result<Arc<Self>, Box<dyn Error>> {
+        let mongo = MongoHandler::new().await?;
+        let redis = RedisHandler::new()?;
+        let metadata_fetcher: Option<Arc<dyn MetadataFetcher>> = if
+            config.rugcheck.fetch_metadata_uri
+        {
+            let timeout = Duration::from_secs(config.rugcheck.metadata_timeout_secs);
+            Some(Arc::new(HttpMetadataFetcher::new(timeout)?))
+        } else {
+            None
+        };
+        let pubsub_urls = if config.rugcheck.lp_burn_subscribe { ws_urls } else { Vec::new() };
+        let pubsub_clients = join_all(pubsub_urls.iter().map(|url| connect_pubsub(url))).await;
+
+        Ok(
+            Arc::new(AppContext {
+                rpc_client,
+                config,
+                keypair,
+                mongo,
+                redis,
+                pubsub_urls,
+                pubsub_clients: RwLock::new(pubsub_clients),
+                metadata_fetcher,
+            })
+        )
+    }
+
+    /// The WebSocket connections that are currently up.
+    pub fn pubsub_clients(&self) -> Vec<Arc<PubsubClient>> {
+        self.pubsub_clients.read().unwrap().iter().flatten().cloned().collect()
+    }
+
+    /// Pings Mongo and Redis periodically so broken connections are noticed before they
+    /// delay an order; their drivers reconnect on the next use. WebSocket connections that
+    /// stop answering are replaced with a new one to the same endpoint.
+    pub async fn monitor_health(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS)).await;
+
+            self.reconnect_pubsub().await;
+
+            if let Err(err) = self.mongo.ping().await {
+                eprintln!("MongoDB health check failed: {}", err);
+            }
+            if self.redis.is_configured() {
+                if let Err(err) = self.redis.ping().await {
+                    eprintln!("{}", err);
+                }
+            }
+        }
+    }
+
+    async fn reconnect_pubsub(&self) {
+        for (index, url) in self.pubsub_urls.iter().enumerate() {
+            // Cloned out so the lock isn't held across the probe
+            let client = self.pubsub_clients.read().unwrap()[index].clone();
+            if let Some(client) = client {
+                if is_alive(&client).await {
+                    continue;
+                }
+                eprintln!("WebSocket connection to {} stopped answering, reconnecting", url);
+            }
+            if let Some(client) = connect_pubsub(url).await {
+                self.pubsub_clients.write().unwrap()[index] = Some(client);
+            }
+        }
+    }
+}
+
+async fn connect_pubsub(url: &str) -> Option<Arc<PubsubClient>> {
+    match PubsubClient::new(url).await {
+        Ok(client) => Some(Arc::new(client)),
+        Err(err) => {
+            eprintln!("Failed to connect to {}: {}", url, err);
+            None
+        }
+    }
+}
+
+/// Whether the connection still takes subscriptions, a dropped socket fails them at once.
+async fn is_alive(client: &PubsubClient) -> bool {
+    match tokio::time::timeout(PUBSUB_PROBE_TIMEOUT, client.slot_subscribe()).await {
+        Ok(Ok((_notifications, unsubscribe))) => {
+            unsubscribe().await;
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,81 @@
+use crate::config::DedupConfig;
+use crate::redis::RedisHandler;
+use std::collections::{ HashMap, VecDeque };
+use std::time::{ Duration, Instant };
+
+const REDIS_KEY_PREFIX: &str = "solsniper:seen:";
+
+/// Remembers which transaction signatures were already handled. The in-memory window is
+/// bounded by both `capacity` and `ttl`; with Redis enabled the check is a `SET NX` with the
+/// same TTL, so restarted or replicated listeners agree on what has been seen.
+pub struct SignatureDedup {
+    capacity: usize,
+    ttl: Duration,
+    seen: HashMap<String, Instant>,
+    order: VecDeque<String>,
+    redis: Option<RedisHandler>,
+}
+
+impl SignatureDedup {
+    pub fn new(config: &DedupConfig, redis: &RedisHandler) -> Self {
+        SignatureDedup {
+            capacity: config.capacity,
+            ttl: Duration::from_secs(config.ttl_secs),
+            seen: HashMap::with_capacity(config.capacity),
+            order: VecDeque::with_capacity(config.capacity),
+            redis: config.redis.then(|| redis.clone()),
+        }
+    }
+
+    /// Records `signature`, returning `true` only the first time it is seen.
+    pub async fn insert(&mut self, signature: &str) -> bool {
+        let now = Instant::now();
+        self.evict(now);
+
+        if self.seen.contains_key(signature) {
+            return false;
+        }
+
+        if let Some(redis) = self.redis.as_ref() {
+            let key = format!("{}{}", REDIS_KEY_PREFIX, signature);
+            match redis.set_nx_ex(&key, self.ttl.as_secs()).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    // Another instance (or an earlier run) already took it
+                    self.remember(signature, now);
+                    return false;
+                }
+                Err(err) => {
+                    eprintln!("Redis dedup failed, using the local window only: {}", err);
+                }
+            }
+        }
+
+        self.remember(signature, now);
+        true
+    }
+
+    fn remember(&mut self, signature: &str, now: Instant) {
+        self.seen.insert(signature.to_string(), now);
+        self.order.push_back(signature.to_string());
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while let Some(oldest) = self.order.front() {
+            match self.seen.get(oldest) {
+                Some(seen_at) if now.duration_since(*seen_at) < self.ttl => {
+                    break;
+                }
+                _ => {
+                    self.seen.remove(oldest);
+                    self.order.pop_front();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::config::Config;
+use crate::meteora::{ MeteoraDlmmDetector, MeteoraDynamicDetector };
+use crate::orca::OrcaWhirlpoolDetector;
+use crate::swap::{ self, PoolParseError };
+use crate::utils::{ find_log_entry, PoolInfo, PoolType };
+use crate::raydium_sdk::{
+    ClmmCreatePoolInstructionData,
+    CpmmInitializeInstructionData,
+    Initialize2InstructionData,
+};
+use solana_sdk::bs58;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use solana_transaction_status::EncodedTransaction;
+use solana_transaction_status::UiInnerInstructions;
+use solana_transaction_status::UiInstruction;
+use solana_transaction_status::UiMessage;
+use solana_transaction_status::UiParsedInstruction;
+use solana_transaction_status::UiTransactionTokenBalance;
+use std::str::FromStr;
+
+/// Finds the pools one AMM program creates. Every detector produces a `PoolInfo`, so new
+/// pools of any program go through the same rug check and buy.
+pub trait PoolDetector: Send + Sync {
+    fn program_id(&self) -> Pubkey;
+
+    /// Cheap pre-filter on the logs of a transaction, run before it is fetched.
+    fn is_creation_log(&self, log_messages: &[String]) -> bool;
+
+    /// Whether the data of one of the program's instructions creates a pool.
+    fn is_creation_instruction(&self, data: &[u8]) -> bool;
+
+    /// Builds the pool from its creating instruction without touching the network.
+    fn parse(
+        &self,
+        instruction: &CreationInstruction,
+        tx: &PoolTransaction
+    ) -> Result<PoolInfo, PoolParseError>;
+}
+
+/// A detector for every enabled pool type, see `listener.pool_types`.
+pub fn pool_detectors(config: &Config) -> Vec<Box<dyn PoolDetector>> {
+    config
+        .pool_programs()
+        .into_iter()
+        .map(|(pool_type, program_id)| -> Box<dyn PoolDetector> {
+            match pool_type {
+                PoolType::AmmV4 | PoolType::Cpmm | PoolType::Clmm => {
+                    Box::new(RaydiumDetector { pool_type, program_id })
+                }
+                PoolType::MeteoraDlmm => Box::new(MeteoraDlmmDetector { program_id }),
+                PoolType::MeteoraDynamic => Box::new(MeteoraDynamicDetector { program_id }),
+                PoolType::OrcaWhirlpool => Box::new(OrcaWhirlpoolDetector { program_id }),
+            }
+        })
+        .collect()
+}
+
+/// Whether any detector recognizes the logs as a pool creation.
+pub fn is_pool_creation_log(log_messages: &[String], detectors: &[Box<dyn PoolDetector>]) -> bool {
+    detectors.iter().any(|detector| detector.is_creation_log(log_messages))
+}
+
+/// Whether an Anchor program logged one of the `names` instructions. Exact matches, since
+/// `Instruction: InitializeMint` and friends share their prefixes.
+pub fn logs_instruction(log_messages: &[String], names: &[&str]) -> bool {
+    log_messages.iter().any(|entry| {
+        entry
+            .strip_prefix("Program log: Instruction: ")
+            .is_some_and(|name| names.contains(&name))
+    })
+}
+
+/// The top-level instruction that created a pool, with its data decoded.
+pub struct CreationInstruction {
+    pub accounts: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+impl CreationInstruction {
+    pub fn account(&self, index: usize) -> Result<Pubkey, PoolParseError> {
+        parse_pubkey(self.accounts.get(index).ok_or(PoolParseError::MissingAccount(index))?)
+    }
+
+    /// The Anchor discriminator, the first 8 bytes of the data.
+    pub fn discriminator(&self) -> Option<[u8; 8]> {
+        self.data.get(..8)?.try_into().ok()
+    }
+}
+
+/// The parts of a JSON parsed transaction detectors read, extracted once.
+pub struct PoolTransaction {
+    pub instructions: Vec<UiInstruction>,
+    pub inner_instructions: Vec<UiInnerInstructions>,
+    pub log_messages: Vec<String>,
+    pub pre_token_balances: Vec<UiTransactionTokenBalance>,
+    pub post_token_balances: Vec<UiTransactionTokenBalance>,
+    /// Static and lookup table keys, in the order token balances index them
+    pub account_keys: Vec<String>,
+}
+
+impl TryFrom<EncodedConfirmedTransactionWithStatusMeta> for PoolTransaction {
+    type Error = PoolParseError;
+
+    fn try_from(tx: EncodedConfirmedTransactionWithStatusMeta) -> Result<Self, Self::Error> {
+        let meta = tx.transaction.meta.ok_or(PoolParseError::MissingMeta)?;
+
+        let inner_instructions = match meta.inner_instructions {
+            OptionSerializer::Some(inner) => inner,
+            _ => {
+                return Err(PoolParseError::MissingMetaField("inner instructions"));
+            }
+        };
+        let log_messages = match meta.log_messages {
+            OptionSerializer::Some(inner) => inner,
+            _ => {
+                return Err(PoolParseError::MissingMetaField("log messages"));
+            }
+        };
+        let pre_token_balances = match meta.pre_token_balances {
+            OptionSerializer::Some(inner) => inner,
+            _ => {
+                return Err(PoolParseError::MissingMetaField("pre token balances"));
+            }
+        };
+        let post_token_balances = match meta.post_token_balances {
+            OptionSerializer::Some(inner) => inner,
+            _ => {
+                return Err(PoolParseError::MissingMetaField("post token balances"));
+            }
+        };
+
+        let ui_tx = match tx.transaction.transaction {
+            EncodedTransaction::Json(ui_tx) => ui_tx,
+            _ => {
+                return Err(PoolParseError::UnsupportedEncoding);
+            }
+        };
+        let message = match ui_tx.message {
+            UiMessage::Parsed(message) => message,
+            _ => {
+                return Err(PoolParseError::UnsupportedEncoding);
+            }
+        };
+
+        Ok(PoolTransaction {
+            instructions: message.instructions,
+            inner_instructions,
+            log_messages,
+            pre_token_balances,
+            post_token_balances,
+            account_keys: message.account_keys
+                .into_iter()
+                .map(|key| key.pubkey)
+                .collect(),
+        })
+    }
+}
+
+impl PoolTransaction {
+    /// The first top-level instruction of `detector`'s program that creates a pool.
+    pub fn find_creation(&self, detector: &dyn PoolDetector) -> Option<CreationInstruction> {
+        let program_id = detector.program_id().to_string();
+        self.instructions.iter().find_map(|instruction| {
+            // JsonParsed encoding never leaves an AMM instruction compiled
+            let UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) =
+                instruction else {
+                return None;
+            };
+            if decoded.program_id != program_id {
+                return None;
+            }
+            let data = bs58::decode(&decoded.data).into_vec().ok()?;
+            if !detector.is_creation_instruction(&data) {
+                return None;
+            }
+            Some(CreationInstruction { accounts: decoded.accounts.clone(), data })
+        })
+    }
+
+    /// Decimals of `mint` from any token balance of the transaction. Vaults created by the
+    /// transaction only show up in the post balances.
+    pub fn mint_decimals(&self, mint: &Pubkey) -> Result<u8, PoolParseError> {
+        let mint = mint.to_string();
+        self.pre_token_balances
+            .iter()
+            .chain(&self.post_token_balances)
+            .find(|balance| balance.mint == mint)
+            .map(|balance| balance.ui_token_amount.decimals)
+            .ok_or(PoolParseError::MintDecimalsNotFound(mint))
+    }
+
+    /// How much the transaction added to the token account `account`, in raw units.
+    pub fn token_balance_increase(&self, account: &Pubkey) -> Result<u64, PoolParseError> {
+        let account = account.to_string();
+        let index = self.account_keys
+            .iter()
+            .position(|key| *key == account)
+            .ok_or_else(|| PoolParseError::TokenBalanceNotFound(account.clone()))? as u8;
+        let find = |balances: &'_ [UiTransactionTokenBalance]| {
+            balances.iter().find(|balance| balance.account_index == index).cloned()
+        };
+        let post = find(&self.post_token_balances).ok_or_else(||
+            PoolParseError::TokenBalanceNotFound(account.clone())
+        )?;
+        let pre = match find(&self.pre_token_balances) {
+            Some(balance) => parse_amount(&balance.ui_token_amount.amount)?,
+            None => 0,
+        };
+        Ok(parse_amount(&post.ui_token_amount.amount)?.saturating_sub(pre))
+    }
+}
+
+/// Raydium AMM v4, CPMM and CLMM pools, whose parsers live next to the swap pipeline.
+pub struct RaydiumDetector {
+    pub pool_type: PoolType,
+    pub program_id: Pubkey,
+}
+
+impl PoolDetector for RaydiumDetector {
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn is_creation_log(&self, log_messages: &[String]) -> bool {
+        match self.pool_type {
+            PoolType::AmmV4 => find_log_entry("init_pc_amount", log_messages).is_some(),
+            PoolType::Cpmm => logs_instruction(log_messages, &["Initialize"]),
+            PoolType::Clmm => logs_instruction(log_messages, &["CreatePool"]),
+            _ => false,
+        }
+    }
+
+    fn is_creation_instruction(&self, data: &[u8]) -> bool {
+        match self.pool_type {
+            // The instruction data of older pools doesn't always decode, the parser falls
+            // back to the logs for those
+            PoolType::AmmV4 => true,
+            PoolType::Cpmm => CpmmInitializeInstructionData::decode(data).is_some(),
+            PoolType::Clmm => ClmmCreatePoolInstructionData::decode(data).is_some(),
+            _ => false,
+        }
+    }
+
+    fn parse(
+        &self,
+        instruction: &CreationInstruction,
+        tx: &PoolTransaction
+    ) -> Result<PoolInfo, PoolParseError> {
+        match self.pool_type {
+            PoolType::AmmV4 => {
+                let init2 = Initialize2InstructionData::decode(&instruction.data);
+                swap::parse_amm_v4_pool(instruction, init2, tx, &self.program_id)
+            }
+            PoolType::Cpmm => swap::parse_cpmm_pool(instruction, tx, &self.program_id),
+            _ => swap::parse_clmm_pool(instruction, tx, &self.program_id),
+        }
+    }
+}
+
+/// One token of a pool whose program orders the mints by address.
+pub struct PoolSide {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub reserve: u64,
+}
+
+/// `(base, quote)`, with WSOL on the quote side when the pool has it.
+pub fn orient_sides(side_a: PoolSide, side_b: PoolSide) -> (PoolSide, PoolSide) {
+    if side_a.mint == spl_token::native_mint::id() {
+        (side_b, side_a)
+    } else {
+        (side_a, side_b)
+    }
+}
+
+/// A pool of `pool_type` with the base and quote fields filled in from the sides.
+pub fn pool_info_from_sides(pool_type: PoolType, base: &PoolSide, quote: &PoolSide) -> PoolInfo {
+    PoolInfo {
+        pool_type,
+        base_mint: base.mint,
+        quote_mint: quote.mint,
+        base_decimals: base.decimals,
+        quote_decimals: quote.decimals,
+        base_vault: base.vault,
+        quote_vault: quote.vault,
+        base_reserve: base.reserve,
+        quote_reserve: quote.reserve,
+        base_token_program: base.token_program,
+        quote_token_program: quote.token_program,
+        ..PoolInfo::default()
+    }
+}
+
+pub fn parse_amount(amount_str: &str) -> Result<u64, PoolParseError> {
+    u64::from_str(amount_str).map_err(|_| PoolParseError::InvalidAmount(amount_str.to_string()))
+}
+
+pub fn parse_pubkey(address: &str) -> Result<Pubkey, PoolParseError> {
+    Pubkey::from_str(address).map_err(|_| PoolParseError::InvalidPubkey(address.to_string()))
+}
@@ -0,0 +1,396 @@
+//! Chain data for tests, built in memory with the layout the programs produce on mainnet.
+
+use crate::raydium_sdk::{ get_amm_authority, Initialize2InstructionData, INITIALIZE2_INSTRUCTION };
+use crate::rpc::FakeRpc;
+use borsh::BorshSerialize;
+use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_client::rpc_response::RpcTokenAccountBalance;
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{ AccountMeta, CompiledInstruction, Instruction };
+use solana_sdk::message::Message;
+use solana_sdk::program_option::COption;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ Keypair, Signature, Signer };
+use solana_sdk::transaction::{ Transaction, VersionedTransaction };
+use solana_sdk::{ system_program, sysvar };
+use solana_transaction_status::{
+    ConfirmedTransactionWithStatusMeta,
+    EncodedConfirmedTransactionWithStatusMeta,
+    InnerInstruction,
+    InnerInstructions,
+    TransactionStatusMeta,
+    TransactionTokenBalance,
+    TransactionWithStatusMeta,
+    UiTransactionEncoding,
+    VersionedTransactionWithStatusMeta,
+};
+use spl_token::state::{ Account as TokenAccount, AccountState, Mint };
+
+pub const RAYDIUM_AMM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+pub const OPENBOOK_PROGRAM_ID: &str = "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX";
+
+pub const TOKEN_DECIMALS: u8 = 6;
+pub const LP_DECIMALS: u8 = 9;
+pub const INIT_COIN_AMOUNT: u64 = 800_000_000_000_000;
+pub const INIT_PC_AMOUNT: u64 = 100_000_000_000;
+pub const LP_AMOUNT: u64 = 8_944_271_909_999;
+pub const OPEN_TIME: u64 = 1_718_000_000;
+pub const TOKEN_SUPPLY: u64 = 1_000_000_000_000_000;
+
+/// A Raydium AMM v4 `initialize2` transaction pooling a fresh token against WSOL, with
+/// every account it touches.
+pub struct AmmV4Creation {
+    pub creator: Keypair,
+    pub program_id: Pubkey,
+    pub pool_id: Pubkey,
+    pub authority: Pubkey,
+    pub open_orders: Pubkey,
+    pub lp_mint: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub withdraw_queue: Pubkey,
+    pub target_orders: Pubkey,
+    pub temp_lp: Pubkey,
+    pub market_program_id: Pubkey,
+    pub market_id: Pubkey,
+    pub market_base_vault: Pubkey,
+    pub market_quote_vault: Pubkey,
+    pub market_event_queue: Pubkey,
+    pub market_bids: Pubkey,
+    pub market_asks: Pubkey,
+    pub creator_base_account: Pubkey,
+    pub creator_quote_account: Pubkey,
+    pub creator_lp_account: Pubkey,
+}
+
+impl AmmV4Creation {
+    pub fn new() -> Self {
+        let program_id: Pubkey = RAYDIUM_AMM_V4_PROGRAM_ID.parse().unwrap();
+        AmmV4Creation {
+            creator: Keypair::new(),
+            program_id,
+            pool_id: Pubkey::new_unique(),
+            authority: get_amm_authority(&program_id),
+            open_orders: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            base_mint: Pubkey::new_unique(),
+            quote_mint: spl_token::native_mint::id(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            withdraw_queue: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            temp_lp: Pubkey::new_unique(),
+            market_program_id: OPENBOOK_PROGRAM_ID.parse().unwrap(),
+            market_id: Pubkey::new_unique(),
+            market_base_vault: Pubkey::new_unique(),
+            market_quote_vault: Pubkey::new_unique(),
+            market_event_queue: Pubkey::new_unique(),
+            market_bids: Pubkey::new_unique(),
+            market_asks: Pubkey::new_unique(),
+            creator_base_account: Pubkey::new_unique(),
+            creator_quote_account: Pubkey::new_unique(),
+            creator_lp_account: Pubkey::new_unique(),
+        }
+    }
+
+    /// The 21 accounts of `initialize2`, in the program's order.
+    fn initialize2_instruction(&self) -> Instruction {
+        let data = Initialize2InstructionData {
+            instruction: INITIALIZE2_INSTRUCTION,
+            nonce: 254,
+            open_time: OPEN_TIME,
+            init_pc_amount: INIT_PC_AMOUNT,
+            init_coin_amount: INIT_COIN_AMOUNT,
+        };
+        Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(sysvar::rent::id(), false),
+                AccountMeta::new(self.pool_id, false),
+                AccountMeta::new_readonly(self.authority, false),
+                AccountMeta::new(self.open_orders, false),
+                AccountMeta::new(self.lp_mint, false),
+                AccountMeta::new_readonly(self.base_mint, false),
+                AccountMeta::new_readonly(self.quote_mint, false),
+                AccountMeta::new(self.base_vault, false),
+                AccountMeta::new(self.quote_vault, false),
+                AccountMeta::new(self.withdraw_queue, false),
+                AccountMeta::new(self.target_orders, false),
+                AccountMeta::new(self.temp_lp, false),
+                AccountMeta::new_readonly(self.market_program_id, false),
+                AccountMeta::new_readonly(self.market_id, false),
+                AccountMeta::new(self.creator.pubkey(), true),
+                AccountMeta::new(self.creator_base_account, false),
+                AccountMeta::new(self.creator_quote_account, false),
+                AccountMeta::new(self.creator_lp_account, false)
+            ],
+            data: data.try_to_vec().unwrap(),
+        }
+    }
+
+    pub fn transaction(&self) -> VersionedTransaction {
+        let message = Message::new(&[self.initialize2_instruction()], Some(&self.creator.pubkey()));
+        Transaction::new(&[&self.creator], message, Hash::default()).into()
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.transaction().signatures[0]
+    }
+
+    /// The LP mint initialization and mint to the creator the program does through CPIs,
+    /// plus the token balances and logs of a successful run.
+    pub fn status_meta(&self) -> TransactionStatusMeta {
+        let transaction = self.transaction();
+        let account_keys = transaction.message.static_account_keys();
+        let compile = |instruction: Instruction| CompiledInstruction {
+            program_id_index: index_of(account_keys, &instruction.program_id),
+            accounts: instruction.accounts
+                .iter()
+                .map(|meta| index_of(account_keys, &meta.pubkey))
+                .collect(),
+            data: instruction.data,
+        };
+        let initialize_mint = spl_token::instruction
+            ::initialize_mint(&spl_token::id(), &self.lp_mint, &self.authority, None, LP_DECIMALS)
+            .unwrap();
+        let mint_to = spl_token::instruction
+            ::mint_to(
+                &spl_token::id(),
+                &self.lp_mint,
+                &self.creator_lp_account,
+                &self.authority,
+                &[],
+                LP_AMOUNT
+            )
+            .unwrap();
+
+        let token_balance = |account: &Pubkey, mint: &Pubkey, decimals: u8, amount: u64| {
+            TransactionTokenBalance {
+                account_index: index_of(account_keys, account),
+                mint: mint.to_string(),
+                ui_token_amount: token_amount(amount, decimals),
+                owner: self.creator.pubkey().to_string(),
+                program_id: spl_token::id().to_string(),
+            }
+        };
+
+        TransactionStatusMeta {
+            status: Ok(()),
+            fee: 5000,
+            pre_balances: vec![0; account_keys.len()],
+            post_balances: vec![0; account_keys.len()],
+            inner_instructions: Some(
+                vec![InnerInstructions {
+                    index: 0,
+                    instructions: [initialize_mint, mint_to]
+                        .into_iter()
+                        .map(|instruction| InnerInstruction {
+                            instruction: compile(instruction),
+                            stack_height: Some(2),
+                        })
+                        .collect(),
+                }]
+            ),
+            log_messages: Some(
+                vec![
+                    format!("Program {} invoke [1]", self.program_id),
+                    format!(
+                        "Program log: initialize2: InitializeInstruction2 {{ nonce: 254, \
+                         open_time: {}, init_pc_amount: {}, init_coin_amount: {} }}",
+                        OPEN_TIME,
+                        INIT_PC_AMOUNT,
+                        INIT_COIN_AMOUNT
+                    ),
+                    format!("Program {} success", self.program_id)
+                ]
+            ),
+            pre_token_balances: Some(
+                vec![
+                    token_balance(
+                        &self.creator_base_account,
+                        &self.base_mint,
+                        TOKEN_DECIMALS,
+                        INIT_COIN_AMOUNT
+                    ),
+                    token_balance(&self.creator_quote_account, &self.quote_mint, 9, INIT_PC_AMOUNT)
+                ]
+            ),
+            post_token_balances: Some(
+                vec![
+                    token_balance(&self.creator_base_account, &self.base_mint, TOKEN_DECIMALS, 0),
+                    token_balance(&self.creator_quote_account, &self.quote_mint, 9, 0),
+                    token_balance(&self.creator_lp_account, &self.lp_mint, LP_DECIMALS, LP_AMOUNT)
+                ]
+            ),
+            rewards: None,
+            loaded_addresses: Default::default(),
+            return_data: None,
+            compute_units_consumed: None,
+        }
+    }
+
+    /// The transaction as `getTransaction` returns it with the `jsonParsed` encoding.
+    pub fn encoded(&self) -> EncodedConfirmedTransactionWithStatusMeta {
+        let confirmed = ConfirmedTransactionWithStatusMeta {
+            slot: 1,
+            tx_with_meta: TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+                transaction: self.transaction(),
+                meta: self.status_meta(),
+            }),
+            block_time: None,
+        };
+        confirmed.encode(UiTransactionEncoding::JsonParsed, Some(0)).unwrap()
+    }
+
+    /// The chain right after the pool was created: the token without authorities, the LP
+    /// minted to the creator and nothing burnt yet, and `holders` owning the given shares of
+    /// the supply next to the pool vault.
+    pub fn load_chain(&self, rpc: &FakeRpc, holders: &[(Pubkey, u64)]) {
+        rpc.insert_account(self.base_mint, mint_account(TOKEN_SUPPLY, TOKEN_DECIMALS, None));
+        rpc.insert_account(
+            self.lp_mint,
+            mint_account(LP_AMOUNT, LP_DECIMALS, Some(self.authority))
+        );
+        rpc.insert_token_balance(self.base_vault, token_amount(INIT_COIN_AMOUNT, TOKEN_DECIMALS));
+        rpc.insert_token_balance(self.quote_vault, token_amount(INIT_PC_AMOUNT, 9));
+
+        let mut largest_accounts = vec![(self.base_vault, self.authority, INIT_COIN_AMOUNT)];
+        largest_accounts.extend(holders.iter().map(|(owner, amount)| {
+            (Pubkey::new_unique(), *owner, *amount)
+        }));
+        for (address, owner, amount) in &largest_accounts {
+            rpc.insert_account(*address, token_account(&self.base_mint, owner, *amount));
+        }
+        rpc.insert_largest_accounts(
+            self.base_mint,
+            largest_accounts
+                .iter()
+                .map(|(address, _, amount)| RpcTokenAccountBalance {
+                    address: address.to_string(),
+                    amount: token_amount(*amount, TOKEN_DECIMALS),
+                })
+                .collect()
+        );
+        rpc.insert_token_supply(self.base_mint, token_amount(TOKEN_SUPPLY, TOKEN_DECIMALS));
+    }
+
+    /// The pool's 752-byte AMM account, `LIQUIDITY_STATE_LAYOUT_V4`.
+    pub fn amm_account(&self) -> Account {
+        let mut data = vec![0; 752];
+        let mut put_u64 = |offset: usize, value: u64| {
+            data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        };
+        put_u64(0, 6); // status, swap only
+        put_u64(8, 254); // nonce
+        put_u64(32, TOKEN_DECIMALS as u64);
+        put_u64(40, 9);
+        put_u64(144, 25); // trade_fee_numerator
+        put_u64(152, 10_000);
+        put_u64(224, OPEN_TIME);
+        put_u64(720, LP_AMOUNT);
+        let keys = [
+            (336, self.base_vault),
+            (368, self.quote_vault),
+            (400, self.base_mint),
+            (432, self.quote_mint),
+            (464, self.lp_mint),
+            (496, self.open_orders),
+            (528, self.market_id),
+            (560, self.market_program_id),
+            (592, self.target_orders),
+            (624, self.withdraw_queue),
+            (656, self.temp_lp),
+            (688, self.creator.pubkey()),
+        ];
+        for (offset, key) in keys {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        Account {
+            lamports: 6_124_800,
+            data,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// The pool's 388-byte OpenBook market account, `MARKET_STATE_LAYOUT_V3`.
+    pub fn market_account(&self) -> Account {
+        let mut data = vec![0; 388];
+        let keys = [
+            (13, self.market_id),
+            (53, self.base_mint),
+            (85, self.quote_mint),
+            (117, self.market_base_vault),
+            (165, self.market_quote_vault),
+            (253, self.market_event_queue),
+            (285, self.market_bids),
+            (317, self.market_asks),
+        ];
+        for (offset, key) in keys {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        Account {
+            lamports: 3_591_360,
+            data,
+            owner: self.market_program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// Burns the creator's LP, the supply left is what the pool keeps locked for itself.
+    pub fn burn_lp(&self, rpc: &FakeRpc) {
+        rpc.insert_account(self.lp_mint, mint_account(0, LP_DECIMALS, Some(self.authority)));
+    }
+}
+
+fn index_of(account_keys: &[Pubkey], pubkey: &Pubkey) -> u8 {
+    account_keys
+        .iter()
+        .position(|key| key == pubkey)
+        .expect("Account is in the message") as u8
+}
+
+pub fn token_amount(amount: u64, decimals: u8) -> UiTokenAmount {
+    let ui_amount = (amount as f64) / (10_f64).powi(decimals as i32);
+    UiTokenAmount {
+        ui_amount: Some(ui_amount),
+        decimals,
+        amount: amount.to_string(),
+        ui_amount_string: ui_amount.to_string(),
+    }
+}
+
+pub fn mint_account(supply: u64, decimals: u8, mint_authority: Option<Pubkey>) -> Account {
+    let mint = Mint {
+        mint_authority: mint_authority.map_or(COption::None, COption::Some),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+    Account { lamports: 1_461_600, data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
+
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let account = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(account, &mut data).unwrap();
+    Account { lamports: 2_039_280, data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
@@ -0,0 +1,798 @@
+use crate::config::{ GeyserCommitment, GeyserConfig };
+use crate::pump_sdk::{ self, PumpEvent };
+use crate::detector::{ is_pool_creation_log, PoolDetector };
+use std::collections::HashMap;
+use std::error::Error;
+use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{ legacy, v0, MessageHeader, VersionedMessage };
+use solana_sdk::message::v0::{ LoadedAddresses, MessageAddressTableLookup };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::{
+    ConfirmedTransactionWithStatusMeta,
+    EncodedConfirmedTransactionWithStatusMeta,
+    InnerInstruction,
+    InnerInstructions,
+    TransactionStatusMeta,
+    TransactionTokenBalance,
+    TransactionWithStatusMeta,
+    UiTransactionEncoding,
+    VersionedTransactionWithStatusMeta,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{ ClientTlsConfig, Endpoint };
+use tonic::Streaming;
+
+/// The subset of Yellowstone's `geyser.proto` and `confirmed_block.proto` needed to
+/// subscribe to transactions. Field tags match upstream, everything else is skipped
+/// when decoding.
+pub mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeRequest {
+        #[prost(map = "string, message", tag = "3")]
+        pub transactions: HashMap<String, SubscribeRequestFilterTransactions>,
+        #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+        pub commitment: Option<i32>,
+        #[prost(message, optional, tag = "9")]
+        pub ping: Option<SubscribeRequestPing>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeRequestFilterTransactions {
+        #[prost(bool, optional, tag = "1")]
+        pub vote: Option<bool>,
+        #[prost(bool, optional, tag = "2")]
+        pub failed: Option<bool>,
+        #[prost(string, repeated, tag = "3")]
+        pub account_include: Vec<String>,
+        #[prost(string, repeated, tag = "4")]
+        pub account_exclude: Vec<String>,
+        #[prost(string, optional, tag = "5")]
+        pub signature: Option<String>,
+        #[prost(string, repeated, tag = "6")]
+        pub account_required: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeRequestPing {
+        #[prost(int32, tag = "1")]
+        pub id: i32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum CommitmentLevel {
+        Processed = 0,
+        Confirmed = 1,
+        Finalized = 2,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeUpdate {
+        #[prost(string, repeated, tag = "1")]
+        pub filters: Vec<String>,
+        #[prost(oneof = "subscribe_update::UpdateOneof", tags = "4, 6, 9")]
+        pub update_oneof: Option<subscribe_update::UpdateOneof>,
+    }
+
+    pub mod subscribe_update {
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum UpdateOneof {
+            #[prost(message, tag = "4")] Transaction(super::SubscribeUpdateTransaction),
+            #[prost(message, tag = "6")] Ping(super::SubscribeUpdatePing),
+            #[prost(message, tag = "9")] Pong(super::SubscribeUpdatePong),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeUpdateTransaction {
+        #[prost(message, optional, tag = "1")]
+        pub transaction: Option<SubscribeUpdateTransactionInfo>,
+        #[prost(uint64, tag = "2")]
+        pub slot: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeUpdateTransactionInfo {
+        #[prost(bytes = "vec", tag = "1")]
+        pub signature: Vec<u8>,
+        #[prost(bool, tag = "2")]
+        pub is_vote: bool,
+        #[prost(message, optional, tag = "3")]
+        pub transaction: Option<Transaction>,
+        #[prost(message, optional, tag = "4")]
+        pub meta: Option<TransactionStatusMeta>,
+        #[prost(uint64, tag = "5")]
+        pub index: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeUpdatePing {}
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeUpdatePong {
+        #[prost(int32, tag = "1")]
+        pub id: i32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Transaction {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub signatures: Vec<Vec<u8>>,
+        #[prost(message, optional, tag = "2")]
+        pub message: Option<Message>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Message {
+        #[prost(message, optional, tag = "1")]
+        pub header: Option<MessageHeader>,
+        #[prost(bytes = "vec", repeated, tag = "2")]
+        pub account_keys: Vec<Vec<u8>>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub recent_blockhash: Vec<u8>,
+        #[prost(message, repeated, tag = "4")]
+        pub instructions: Vec<CompiledInstruction>,
+        #[prost(bool, tag = "5")]
+        pub versioned: bool,
+        #[prost(message, repeated, tag = "6")]
+        pub address_table_lookups: Vec<MessageAddressTableLookup>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MessageHeader {
+        #[prost(uint32, tag = "1")]
+        pub num_required_signatures: u32,
+        #[prost(uint32, tag = "2")]
+        pub num_readonly_signed_accounts: u32,
+        #[prost(uint32, tag = "3")]
+        pub num_readonly_unsigned_accounts: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MessageAddressTableLookup {
+        #[prost(bytes = "vec", tag = "1")]
+        pub account_key: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub writable_indexes: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub readonly_indexes: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CompiledInstruction {
+        #[prost(uint32, tag = "1")]
+        pub program_id_index: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub accounts: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TransactionStatusMeta {
+        #[prost(uint64, tag = "2")]
+        pub fee: u64,
+        #[prost(uint64, repeated, tag = "3")]
+        pub pre_balances: Vec<u64>,
+        #[prost(uint64, repeated, tag = "4")]
+        pub post_balances: Vec<u64>,
+        #[prost(message, repeated, tag = "5")]
+        pub inner_instructions: Vec<InnerInstructions>,
+        #[prost(string, repeated, tag = "6")]
+        pub log_messages: Vec<String>,
+        #[prost(message, repeated, tag = "7")]
+        pub pre_token_balances: Vec<TokenBalance>,
+        #[prost(message, repeated, tag = "8")]
+        pub post_token_balances: Vec<TokenBalance>,
+        #[prost(bool, tag = "10")]
+        pub inner_instructions_none: bool,
+        #[prost(bool, tag = "11")]
+        pub log_messages_none: bool,
+        #[prost(bytes = "vec", repeated, tag = "12")]
+        pub loaded_writable_addresses: Vec<Vec<u8>>,
+        #[prost(bytes = "vec", repeated, tag = "13")]
+        pub loaded_readonly_addresses: Vec<Vec<u8>>,
+        #[prost(uint64, optional, tag = "16")]
+        pub compute_units_consumed: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct InnerInstructions {
+        #[prost(uint32, tag = "1")]
+        pub index: u32,
+        #[prost(message, repeated, tag = "2")]
+        pub instructions: Vec<InnerInstruction>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct InnerInstruction {
+        #[prost(uint32, tag = "1")]
+        pub program_id_index: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub accounts: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub data: Vec<u8>,
+        #[prost(uint32, optional, tag = "4")]
+        pub stack_height: Option<u32>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TokenBalance {
+        #[prost(uint32, tag = "1")]
+        pub account_index: u32,
+        #[prost(string, tag = "2")]
+        pub mint: String,
+        #[prost(message, optional, tag = "3")]
+        pub ui_token_amount: Option<UiTokenAmount>,
+        #[prost(string, tag = "4")]
+        pub owner: String,
+        #[prost(string, tag = "5")]
+        pub program_id: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct UiTokenAmount {
+        #[prost(double, tag = "1")]
+        pub ui_amount: f64,
+        #[prost(uint32, tag = "2")]
+        pub decimals: u32,
+        #[prost(string, tag = "3")]
+        pub amount: String,
+        #[prost(string, tag = "4")]
+        pub ui_amount_string: String,
+    }
+}
+
+use proto::subscribe_update::UpdateOneof;
+
+const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+/// Name of the transaction filter, echoed back by the server in `SubscribeUpdate.filters`
+const FILTER_NAME: &str = "raydium_amm";
+
+/// A transaction pushed by the gRPC stream, already in the shape `getTransaction` returns
+/// with `jsonParsed` encoding.
+pub struct GeyserTransaction {
+    pub signature: String,
+    pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+}
+
+/// What one message of the subscription carried.
+pub enum GeyserUpdate {
+    /// A successful transaction whose logs announce a pool of a watched type
+    NewPool(Box<GeyserTransaction>),
+    /// A transaction that emitted pump.fun create or complete events
+    Pump {
+        signature: String,
+        events: Vec<PumpEvent>,
+    },
+    /// Any other transaction, or a ping
+    Other,
+}
+
+/// A Yellowstone `Subscribe` stream filtered to successful, non-vote transactions that
+/// mention any of the watched programs.
+pub struct GeyserSubscription {
+    updates: Streaming<proto::SubscribeUpdate>,
+    // Kept open for the lifetime of the subscription, closing it ends the stream
+    requests: mpsc::Sender<proto::SubscribeRequest>,
+    detectors: Vec<Box<dyn PoolDetector>>,
+}
+
+impl GeyserSubscription {
+    pub async fn subscribe(
+        config: &GeyserConfig,
+        program_ids: Vec<String>,
+        detectors: Vec<Box<dyn PoolDetector>>
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut endpoint = Endpoint::from_shared(config.endpoint.clone())?;
+        if config.endpoint.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        let channel = endpoint.connect().await?;
+
+        let mut client = tonic::client::Grpc::new(channel);
+        client.ready().await?;
+
+        let (requests, receiver) = mpsc::channel(8);
+        requests.send(subscribe_request(config.commitment, program_ids)).await?;
+
+        let mut request = tonic::Request::new(ReceiverStream::new(receiver));
+        if !config.x_token.is_empty() {
+            request.metadata_mut().insert("x-token", MetadataValue::try_from(&config.x_token)?);
+        }
+
+        let updates = client
+            .streaming(
+                request,
+                PathAndQuery::from_static(SUBSCRIBE_PATH),
+                ProstCodec::<proto::SubscribeRequest, proto::SubscribeUpdate>::default()
+            ).await?
+            .into_inner();
+
+        Ok(GeyserSubscription { updates, requests, detectors })
+    }
+
+    /// Waits for the next message, `None` once the server closes the stream.
+    pub async fn next(&mut self) -> Option<Result<GeyserUpdate, Box<dyn Error + Send + Sync>>> {
+        let update = match self.updates.message().await {
+            Ok(Some(update)) => update,
+            Ok(None) => {
+                return None;
+            }
+            Err(status) => {
+                return Some(Err(status.into()));
+            }
+        };
+
+        Some(match update.update_oneof {
+            Some(UpdateOneof::Transaction(update)) => {
+                new_pool_transaction(update, &self.detectors)
+            }
+            Some(UpdateOneof::Ping(_)) => {
+                // Answer server pings so load balancers in front of it keep the stream open
+                let ping = proto::SubscribeRequest {
+                    ping: Some(proto::SubscribeRequestPing { id: 1 }),
+                    ..Default::default()
+                };
+                let _ = self.requests.try_send(ping);
+                Ok(GeyserUpdate::Other)
+            }
+            _ => Ok(GeyserUpdate::Other),
+        })
+    }
+}
+
+fn subscribe_request(
+    commitment: GeyserCommitment,
+    program_ids: Vec<String>
+) -> proto::SubscribeRequest {
+    // Transactions mentioning any of the included accounts match
+    let filter = proto::SubscribeRequestFilterTransactions {
+        vote: Some(false),
+        failed: Some(false),
+        account_include: program_ids,
+        ..Default::default()
+    };
+    let commitment = match commitment {
+        GeyserCommitment::Processed => proto::CommitmentLevel::Processed,
+        GeyserCommitment::Confirmed => proto::CommitmentLevel::Confirmed,
+        GeyserCommitment::Finalized => proto::CommitmentLevel::Finalized,
+    };
+
+    proto::SubscribeRequest {
+        transactions: HashMap::from([(FILTER_NAME.to_string(), filter)]),
+        commitment: Some(commitment as i32),
+        ping: None,
+    }
+}
+
+fn new_pool_transaction(
+    update: proto::SubscribeUpdateTransaction,
+    detectors: &[Box<dyn PoolDetector>]
+) -> Result<GeyserUpdate, Box<dyn Error + Send + Sync>> {
+    let info = update.transaction.ok_or("transaction update without a transaction")?;
+    let meta = info.meta.as_ref().ok_or("transaction update without meta")?;
+    let signature = Signature::try_from(info.signature.as_slice())
+        .map_err(|_| "invalid transaction signature")?
+        .to_string();
+
+    let events = pump_sdk::parse_events(&meta.log_messages);
+    if !events.is_empty() {
+        return Ok(GeyserUpdate::Pump { signature, events });
+    }
+    // Same cheap pre-filter as the logs subscription, before paying for the conversion
+    if !is_pool_creation_log(&meta.log_messages, detectors) {
+        return Ok(GeyserUpdate::Other);
+    }
+
+    let transaction = encode_transaction(update.slot, info)?;
+
+    Ok(GeyserUpdate::NewPool(Box::new(GeyserTransaction { signature, transaction })))
+}
+
+/// Rebuilds the transaction and its status meta from the protobuf message and encodes it
+/// as `jsonParsed`, so the pool parser sees the same layout `getTransaction` would return.
+pub fn encode_transaction(
+    slot: u64,
+    info: proto::SubscribeUpdateTransactionInfo
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, Box<dyn Error + Send + Sync>> {
+    let transaction = info.transaction.ok_or("transaction update without a transaction")?;
+    let meta = info.meta.ok_or("transaction update without meta")?;
+
+    let transaction = VersionedTransaction {
+        signatures: transaction.signatures
+            .iter()
+            .map(|signature| Signature::try_from(signature.as_slice()))
+            .collect::<Result<_, _>>()
+            .map_err(|_| "invalid transaction signature")?,
+        message: decode_message(transaction.message.ok_or("transaction without a message")?)?,
+    };
+    let meta = decode_meta(meta)?;
+
+    let confirmed = ConfirmedTransactionWithStatusMeta {
+        slot,
+        tx_with_meta: TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+            transaction,
+            meta,
+        }),
+        block_time: None,
+    };
+
+    Ok(confirmed.encode(UiTransactionEncoding::JsonParsed, Some(0))?)
+}
+
+fn decode_message(message: proto::Message) -> Result<VersionedMessage, Box<dyn Error + Send + Sync>> {
+    let header = message.header.ok_or("message without a header")?;
+    let header = MessageHeader {
+        num_required_signatures: u8::try_from(header.num_required_signatures)?,
+        num_readonly_signed_accounts: u8::try_from(header.num_readonly_signed_accounts)?,
+        num_readonly_unsigned_accounts: u8::try_from(header.num_readonly_unsigned_accounts)?,
+    };
+    let account_keys = decode_pubkeys(&message.account_keys)?;
+    let recent_blockhash = Hash::new_from_array(
+        message.recent_blockhash.as_slice().try_into().map_err(|_| "invalid recent blockhash")?
+    );
+    let instructions = message.instructions
+        .into_iter()
+        .map(|instruction| {
+            Ok(CompiledInstruction {
+                program_id_index: u8::try_from(instruction.program_id_index)?,
+                accounts: instruction.accounts,
+                data: instruction.data,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+    if !message.versioned {
+        return Ok(
+            VersionedMessage::Legacy(legacy::Message {
+                header,
+                account_keys,
+                recent_blockhash,
+                instructions,
+            })
+        );
+    }
+
+    let address_table_lookups = message.address_table_lookups
+        .into_iter()
+        .map(|lookup| {
+            Ok(MessageAddressTableLookup {
+                account_key: decode_pubkey(&lookup.account_key)?,
+                writable_indexes: lookup.writable_indexes,
+                readonly_indexes: lookup.readonly_indexes,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+    Ok(
+        VersionedMessage::V0(v0::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        })
+    )
+}
+
+fn decode_meta(
+    meta: proto::TransactionStatusMeta
+) -> Result<TransactionStatusMeta, Box<dyn Error + Send + Sync>> {
+    let inner_instructions = meta.inner_instructions
+        .into_iter()
+        .map(|inner| {
+            Ok(InnerInstructions {
+                index: u8::try_from(inner.index)?,
+                instructions: inner.instructions
+                    .into_iter()
+                    .map(|instruction| {
+                        Ok(InnerInstruction {
+                            instruction: CompiledInstruction {
+                                program_id_index: u8::try_from(instruction.program_id_index)?,
+                                accounts: instruction.accounts,
+                                data: instruction.data,
+                            },
+                            stack_height: instruction.stack_height,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+    Ok(TransactionStatusMeta {
+        // The subscription filters out failed transactions
+        status: Ok(()),
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions: (!meta.inner_instructions_none).then_some(inner_instructions),
+        log_messages: (!meta.log_messages_none).then_some(meta.log_messages),
+        pre_token_balances: Some(decode_token_balances(meta.pre_token_balances)?),
+        post_token_balances: Some(decode_token_balances(meta.post_token_balances)?),
+        rewards: None,
+        loaded_addresses: LoadedAddresses {
+            writable: decode_pubkeys(&meta.loaded_writable_addresses)?,
+            readonly: decode_pubkeys(&meta.loaded_readonly_addresses)?,
+        },
+        return_data: None,
+        compute_units_consumed: meta.compute_units_consumed,
+    })
+}
+
+fn decode_token_balances(
+    balances: Vec<proto::TokenBalance>
+) -> Result<Vec<TransactionTokenBalance>, Box<dyn Error + Send + Sync>> {
+    balances
+        .into_iter()
+        .map(|balance| {
+            let amount = balance.ui_token_amount.unwrap_or_default();
+            Ok(TransactionTokenBalance {
+                account_index: u8::try_from(balance.account_index)?,
+                mint: balance.mint,
+                ui_token_amount: UiTokenAmount {
+                    ui_amount: Some(amount.ui_amount),
+                    decimals: u8::try_from(amount.decimals)?,
+                    amount: amount.amount,
+                    ui_amount_string: amount.ui_amount_string,
+                },
+                owner: balance.owner,
+                program_id: balance.program_id,
+            })
+        })
+        .collect()
+}
+
+fn decode_pubkeys(keys: &[Vec<u8>]) -> Result<Vec<Pubkey>, Box<dyn Error + Send + Sync>> {
+    keys.iter()
+        .map(|key| decode_pubkey(key))
+        .collect()
+}
+
+fn decode_pubkey(key: &[u8]) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+    Ok(Pubkey::try_from(key).map_err(|_| "invalid pubkey")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::detector::pool_detectors;
+    use crate::fixtures::{ self, AmmV4Creation };
+    use crate::swap::parse_new_pool;
+    use crate::utils::PoolType;
+    use solana_sdk::signature::Signer;
+    use std::convert::Infallible;
+    use std::future::{ self, Ready };
+    use std::task::{ Context, Poll };
+    use tokio::net::TcpListener;
+    use tonic::body::BoxBody;
+    use tonic::codegen::{ http, BoxFuture, Service };
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::{ Body, NamedService, Server };
+
+    /// A `geyser.Geyser` server that answers every `Subscribe` with the same updates,
+    /// then closes the stream.
+    #[derive(Clone)]
+    struct MockGeyser {
+        updates: Vec<proto::SubscribeUpdate>,
+    }
+
+    impl NamedService for MockGeyser {
+        const NAME: &'static str = "geyser.Geyser";
+    }
+
+    impl Service<http::Request<Body>> for MockGeyser {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            assert_eq!(request.uri().path(), SUBSCRIBE_PATH);
+            let subscribe = Subscribe { updates: self.updates.clone() };
+            Box::pin(async move {
+                let codec = ProstCodec::<
+                    proto::SubscribeUpdate,
+                    proto::SubscribeRequest
+                >::default();
+                Ok(tonic::server::Grpc::new(codec).streaming(subscribe, request).await)
+            })
+        }
+    }
+
+    struct Subscribe {
+        updates: Vec<proto::SubscribeUpdate>,
+    }
+
+    type UpdateStream = tokio_stream::Iter<
+        std::vec::IntoIter<Result<proto::SubscribeUpdate, tonic::Status>>
+    >;
+
+    impl Service<tonic::Request<Streaming<proto::SubscribeRequest>>> for Subscribe {
+        type Response = tonic::Response<UpdateStream>;
+        type Error = tonic::Status;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(
+            &mut self,
+            _request: tonic::Request<Streaming<proto::SubscribeRequest>>
+        ) -> Self::Future {
+            let updates: Vec<_> = self.updates.drain(..).map(Ok).collect();
+            future::ready(Ok(tonic::Response::new(tokio_stream::iter(updates))))
+        }
+    }
+
+    /// Serves `updates` on a free localhost port and returns its `http://` endpoint.
+    async fn serve(updates: Vec<proto::SubscribeUpdate>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder().add_service(MockGeyser { updates }).serve_with_incoming(incoming)
+        );
+        endpoint
+    }
+
+    /// The pool creation as Yellowstone streams it, the inverse of `encode_transaction`.
+    fn pool_creation_update(creation: &AmmV4Creation) -> proto::SubscribeUpdate {
+        let transaction = creation.transaction();
+        let meta = creation.status_meta();
+        let message = &transaction.message;
+        let header = message.header();
+
+        let compiled = |instruction: &CompiledInstruction| proto::CompiledInstruction {
+            program_id_index: u32::from(instruction.program_id_index),
+            accounts: instruction.accounts.clone(),
+            data: instruction.data.clone(),
+        };
+        let token_balances = |balances: Vec<TransactionTokenBalance>| {
+            balances
+                .into_iter()
+                .map(|balance| proto::TokenBalance {
+                    account_index: u32::from(balance.account_index),
+                    mint: balance.mint,
+                    ui_token_amount: Some(proto::UiTokenAmount {
+                        ui_amount: balance.ui_token_amount.ui_amount.unwrap_or_default(),
+                        decimals: u32::from(balance.ui_token_amount.decimals),
+                        amount: balance.ui_token_amount.amount,
+                        ui_amount_string: balance.ui_token_amount.ui_amount_string,
+                    }),
+                    owner: balance.owner,
+                    program_id: balance.program_id,
+                })
+                .collect()
+        };
+
+        let info = proto::SubscribeUpdateTransactionInfo {
+            signature: creation.signature().as_ref().to_vec(),
+            is_vote: false,
+            transaction: Some(proto::Transaction {
+                signatures: transaction.signatures
+                    .iter()
+                    .map(|signature| signature.as_ref().to_vec())
+                    .collect(),
+                message: Some(proto::Message {
+                    header: Some(proto::MessageHeader {
+                        num_required_signatures: u32::from(header.num_required_signatures),
+                        num_readonly_signed_accounts: u32::from(
+                            header.num_readonly_signed_accounts
+                        ),
+                        num_readonly_unsigned_accounts: u32::from(
+                            header.num_readonly_unsigned_accounts
+                        ),
+                    }),
+                    account_keys: message
+                        .static_account_keys()
+                        .iter()
+                        .map(|key| key.to_bytes().to_vec())
+                        .collect(),
+                    recent_blockhash: message.recent_blockhash().to_bytes().to_vec(),
+                    instructions: message.instructions().iter().map(compiled).collect(),
+                    versioned: false,
+                    address_table_lookups: Vec::new(),
+                }),
+            }),
+            meta: Some(proto::TransactionStatusMeta {
+                fee: meta.fee,
+                pre_balances: meta.pre_balances,
+                post_balances: meta.post_balances,
+                inner_instructions: meta.inner_instructions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|inner| proto::InnerInstructions {
+                        index: u32::from(inner.index),
+                        instructions: inner.instructions
+                            .iter()
+                            .map(|instruction| {
+                                let compiled = compiled(&instruction.instruction);
+                                proto::InnerInstruction {
+                                    program_id_index: compiled.program_id_index,
+                                    accounts: compiled.accounts,
+                                    data: compiled.data,
+                                    stack_height: instruction.stack_height,
+                                }
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                log_messages: meta.log_messages.unwrap_or_default(),
+                pre_token_balances: token_balances(meta.pre_token_balances.unwrap_or_default()),
+                post_token_balances: token_balances(meta.post_token_balances.unwrap_or_default()),
+                compute_units_consumed: meta.compute_units_consumed,
+                ..Default::default()
+            }),
+            index: 0,
+        };
+
+        proto::SubscribeUpdate {
+            filters: vec![FILTER_NAME.to_string()],
+            update_oneof: Some(
+                UpdateOneof::Transaction(proto::SubscribeUpdateTransaction {
+                    transaction: Some(info),
+                    slot: 1,
+                })
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_a_new_pool_from_the_subscription() {
+        let creation = AmmV4Creation::new();
+        let config = Config::default();
+        let geyser = GeyserConfig {
+            endpoint: serve(vec![pool_creation_update(&creation)]).await,
+            ..GeyserConfig::default()
+        };
+
+        let mut subscription = GeyserSubscription::subscribe(
+            &geyser,
+            vec![fixtures::RAYDIUM_AMM_V4_PROGRAM_ID.to_string()],
+            pool_detectors(&config)
+        ).await.unwrap();
+
+        let transaction = match subscription.next().await {
+            Some(Ok(GeyserUpdate::NewPool(transaction))) => transaction,
+            Some(Ok(_)) => panic!("expected a new pool"),
+            Some(Err(err)) => panic!("subscription failed: {}", err),
+            None => panic!("stream closed before the pool was sent"),
+        };
+        assert_eq!(transaction.signature, creation.signature().to_string());
+
+        let pool_info = parse_new_pool(transaction.transaction, &pool_detectors(&config)).unwrap();
+        assert_eq!(pool_info.pool_type, PoolType::AmmV4);
+        assert_eq!(pool_info.id, creation.pool_id);
+        assert_eq!(pool_info.base_mint, creation.base_mint);
+        assert_eq!(pool_info.quote_mint, spl_token::native_mint::id());
+        assert_eq!(pool_info.lp_mint, creation.lp_mint);
+        assert_eq!(pool_info.base_vault, creation.base_vault);
+        assert_eq!(pool_info.quote_vault, creation.quote_vault);
+        assert_eq!(pool_info.market_id, creation.market_id);
+        assert_eq!(pool_info.base_reserve, fixtures::INIT_COIN_AMOUNT);
+        assert_eq!(pool_info.quote_reserve, fixtures::INIT_PC_AMOUNT);
+        assert_eq!(pool_info.lp_reserve, fixtures::LP_AMOUNT);
+        assert_eq!(pool_info.open_time, fixtures::OPEN_TIME);
+        assert_eq!(pool_info.creator, creation.creator.pubkey());
+
+        assert!(subscription.next().await.is_none());
+    }
+}
@@ -5,55 +5,112 @@ mod utils;
 mod redis;
 mod mongo;
 mod rugcheck;
+mod rug_rules;
+mod metadata;
+mod config;
+mod wallet;
+mod raydium_swap;
+mod quote;
+mod sell;
+mod backtest;
+mod rpc;
+mod dedup;
+mod context;
+mod rpc_pool;
+mod pump_sdk;
+mod pump_swap;
+mod detector;
+mod meteora;
+mod orca;
+#[cfg(feature = "geyser")]
+mod geyser;
+#[cfg(test)]
+mod fixtures;
 use dotenv::dotenv;
 use buy::listen_for_buys;
-use solana_client::{ nonblocking::pubsub_client::PubsubClient, rpc_client::RpcClient };
+use config::{ Config, Executor };
+use context::AppContext;
+use rpc::SolanaRpc;
+use rpc_pool::RpcPool;
 use std::sync::Arc;
-use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
+use solana_sdk::signature::Keypair;
+use wallet::{ get_wallet_balance, load_keypair };
 
-async fn check_wsol_balance(
-    rpc_client: &RpcClient,
-    required_wsol: f64
+async fn check_wallet_balance(
+    rpc_client: &dyn SolanaRpc,
+    keypair: &Keypair,
+    required_wsol: f64,
+    required_sol: f64
 ) -> Result<bool, Box<dyn std::error::Error>> {
-    let account_info = rpc_client.get_account(
-        &Pubkey::from_str("3rzKBn91t3ttL23by55oo9h5Ag89nCdvFbHwvs58Uj52").unwrap()
+    let balance = get_wallet_balance(rpc_client, keypair).await?;
+    println!(
+        "Wallet {}: {} WSOL in {}, {} SOL for fees",
+        balance.owner,
+        balance.wsol_balance,
+        balance.wsol_account,
+        balance.sol_balance
     );
 
-    match account_info {
-        Ok(account) => {
-            let lamports = account.lamports;
-            let wsol_balance = (lamports as f64) / ((10u64).pow(9) as f64); // Convert lamports to WSOL
-            Ok(wsol_balance >= required_wsol)
-        }
-        Err(_) => Ok(false),
-    }
+    Ok(balance.wsol_balance >= required_wsol && balance.sol_balance >= required_sol)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
-    let wss_endpoint = std::env
-        ::var("WSS_URL")
-        .expect("You must set the WSS environment variable!");
-    let rpc_endpoint = std::env
-        ::var("RPC_URL")
-        .expect("You must set the RPC_URL environment variable!");
-
-    let program_address = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"; // RAYDIUM_PUBLIC_KEY
-    let rpc_client: Arc<RpcClient> = Arc::new(RpcClient::new(rpc_endpoint.to_string()));
-    let pubsub_client: PubsubClient = PubsubClient::new(&wss_endpoint).await?;
-    let wsol_amount = 0.024;
+    let config = Arc::new(Config::load()?);
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("backtest") {
+        return backtest::run(&args[2..], &config).await;
+    }
+
+    let keypair = Arc::new(load_keypair(&config.wallet.keypair_path)?);
+    let http_urls = config.http_urls()?;
+    let ws_urls = config.ws_urls()?;
+
+    let rpc_client: Arc<dyn SolanaRpc> = Arc::new(RpcPool::new(http_urls, &config.rpc));
+    // Native buys that wrap SOL themselves spend it from the wallet instead of from WSOL
+    let (required_wsol, required_sol) = if
+        config.buy.executor == Executor::Native &&
+        config.buy.wrap_sol
+    {
+        (0.0, config.buy.amount_sol + config.wallet.min_sol_for_fees)
+    } else {
+        (config.buy.amount_sol, config.wallet.min_sol_for_fees)
+    };
+    let ctx = AppContext::new(
+        rpc_client.clone(),
+        config.clone(),
+        keypair.clone(),
+        ws_urls.clone()
+    ).await?;
+    tokio::spawn(ctx.clone().monitor_health());
+
+    if config.sell.enabled {
+        let sell_ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Err(err) = sell::monitor_positions(sell_ctx).await {
+                eprintln!("Position monitor stopped: {}", err);
+            }
+        });
+    }
 
     loop {
-        // Check WSOL balance before listening for buys
-        let enough_wsol = check_wsol_balance(&rpc_client, wsol_amount).await?;
-        if enough_wsol {
-            listen_for_buys(rpc_client.clone(), pubsub_client, program_address, wsol_amount).await?;
+        // Check the wallet can pay for a buy before listening for buys
+        let enough_balance = check_wallet_balance(
+            rpc_client.as_ref(),
+            &keypair,
+            required_wsol,
+            required_sol
+        ).await?;
+        if enough_balance {
+            listen_for_buys(ctx.clone(), &ws_urls).await?;
 
             break;
         } else {
-            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await; // Sleep for 1 hour
+            tokio::time::sleep(
+                tokio::time::Duration::from_secs(config.wallet.balance_check_interval_secs)
+            ).await;
         }
     }
 
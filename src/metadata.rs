@@ -0,0 +1,242 @@
+use crate::mongo::TokenMetadata;
+use crate::rpc::SolanaRpc;
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::{ BaseStateWithExtensions, StateWithExtensions };
+use spl_token_metadata_interface::state::TokenMetadata as Token2022Metadata;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::time::Duration;
+
+const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+
+#[derive(BorshDeserialize)]
+struct MetaplexCreator {
+    _address: Pubkey,
+    _verified: bool,
+    _share: u8,
+}
+
+/// The start of a Metaplex token metadata account, up to the mutability flag. Borsh stops
+/// there, the rest of the account is padding and later fields.
+#[derive(BorshDeserialize)]
+struct MetaplexMetadata {
+    _key: u8,
+    update_authority: Pubkey,
+    _mint: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    _seller_fee_basis_points: u16,
+    _creators: Option<Vec<MetaplexCreator>>,
+    _primary_sale_happened: bool,
+    is_mutable: bool,
+}
+
+/// Name, symbol and URI of a mint, from its Metaplex account or Token-2022 extension.
+#[derive(Debug, Clone)]
+pub struct OnChainMetadata {
+    pub update_authority: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub is_mutable: bool,
+}
+
+/// The JSON document the metadata URI points to. pump.fun puts the socials at the top
+/// level, other launchpads under `extensions`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OffChainMetadata {
+    pub description: String,
+    pub image: String,
+    pub twitter: String,
+    pub telegram: String,
+    pub website: String,
+    #[serde(rename = "createdOn")]
+    pub created_on: String,
+    pub extensions: OffChainExtensions,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OffChainExtensions {
+    pub twitter: String,
+    pub telegram: String,
+    pub website: String,
+}
+
+impl OffChainMetadata {
+    pub fn twitter(&self) -> &str {
+        first_non_empty(&self.twitter, &self.extensions.twitter)
+    }
+
+    pub fn telegram(&self) -> &str {
+        first_non_empty(&self.telegram, &self.extensions.telegram)
+    }
+
+    pub fn website(&self) -> &str {
+        first_non_empty(&self.website, &self.extensions.website)
+    }
+
+    pub fn has_socials(&self) -> bool {
+        !self.twitter().is_empty() || !self.telegram().is_empty() || !self.website().is_empty()
+    }
+}
+
+fn first_non_empty<'a>(value: &'a str, fallback: &'a str) -> &'a str {
+    if value.trim().is_empty() { fallback.trim() } else { value.trim() }
+}
+
+/// Everything known about a token's metadata. `off_chain` is `None` when the URI wasn't
+/// fetched or couldn't be read.
+#[derive(Debug, Clone)]
+pub struct FetchedMetadata {
+    pub on_chain: OnChainMetadata,
+    pub off_chain: Option<OffChainMetadata>,
+}
+
+impl FetchedMetadata {
+    /// The document stored alongside the pool, without a balance since nothing is held yet.
+    pub fn token_metadata(&self, mint: &Pubkey) -> TokenMetadata {
+        let off_chain = self.off_chain.clone().unwrap_or_default();
+        TokenMetadata {
+            name: self.on_chain.name.clone(),
+            symbol: self.on_chain.symbol.clone(),
+            balance: 0.0,
+            mint: mint.to_string(),
+            description: off_chain.description.clone(),
+            image: off_chain.image.clone(),
+            twitter: off_chain.twitter().to_string(),
+            created_on: off_chain.created_on.clone(),
+        }
+    }
+}
+
+/// Reads the JSON document behind a metadata URI.
+#[async_trait]
+pub trait MetadataFetcher: Send + Sync {
+    async fn fetch(&self, uri: &str) -> Result<OffChainMetadata, Box<dyn Error>>;
+}
+
+pub struct HttpMetadataFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpMetadataFetcher {
+    pub fn new(timeout: Duration) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(HttpMetadataFetcher { client })
+    }
+}
+
+#[async_trait]
+impl MetadataFetcher for HttpMetadataFetcher {
+    async fn fetch(&self, uri: &str) -> Result<OffChainMetadata, Box<dyn Error>> {
+        let url = match uri.strip_prefix("ipfs://") {
+            Some(cid) => format!("{}{}", IPFS_GATEWAY, cid),
+            None => uri.to_string(),
+        };
+        let response = self.client.get(&url).send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Answers from documents recorded up front, for backtests.
+#[derive(Default)]
+pub struct FakeMetadataFetcher {
+    documents: HashMap<String, OffChainMetadata>,
+}
+
+impl FakeMetadataFetcher {
+    pub fn new(documents: HashMap<String, OffChainMetadata>) -> Self {
+        FakeMetadataFetcher { documents }
+    }
+}
+
+#[async_trait]
+impl MetadataFetcher for FakeMetadataFetcher {
+    async fn fetch(&self, uri: &str) -> Result<OffChainMetadata, Box<dyn Error>> {
+        self.documents
+            .get(uri)
+            .cloned()
+            .ok_or_else(|| format!("Metadata document {} not found", uri).into())
+    }
+}
+
+/// The mint's metadata, `None` when it has neither a Metaplex account nor a Token-2022
+/// metadata extension.
+pub async fn fetch_on_chain_metadata(
+    client: &dyn SolanaRpc,
+    mint: &Pubkey
+) -> Result<Option<OnChainMetadata>, Box<dyn Error>> {
+    let metadata_program = Pubkey::from_str(METADATA_PROGRAM_ID)?;
+    let (metadata, _) = Pubkey::find_program_address(
+        &[b"metadata", metadata_program.as_ref(), mint.as_ref()],
+        &metadata_program
+    );
+    if let Ok(account) = client.get_account(&metadata).await {
+        let metadata = MetaplexMetadata::deserialize(&mut &account.data[..])?;
+        // Metaplex pads the strings to their maximum length with NULs
+        return Ok(
+            Some(OnChainMetadata {
+                update_authority: metadata.update_authority,
+                name: metadata.name.trim_end_matches('\0').to_string(),
+                symbol: metadata.symbol.trim_end_matches('\0').to_string(),
+                uri: metadata.uri.trim_end_matches('\0').to_string(),
+                is_mutable: metadata.is_mutable,
+            })
+        );
+    }
+
+    let mint_account = client.get_account(mint).await?;
+    if mint_account.owner != spl_token_2022::id() {
+        return Ok(None);
+    }
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)?;
+    let Ok(metadata) = mint.get_variable_len_extension::<Token2022Metadata>() else {
+        return Ok(None);
+    };
+    // Token-2022 metadata stays mutable for as long as it has an update authority
+    let update_authority: Option<Pubkey> = metadata.update_authority.into();
+    Ok(
+        Some(OnChainMetadata {
+            update_authority: update_authority.unwrap_or_default(),
+            name: metadata.name,
+            symbol: metadata.symbol,
+            uri: metadata.uri,
+            is_mutable: update_authority.is_some(),
+        })
+    )
+}
+
+/// The mint's on-chain metadata and, with a `fetcher`, the document its URI points to.
+/// Failing to read the document leaves `off_chain` empty rather than failing the fetch.
+pub async fn fetch_token_metadata(
+    client: &dyn SolanaRpc,
+    fetcher: Option<&dyn MetadataFetcher>,
+    mint: &Pubkey
+) -> Result<Option<FetchedMetadata>, Box<dyn Error>> {
+    let Some(on_chain) = fetch_on_chain_metadata(client, mint).await? else {
+        return Ok(None);
+    };
+
+    let off_chain = match fetcher {
+        Some(fetcher) if !on_chain.uri.is_empty() => {
+            match fetcher.fetch(&on_chain.uri).await {
+                Ok(document) => Some(document),
+                Err(err) => {
+                    eprintln!("Failed to fetch metadata {} of {}: {}", on_chain.uri, mint, err);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(Some(FetchedMetadata { on_chain, off_chain }))
+}
@@ -0,0 +1,190 @@
+use crate::detector::{
+    logs_instruction,
+    orient_sides,
+    parse_amount,
+    pool_info_from_sides,
+    CreationInstruction,
+    PoolDetector,
+    PoolSide,
+    PoolTransaction,
+};
+use crate::swap::{
+    find_mint_in_inner_instructions_by_mint_address,
+    get_info_amount,
+    PoolParseError,
+};
+use crate::utils::{ PoolInfo, PoolType };
+use solana_sdk::pubkey::Pubkey;
+
+// Anchor discriminators of the DLMM pair creations. Every variant starts with the pair,
+// bitmap extension, mints and reserves
+const DLMM_INITIALIZE_LB_PAIR: [u8; 8] = [45, 154, 237, 210, 221, 15, 166, 92];
+const DLMM_INITIALIZE_CUSTOMIZABLE_LB_PAIR: [u8; 8] = [46, 39, 41, 135, 111, 183, 200, 64];
+const DLMM_INITIALIZE_LB_PAIR2: [u8; 8] = [73, 59, 36, 120, 237, 83, 108, 198];
+const DLMM_INITIALIZE_CUSTOMIZABLE_LB_PAIR2: [u8; 8] = [243, 73, 129, 126, 51, 19, 241, 107];
+
+// Anchor discriminators of the dynamic AMM pool creations
+const DYNAMIC_INITIALIZE_PERMISSIONLESS_POOL: [u8; 8] = [118, 173, 41, 157, 173, 72, 97, 103];
+const DYNAMIC_INITIALIZE_WITH_FEE_TIER: [u8; 8] = [6, 135, 68, 147, 229, 82, 169, 113];
+const DYNAMIC_INITIALIZE_CUSTOMIZABLE: [u8; 8] = [145, 24, 172, 194, 219, 125, 3, 190];
+const DYNAMIC_INITIALIZE_WITH_CONFIG: [u8; 8] = [7, 166, 138, 171, 206, 171, 236, 244];
+const DYNAMIC_INITIALIZE_WITH_CONFIG2: [u8; 8] = [48, 149, 220, 130, 61, 11, 9, 178];
+
+/// Meteora DLMM (liquidity book) pairs. Pairs are created with empty reserves, liquidity
+/// is added to bins afterwards.
+pub struct MeteoraDlmmDetector {
+    pub program_id: Pubkey,
+}
+
+impl PoolDetector for MeteoraDlmmDetector {
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn is_creation_log(&self, log_messages: &[String]) -> bool {
+        logs_instruction(
+            log_messages,
+            &[
+                "InitializeLbPair",
+                "InitializeCustomizablePermissionlessLbPair",
+                "InitializeLbPair2",
+                "InitializeCustomizablePermissionlessLbPair2",
+            ]
+        )
+    }
+
+    fn is_creation_instruction(&self, data: &[u8]) -> bool {
+        matches!(
+            data.get(..8).and_then(|discriminator| discriminator.try_into().ok()),
+            Some(
+                DLMM_INITIALIZE_LB_PAIR |
+                    DLMM_INITIALIZE_CUSTOMIZABLE_LB_PAIR |
+                    DLMM_INITIALIZE_LB_PAIR2 |
+                    DLMM_INITIALIZE_CUSTOMIZABLE_LB_PAIR2
+            )
+        )
+    }
+
+    fn parse(
+        &self,
+        instruction: &CreationInstruction,
+        tx: &PoolTransaction
+    ) -> Result<PoolInfo, PoolParseError> {
+        // The v1 instructions take one token program, v2 one per mint after the token badges
+        let (token_program_x, token_program_y) = match instruction.discriminator() {
+            Some(DLMM_INITIALIZE_LB_PAIR2 | DLMM_INITIALIZE_CUSTOMIZABLE_LB_PAIR2) => {
+                (instruction.account(11)?, instruction.account(12)?)
+            }
+            _ => (instruction.account(9)?, instruction.account(9)?),
+        };
+
+        let side_x = PoolSide {
+            mint: instruction.account(2)?,
+            vault: instruction.account(4)?,
+            token_program: token_program_x,
+            decimals: tx.mint_decimals(&instruction.account(2)?)?,
+            reserve: 0,
+        };
+        let side_y = PoolSide {
+            mint: instruction.account(3)?,
+            vault: instruction.account(5)?,
+            token_program: token_program_y,
+            decimals: tx.mint_decimals(&instruction.account(3)?)?,
+            reserve: 0,
+        };
+        let (base, quote) = orient_sides(side_x, side_y);
+
+        Ok(PoolInfo {
+            id: instruction.account(0)?,
+            program_id: self.program_id,
+            observation_id: instruction.account(6)?,
+            ..pool_info_from_sides(PoolType::MeteoraDlmm, &base, &quote)
+        })
+    }
+}
+
+/// Meteora dynamic AMM pools. Their tokens are deposited into Meteora vaults shared by
+/// every pool of the mint, so the reserves come from what the creation added to them.
+pub struct MeteoraDynamicDetector {
+    pub program_id: Pubkey,
+}
+
+impl PoolDetector for MeteoraDynamicDetector {
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn is_creation_log(&self, log_messages: &[String]) -> bool {
+        logs_instruction(
+            log_messages,
+            &[
+                "InitializePermissionlessPool",
+                "InitializePermissionlessPoolWithFeeTier",
+                "InitializeCustomizablePermissionlessConstantProductPool",
+                "InitializePermissionlessConstantProductPoolWithConfig",
+                "InitializePermissionlessConstantProductPoolWithConfig2",
+            ]
+        )
+    }
+
+    fn is_creation_instruction(&self, data: &[u8]) -> bool {
+        matches!(
+            data.get(..8).and_then(|discriminator| discriminator.try_into().ok()),
+            Some(
+                DYNAMIC_INITIALIZE_PERMISSIONLESS_POOL |
+                    DYNAMIC_INITIALIZE_WITH_FEE_TIER |
+                    DYNAMIC_INITIALIZE_CUSTOMIZABLE |
+                    DYNAMIC_INITIALIZE_WITH_CONFIG |
+                    DYNAMIC_INITIALIZE_WITH_CONFIG2
+            )
+        )
+    }
+
+    fn parse(
+        &self,
+        instruction: &CreationInstruction,
+        tx: &PoolTransaction
+    ) -> Result<PoolInfo, PoolParseError> {
+        // The config variants take the config account right after the pool
+        let (config_id, offset) = match instruction.discriminator() {
+            Some(DYNAMIC_INITIALIZE_WITH_CONFIG | DYNAMIC_INITIALIZE_WITH_CONFIG2) => {
+                (instruction.account(1)?, 1)
+            }
+            _ => (Pubkey::default(), 0),
+        };
+        let account = |index: usize| instruction.account(index + offset);
+
+        let lp_mint = account(1)?;
+        let lp_mint_instruction = find_mint_in_inner_instructions_by_mint_address(
+            &tx.inner_instructions,
+            &lp_mint.to_string()
+        ).ok_or_else(|| PoolParseError::LpMintToNotFound(lp_mint.to_string()))?;
+
+        let side_a = PoolSide {
+            mint: account(2)?,
+            vault: account(6)?,
+            token_program: spl_token::id(),
+            decimals: tx.mint_decimals(&account(2)?)?,
+            reserve: tx.token_balance_increase(&account(6)?)?,
+        };
+        let side_b = PoolSide {
+            mint: account(3)?,
+            vault: account(7)?,
+            token_program: spl_token::id(),
+            decimals: tx.mint_decimals(&account(3)?)?,
+            reserve: tx.token_balance_increase(&account(7)?)?,
+        };
+        let (base, quote) = orient_sides(side_a, side_b);
+
+        Ok(PoolInfo {
+            id: account(0)?,
+            lp_mint,
+            lp_decimals: tx.mint_decimals(&lp_mint).unwrap_or(base.decimals),
+            program_id: self.program_id,
+            lp_vault: account(14)?,
+            lp_reserve: parse_amount(&get_info_amount(lp_mint_instruction)?)?,
+            config_id,
+            ..pool_info_from_sides(PoolType::MeteoraDynamic, &base, &quote)
+        })
+    }
+}
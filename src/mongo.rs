@@ -4,8 +4,11 @@ use serde::Serialize;
 use serde::Deserialize;
 use futures::stream::TryStreamExt;
 use mongodb::bson::DateTime;
+use mongodb::bson::oid::ObjectId;
+use crate::rug_rules::RugVerdict;
+use crate::utils::PoolType;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct TokenMetadata {
     pub name: String,
     pub symbol: String,
@@ -17,7 +20,7 @@ pub struct TokenMetadata {
     pub created_on: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BuyTransaction {
     pub transaction_signature: String,
     pub token_info: TokenInfo,
@@ -27,6 +30,14 @@ pub struct BuyTransaction {
     pub entry_price: f64,
     pub token_metadata: TokenMetadata,
     pub created_at: DateTime,
+    #[serde(default)]
+    pub sold: bool,
+    /// Take-profit levels already sold, kept so a restart doesn't sell them again
+    #[serde(default)]
+    pub levels_hit: u32,
+    /// Tokens left after partial sells, `None` before the first one
+    #[serde(default)]
+    pub remaining: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,8 +59,36 @@ pub struct TokenInfo {
     pub quote_mint: String,
     pub base_vault: String,
     pub quote_vault: String,
+    /// Set for positions still on a pump.fun bonding curve, priced and sold through the curve
+    #[serde(default)]
+    pub bonding_curve: Option<String>,
+    #[serde(default)]
+    pub pool_type: PoolType,
+    /// Pool account, needed to price CLMM positions whose vaults span every price range
+    #[serde(default)]
+    pub pool_id: Option<String>,
+    /// Swap fee of the pool, `None` on positions recorded without it, which are quoted at
+    /// the AMM v4 fee
+    #[serde(default)]
+    pub trade_fee_bps: Option<u64>,
 }
 
+/// The rug check verdict of a pool, stored for every pool evaluated, bought or not.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolVerdict {
+    pub pool_id: String,
+    pub base_mint: String,
+    pub pool_type: PoolType,
+    pub creator: String,
+    pub verdict: RugVerdict,
+    /// `None` when the mint has no metadata
+    #[serde(default)]
+    pub token_metadata: Option<TokenMetadata>,
+    pub created_at: DateTime,
+}
+
+/// Cheap to clone, clones share the driver's connection pool.
+#[derive(Clone)]
 pub struct MongoHandler {
     client: Client,
 }
@@ -68,6 +107,12 @@ impl MongoHandler {
         Ok(Self { client })
     }
 
+    /// Round trip to the server; the driver itself re-establishes dropped pool connections.
+    pub async fn ping(&self) -> Result<(), MongoError> {
+        self.client.database("admin").run_command(doc! { "ping": 1 }, None).await?;
+        Ok(())
+    }
+
     pub async fn fetch_all_tokens(
         &self,
         db_name: &str,
@@ -84,9 +129,10 @@ impl MongoHandler {
             // Extract the `token_metadata` field from the document
             if let Some(token_metadata_bson) = doc.get("token_metadata") {
                 // Check if the extracted value is a document
-                let sold = doc.get("sold").unwrap().as_bool().unwrap();
+                // Positions recorded before `sold` existed are still open
+                let sold = doc.get_bool("sold").unwrap_or(false);
 
-                if sold == true {
+                if sold {
                     continue;
                 } else {
                     if let Some(token_metadata_doc) = token_metadata_bson.as_document() {
@@ -117,4 +163,122 @@ impl MongoHandler {
 
         Ok(tokens) // Return the vector of tokens
     }
+
+    /// Returns every position in the collection that has not been marked `sold` yet,
+    /// together with its document id. Positions recorded before `sold` existed are open.
+    pub async fn fetch_open_positions(
+        &self,
+        db_name: &str,
+        collection_name: &str
+    ) -> Result<Vec<(ObjectId, BuyTransaction)>, MongoError> {
+        let my_coll: Collection<Document> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        let mut cursor = my_coll.find(doc! { "sold": { "$ne": true } }, None).await?;
+        let mut positions: Vec<(ObjectId, BuyTransaction)> = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await? {
+            let id = doc
+                .get_object_id("_id")
+                .map_err(|_| MongoError::custom("Position _id is not an ObjectId".to_string()))?;
+            let position = bson::from_document::<BuyTransaction>(doc).map_err(|_| {
+                MongoError::custom("Failed to deserialize position".to_string())
+            })?;
+            positions.push((id, position));
+        }
+
+        Ok(positions)
+    }
+
+    /// Records a bought token as an open position.
+    pub async fn insert_position(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        position: &BuyTransaction
+    ) -> Result<(), MongoError> {
+        let my_coll: Collection<BuyTransaction> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.insert_one(position, None).await?;
+        Ok(())
+    }
+
+    pub async fn insert_sell(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        sell: &SellTransaction
+    ) -> Result<(), MongoError> {
+        let my_coll: Collection<SellTransaction> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.insert_one(sell, None).await?;
+        Ok(())
+    }
+
+    pub async fn insert_verdict(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        verdict: &PoolVerdict
+    ) -> Result<(), MongoError> {
+        let my_coll: Collection<PoolVerdict> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.insert_one(verdict, None).await?;
+        Ok(())
+    }
+
+    pub async fn count_pools_by_creator(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        creator: &str
+    ) -> Result<u64, MongoError> {
+        let my_coll: Collection<Document> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.count_documents(doc! { "creator": creator }, None).await
+    }
+
+    /// Stores how far a partially sold position got down the take-profit ladder.
+    pub async fn update_position_progress(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        id: ObjectId,
+        levels_hit: u32,
+        remaining: f64
+    ) -> Result<(), MongoError> {
+        let my_coll: Collection<Document> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.update_one(
+            doc! { "_id": id },
+            doc! { "$set": { "levels_hit": levels_hit, "remaining": remaining } },
+            None
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn mark_position_sold(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        id: ObjectId
+    ) -> Result<(), MongoError> {
+        let my_coll: Collection<Document> = self.client
+            .database(db_name)
+            .collection(collection_name);
+
+        my_coll.update_one(doc! { "_id": id }, doc! { "$set": { "sold": true } }, None).await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,90 @@
+use crate::detector::{
+    logs_instruction,
+    orient_sides,
+    pool_info_from_sides,
+    CreationInstruction,
+    PoolDetector,
+    PoolSide,
+    PoolTransaction,
+};
+use crate::swap::PoolParseError;
+use crate::utils::{ PoolInfo, PoolType };
+use solana_sdk::pubkey::Pubkey;
+
+// Anchor discriminators of the Whirlpool creations
+const INITIALIZE_POOL: [u8; 8] = [95, 180, 10, 172, 84, 174, 232, 40];
+const INITIALIZE_POOL_V2: [u8; 8] = [207, 45, 87, 242, 27, 63, 204, 67];
+
+/// Orca Whirlpools. Pools are created with empty vaults, liquidity comes with a later
+/// position.
+pub struct OrcaWhirlpoolDetector {
+    pub program_id: Pubkey,
+}
+
+impl PoolDetector for OrcaWhirlpoolDetector {
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn is_creation_log(&self, log_messages: &[String]) -> bool {
+        logs_instruction(log_messages, &["InitializePool", "InitializePoolV2"])
+    }
+
+    fn is_creation_instruction(&self, data: &[u8]) -> bool {
+        matches!(
+            data.get(..8).and_then(|discriminator| discriminator.try_into().ok()),
+            Some(INITIALIZE_POOL | INITIALIZE_POOL_V2)
+        )
+    }
+
+    fn parse(
+        &self,
+        instruction: &CreationInstruction,
+        tx: &PoolTransaction
+    ) -> Result<PoolInfo, PoolParseError> {
+        // v2 adds a token badge per mint before the funder and a token program per mint
+        let (pool, vault_a, vault_b, token_program_a, token_program_b) = match
+            instruction.discriminator()
+        {
+            Some(INITIALIZE_POOL_V2) =>
+                (
+                    instruction.account(6)?,
+                    instruction.account(7)?,
+                    instruction.account(8)?,
+                    instruction.account(10)?,
+                    instruction.account(11)?,
+                ),
+            _ =>
+                (
+                    instruction.account(4)?,
+                    instruction.account(5)?,
+                    instruction.account(6)?,
+                    instruction.account(8)?,
+                    instruction.account(8)?,
+                ),
+        };
+
+        let side_a = PoolSide {
+            mint: instruction.account(1)?,
+            vault: vault_a,
+            token_program: token_program_a,
+            decimals: tx.mint_decimals(&instruction.account(1)?)?,
+            reserve: 0,
+        };
+        let side_b = PoolSide {
+            mint: instruction.account(2)?,
+            vault: vault_b,
+            token_program: token_program_b,
+            decimals: tx.mint_decimals(&instruction.account(2)?)?,
+            reserve: 0,
+        };
+        let (base, quote) = orient_sides(side_a, side_b);
+
+        Ok(PoolInfo {
+            id: pool,
+            program_id: self.program_id,
+            config_id: instruction.account(0)?,
+            ..pool_info_from_sides(PoolType::OrcaWhirlpool, &base, &quote)
+        })
+    }
+}
@@ -0,0 +1,200 @@
+use crate::quote::{ quote_exact_in, Quote };
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use borsh::{ BorshDeserialize, BorshSerialize };
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+use std::error::Error;
+
+// pump.fun takes 1% of the SOL side of every bonding curve trade
+pub const PUMP_FEE_BPS: u64 = 100;
+// Every pump.fun mint has 6 decimals
+pub const PUMP_TOKEN_DECIMALS: u8 = 6;
+
+pub const PUMP_FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
+
+pub const GLOBAL_SEED: &[u8] = b"global";
+pub const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+pub const CREATOR_VAULT_SEED: &[u8] = b"creator-vault";
+
+// Anchor discriminators, the first 8 bytes of sha256("<namespace>:<name>")
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+pub const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+pub const CREATE_EVENT_DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+pub const COMPLETE_EVENT_DISCRIMINATOR: [u8; 8] = [95, 114, 97, 156, 212, 46, 152, 8];
+
+const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Instruction data of the pump.fun `buy` and `sell` instructions. `buy` takes the token
+/// amount and the most SOL it may cost, `sell` the token amount and the least SOL to receive.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TradeInstructionData {
+    pub discriminator: [u8; 8],
+    pub amount: u64,
+    pub sol_limit: u64,
+}
+
+/// On-chain state of a pump.fun bonding curve account.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct BondingCurveAccount {
+    pub discriminator: [u8; 8],
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// Set once the curve sold out; trading moves to Raydium and the curve rejects trades
+    pub complete: bool,
+    /// Paid the creator fee of every trade, through its creator vault
+    pub creator: Pubkey,
+}
+
+impl BondingCurveAccount {
+    /// Decodes the account data, `None` if it is not a bonding curve. Fields appended by
+    /// later program versions are ignored.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.discriminator != BONDING_CURVE_DISCRIMINATOR {
+            return None;
+        }
+        Some(decoded)
+    }
+
+    /// SOL per token, in whole units.
+    pub fn price(&self) -> f64 {
+        if self.virtual_token_reserves == 0 {
+            return 0.0;
+        }
+        let sol = (self.virtual_sol_reserves as f64) / 1_000_000_000.0;
+        let tokens =
+            (self.virtual_token_reserves as f64) / (10_f64).powi(PUMP_TOKEN_DECIMALS as i32);
+        sol / tokens
+    }
+
+    /// Tokens bought for `sol_in` lamports. `min_amount_out` is the token amount to ask the
+    /// `buy` instruction for, with `sol_in` as its maximum cost.
+    pub fn quote_buy(&self, sol_in: u64, slippage_bps: u64) -> Result<Quote, Box<dyn Error>> {
+        if self.complete {
+            return Err("Bonding curve is complete".into());
+        }
+        let mut quote = quote_exact_in(
+            sol_in,
+            self.virtual_sol_reserves,
+            self.virtual_token_reserves,
+            PUMP_FEE_BPS,
+            slippage_bps
+        )?;
+        // The curve never sells more than it really holds
+        quote.amount_out = quote.amount_out.min(self.real_token_reserves);
+        quote.min_amount_out = quote.min_amount_out.min(self.real_token_reserves);
+        Ok(quote)
+    }
+}
+
+/// Emitted by the pump.fun `create` instruction. Borsh decodes fields in order, so the
+/// full layout is kept even where the sniper only needs the mint and curve.
+#[allow(dead_code)]
+#[derive(BorshDeserialize, Debug, Clone)]
+pub struct CreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub user: Pubkey,
+}
+
+/// Emitted by the trade that sells out a bonding curve, before the migration to Raydium.
+#[allow(dead_code)]
+#[derive(BorshDeserialize, Debug, Clone)]
+pub struct CompleteEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone)]
+pub enum PumpEvent {
+    Create(CreateEvent),
+    Complete(CompleteEvent),
+}
+
+/// Decodes the Anchor events pump.fun emitted as `Program data:` log lines. Other programs'
+/// data lines, trade events and anything that fails to decode are skipped.
+pub fn parse_events(log_messages: &[String]) -> Vec<PumpEvent> {
+    log_messages
+        .iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA_LOG_PREFIX))
+        .filter_map(|data| BASE64.decode(data).ok())
+        .filter_map(|data| decode_event(&data))
+        .collect()
+}
+
+fn decode_event(data: &[u8]) -> Option<PumpEvent> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, mut fields) = data.split_at(8);
+
+    // `deserialize` rather than `try_from_slice`, newer program versions append fields
+    if discriminator == CREATE_EVENT_DISCRIMINATOR {
+        CreateEvent::deserialize(&mut fields).ok().map(PumpEvent::Create)
+    } else if discriminator == COMPLETE_EVENT_DISCRIMINATOR {
+        CompleteEvent::deserialize(&mut fields).ok().map(PumpEvent::Complete)
+    } else {
+        None
+    }
+}
+
+pub fn get_global(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[GLOBAL_SEED], program_id).0
+}
+
+pub fn get_bonding_curve(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BONDING_CURVE_SEED, mint.as_ref()], program_id).0
+}
+
+/// Token account of the bonding curve that holds the tokens not sold yet.
+pub fn get_associated_bonding_curve(bonding_curve: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(bonding_curve, mint)
+}
+
+pub fn get_event_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id).0
+}
+
+/// Collects the creator fee of every bonding curve launched by `creator`.
+pub fn get_creator_vault(program_id: &Pubkey, creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CREATOR_VAULT_SEED, creator.as_ref()], program_id).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_creator_of_a_bonding_curve() {
+        let creator = Pubkey::new_unique();
+        let curve = BondingCurveAccount {
+            discriminator: BONDING_CURVE_DISCRIMINATOR,
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            real_sol_reserves: 0,
+            token_total_supply: 1_000_000_000_000_000,
+            complete: false,
+            creator,
+        };
+        // Padded like the on-chain account, which has room for fields added later
+        let mut data = curve.try_to_vec().unwrap();
+        data.resize(150, 0);
+
+        let decoded = BondingCurveAccount::decode(&data).unwrap();
+        assert_eq!(decoded.creator, creator);
+        assert_eq!(decoded.virtual_sol_reserves, 30_000_000_000);
+        assert!(!decoded.complete);
+    }
+}
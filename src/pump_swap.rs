@@ -0,0 +1,286 @@
+use crate::pump_sdk::{
+    self,
+    TradeInstructionData,
+    BUY_DISCRIMINATOR,
+    PUMP_FEE_RECIPIENT,
+    SELL_DISCRIMINATOR,
+};
+use borsh::BorshSerialize;
+use crate::rpc::SolanaRpc;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{ AccountMeta, Instruction };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ Keypair, Signature, Signer };
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::error::Error;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PumpSide {
+    /// Spend up to `sol_limit` lamports on `token_amount` tokens
+    Buy,
+    /// Sell `token_amount` tokens for at least `sol_limit` lamports
+    Sell,
+}
+
+#[derive(Debug, Clone)]
+pub struct PumpTradeParams {
+    pub side: PumpSide,
+    pub mint: Pubkey,
+    /// Creator recorded on the bonding curve, its vault is paid the creator fee
+    pub creator: Pubkey,
+    pub token_amount: u64,
+    pub sol_limit: u64,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+/// Accounts in the order of the `buy` instruction in the pump.fun IDL.
+pub fn buy_instruction(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+    user: &Pubkey,
+    token_amount: u64,
+    max_sol_cost: u64
+) -> Result<Instruction, Box<dyn Error>> {
+    let bonding_curve = pump_sdk::get_bonding_curve(program_id, mint);
+    let data = TradeInstructionData {
+        discriminator: BUY_DISCRIMINATOR,
+        amount: token_amount,
+        sol_limit: max_sol_cost,
+    }.try_to_vec()?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pump_sdk::get_global(program_id), false),
+        AccountMeta::new(Pubkey::from_str(PUMP_FEE_RECIPIENT)?, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(bonding_curve, false),
+        AccountMeta::new(pump_sdk::get_associated_bonding_curve(&bonding_curve, mint), false),
+        AccountMeta::new(get_associated_token_address(user, mint), false),
+        AccountMeta::new(*user, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(pump_sdk::get_creator_vault(program_id, creator), false),
+        AccountMeta::new_readonly(pump_sdk::get_event_authority(program_id), false),
+        AccountMeta::new_readonly(*program_id, false)
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Accounts in the order of the `sell` instruction in the pump.fun IDL, which puts the
+/// creator vault before the token program unlike `buy`.
+pub fn sell_instruction(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+    user: &Pubkey,
+    token_amount: u64,
+    min_sol_output: u64
+) -> Result<Instruction, Box<dyn Error>> {
+    let bonding_curve = pump_sdk::get_bonding_curve(program_id, mint);
+    let data = TradeInstructionData {
+        discriminator: SELL_DISCRIMINATOR,
+        amount: token_amount,
+        sol_limit: min_sol_output,
+    }.try_to_vec()?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pump_sdk::get_global(program_id), false),
+        AccountMeta::new(Pubkey::from_str(PUMP_FEE_RECIPIENT)?, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(bonding_curve, false),
+        AccountMeta::new(pump_sdk::get_associated_bonding_curve(&bonding_curve, mint), false),
+        AccountMeta::new(get_associated_token_address(user, mint), false),
+        AccountMeta::new(*user, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(pump_sdk::get_creator_vault(program_id, creator), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pump_sdk::get_event_authority(program_id), false),
+        AccountMeta::new_readonly(*program_id, false)
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Bonding curve trades are settled in native SOL, so unlike Raydium swaps there is no
+/// WSOL account to wrap or close.
+pub fn build_trade_instructions(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    params: &PumpTradeParams
+) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(params.compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(params.compute_unit_price)
+    ];
+
+    match params.side {
+        PumpSide::Buy => {
+            instructions.push(
+                create_associated_token_account_idempotent(
+                    owner,
+                    owner,
+                    &params.mint,
+                    &spl_token::id()
+                )
+            );
+            instructions.push(
+                buy_instruction(
+                    program_id,
+                    &params.mint,
+                    &params.creator,
+                    owner,
+                    params.token_amount,
+                    params.sol_limit
+                )?
+            );
+        }
+        PumpSide::Sell => {
+            instructions.push(
+                sell_instruction(
+                    program_id,
+                    &params.mint,
+                    &params.creator,
+                    owner,
+                    params.token_amount,
+                    params.sol_limit
+                )?
+            );
+        }
+    }
+
+    Ok(instructions)
+}
+
+pub async fn trade(
+    rpc_client: &dyn SolanaRpc,
+    keypair: &Keypair,
+    program_id: &Pubkey,
+    params: &PumpTradeParams
+) -> Result<Signature, Box<dyn Error>> {
+    let instructions = build_trade_instructions(program_id, &keypair.pubkey(), params)?;
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&keypair.pubkey()),
+        &[keypair],
+        recent_blockhash
+    );
+
+    let config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        ..RpcSendTransactionConfig::default()
+    };
+    let signature = rpc_client.send_transaction_with_config(&transaction, config).await?;
+
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::Message;
+
+    fn trade_params(side: PumpSide) -> PumpTradeParams {
+        PumpTradeParams {
+            side,
+            mint: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            token_amount: 1_000_000,
+            sol_limit: 10_000_000,
+            compute_unit_limit: 100_000,
+            compute_unit_price: 1,
+        }
+    }
+
+    /// Signs the trade like `trade` does and resolves the accounts of its pump.fun
+    /// instruction against the message keys, with whether each is writable.
+    fn pump_accounts(program_id: &Pubkey, params: &PumpTradeParams) -> Vec<(Pubkey, bool)> {
+        let keypair = Keypair::new();
+        let instructions = build_trade_instructions(program_id, &keypair.pubkey(), params).unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            Hash::default()
+        );
+        let message: &Message = &transaction.message;
+        let instruction = message.instructions
+            .iter()
+            .find(|instruction| instruction.program_id(&message.account_keys) == program_id)
+            .unwrap();
+
+        instruction.accounts
+            .iter()
+            .map(|&index| {
+                let index = index as usize;
+                (message.account_keys[index], message.is_writable(index))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buys_with_the_accounts_of_the_idl() {
+        let program_id = Config::default().pump_program_id();
+        let params = trade_params(PumpSide::Buy);
+        let bonding_curve = pump_sdk::get_bonding_curve(&program_id, &params.mint);
+        let accounts = pump_accounts(&program_id, &params);
+        let user = accounts[6].0;
+
+        assert_eq!(accounts, vec![
+            (pump_sdk::get_global(&program_id), false),
+            (Pubkey::from_str(PUMP_FEE_RECIPIENT).unwrap(), true),
+            (params.mint, false),
+            (bonding_curve, true),
+            (pump_sdk::get_associated_bonding_curve(&bonding_curve, &params.mint), true),
+            (get_associated_token_address(&user, &params.mint), true),
+            (user, true),
+            (system_program::id(), false),
+            (spl_token::id(), false),
+            (pump_sdk::get_creator_vault(&program_id, &params.creator), true),
+            (pump_sdk::get_event_authority(&program_id), false),
+            (program_id, false)
+        ]);
+    }
+
+    #[test]
+    fn sells_with_the_accounts_of_the_idl() {
+        let program_id = Config::default().pump_program_id();
+        let params = trade_params(PumpSide::Sell);
+        let bonding_curve = pump_sdk::get_bonding_curve(&program_id, &params.mint);
+        let accounts = pump_accounts(&program_id, &params);
+        let user = accounts[6].0;
+
+        assert_eq!(accounts, vec![
+            (pump_sdk::get_global(&program_id), false),
+            (Pubkey::from_str(PUMP_FEE_RECIPIENT).unwrap(), true),
+            (params.mint, false),
+            (bonding_curve, true),
+            (pump_sdk::get_associated_bonding_curve(&bonding_curve, &params.mint), true),
+            (get_associated_token_address(&user, &params.mint), true),
+            (user, true),
+            (system_program::id(), false),
+            (pump_sdk::get_creator_vault(&program_id, &params.creator), true),
+            (spl_token::id(), false),
+            (pump_sdk::get_event_authority(&program_id), false),
+            (program_id, false)
+        ]);
+    }
+}
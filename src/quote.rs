@@ -0,0 +1,249 @@
+use crate::utils::{ PoolInfo, PoolType };
+use crate::raydium_sdk::ClmmPoolState;
+use crate::rpc::SolanaRpc;
+use solana_sdk::pubkey::Pubkey;
+use std::error::Error;
+
+// Raydium AMM v4 trade fee, 25 / 10_000 = 0.25%
+pub const RAYDIUM_FEE_BPS: u64 = 25;
+const BPS_DENOMINATOR: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub min_amount_out: u64,
+    /// Price movement caused by the trade itself, fee excluded, in percent
+    pub price_impact_pct: f64,
+}
+
+/// Constant product quote for swapping `amount_in` of the token backing `reserve_in`.
+pub fn quote_exact_in(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_bps: u64,
+    slippage_bps: u64
+) -> Result<Quote, Box<dyn Error>> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err("Pool has no liquidity".into());
+    }
+    if fee_bps >= BPS_DENOMINATOR || slippage_bps > BPS_DENOMINATOR {
+        return Err("Fee and slippage must be below 10000 bps".into());
+    }
+
+    let amount_in_after_fee =
+        ((amount_in as u128) * ((BPS_DENOMINATOR - fee_bps) as u128)) / (BPS_DENOMINATOR as u128);
+    let amount_out =
+        ((reserve_out as u128) * amount_in_after_fee) /
+        ((reserve_in as u128) + amount_in_after_fee);
+    let min_amount_out =
+        (amount_out * ((BPS_DENOMINATOR - slippage_bps) as u128)) / (BPS_DENOMINATOR as u128);
+
+    let price_impact_pct =
+        ((amount_in_after_fee as f64) / ((reserve_in as f64) + (amount_in_after_fee as f64))) *
+        100.0;
+
+    Ok(Quote {
+        amount_in,
+        amount_out: amount_out as u64,
+        min_amount_out: min_amount_out as u64,
+        price_impact_pct,
+    })
+}
+
+/// Quotes a swap of `amount_in` of `input_mint` against the pool's base/quote reserves.
+/// Works in both directions, so the same call covers buys (WSOL in) and sells (token in).
+pub fn quote_pool(
+    pool_info: &PoolInfo,
+    base_reserve: u64,
+    quote_reserve: u64,
+    input_mint: &Pubkey,
+    amount_in: u64,
+    slippage_bps: u64
+) -> Result<Quote, Box<dyn Error>> {
+    if *input_mint == pool_info.base_mint {
+        quote_exact_in(
+            amount_in,
+            base_reserve,
+            quote_reserve,
+            pool_info.trade_fee_bps,
+            slippage_bps
+        )
+    } else if *input_mint == pool_info.quote_mint {
+        quote_exact_in(
+            amount_in,
+            quote_reserve,
+            base_reserve,
+            pool_info.trade_fee_bps,
+            slippage_bps
+        )
+    } else {
+        Err(format!("Mint {} is not part of pool {}", input_mint, pool_info.id).into())
+    }
+}
+
+/// Reads the current base and quote vault balances of the pool.
+pub async fn fetch_vault_reserves(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo
+) -> Result<(u64, u64), Box<dyn Error>> {
+    let base_balance = client.get_token_account_balance(&pool_info.base_vault).await?;
+    let quote_balance = client.get_token_account_balance(&pool_info.quote_vault).await?;
+    let base_reserve = base_balance.amount.parse()?;
+    let quote_reserve = quote_balance.amount.parse()?;
+    Ok((base_reserve, quote_reserve))
+}
+
+/// Current `(base, quote)` reserves to quote against. CLMM vaults hold liquidity of every
+/// price range, so those pools are quoted on the virtual reserves of the active liquidity.
+pub async fn fetch_pool_reserves(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo
+) -> Result<(u64, u64), Box<dyn Error>> {
+    if pool_info.pool_type != PoolType::Clmm {
+        return fetch_vault_reserves(client, pool_info).await;
+    }
+
+    let account = client.get_account(&pool_info.id).await?;
+    let pool_state = ClmmPoolState::decode(&account.data).ok_or_else(||
+        format!("{} is not a CLMM pool", pool_info.id)
+    )?;
+    let (reserve_0, reserve_1) = pool_state.virtual_reserves();
+    if pool_info.base_mint == pool_state.token_mint_0 {
+        Ok((reserve_0, reserve_1))
+    } else {
+        Ok((reserve_1, reserve_0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1000 SOL against 1000 tokens with 6 decimals
+    const SOL_RESERVE: u64 = 1_000_000_000_000;
+    const TOKEN_RESERVE: u64 = 1_000_000_000;
+
+    fn pool() -> PoolInfo {
+        PoolInfo {
+            id: Pubkey::new_unique(),
+            base_mint: Pubkey::new_unique(),
+            quote_mint: spl_token::native_mint::id(),
+            trade_fee_bps: RAYDIUM_FEE_BPS,
+            ..PoolInfo::default()
+        }
+    }
+
+    #[test]
+    fn quotes_a_buy_against_known_reserves() {
+        let quote = quote_exact_in(1_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 25, 500).unwrap();
+
+        assert_eq!(quote.amount_in, 1_000_000_000);
+        // 997_500_000 lamports after the fee, 1e9 * 997.5e6 / (1e12 + 997.5e6)
+        assert_eq!(quote.amount_out, 996_505);
+        assert_eq!(quote.min_amount_out, 946_679);
+        assert!((quote.price_impact_pct - 0.0996506).abs() < 1e-6);
+    }
+
+    #[test]
+    fn charges_the_raydium_fee() {
+        let without_fee = quote_exact_in(1_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 0, 0).unwrap();
+        let with_fee = quote_exact_in(
+            1_000_000_000,
+            SOL_RESERVE,
+            TOKEN_RESERVE,
+            RAYDIUM_FEE_BPS,
+            0
+        ).unwrap();
+
+        assert_eq!(without_fee.amount_out, 999_000);
+        assert_eq!(with_fee.amount_out, 996_505);
+        // The fee comes off the input, so the impact is measured on 0.25% less
+        assert!(with_fee.price_impact_pct < without_fee.price_impact_pct);
+    }
+
+    #[test]
+    fn price_impact_grows_with_trade_size() {
+        let small = quote_exact_in(1_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 25, 0).unwrap();
+        let large = quote_exact_in(100_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 25, 0).unwrap();
+
+        assert!(small.price_impact_pct < 0.1);
+        // 99.75 SOL into 1000 SOL moves the price by about 9%
+        assert!((large.price_impact_pct - 9.0700).abs() < 1e-3);
+    }
+
+    #[test]
+    fn min_amount_out_rounds_down() {
+        // 999 * 0.99 = 989.01, rounding up would ask for more than the tolerance allows
+        let quote = quote_exact_in(1_000, 1_000_000_000, 1_000_000_000, 0, 100).unwrap();
+        assert_eq!(quote.amount_out, 999);
+        assert_eq!(quote.min_amount_out, 989);
+
+        let no_slippage = quote_exact_in(1_000, 1_000_000_000, 1_000_000_000, 0, 0).unwrap();
+        assert_eq!(no_slippage.min_amount_out, no_slippage.amount_out);
+
+        let full_slippage = quote_exact_in(1_000, 1_000_000_000, 1_000_000_000, 0, 10_000).unwrap();
+        assert_eq!(full_slippage.min_amount_out, 0);
+    }
+
+    #[test]
+    fn rejects_empty_reserves() {
+        assert!(quote_exact_in(1_000, 0, TOKEN_RESERVE, 25, 500).is_err());
+        assert!(quote_exact_in(1_000, SOL_RESERVE, 0, 25, 500).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_fee_and_slippage() {
+        assert!(quote_exact_in(1_000, SOL_RESERVE, TOKEN_RESERVE, 10_000, 500).is_err());
+        assert!(quote_exact_in(1_000, SOL_RESERVE, TOKEN_RESERVE, 25, 10_001).is_err());
+    }
+
+    #[test]
+    fn handles_overflow_sized_inputs() {
+        let quote = quote_exact_in(u64::MAX, u64::MAX, u64::MAX, 25, 500).unwrap();
+        // Half the pool, less the fee, and never more than the output reserve
+        assert!(quote.amount_out < u64::MAX / 2);
+        assert!(quote.amount_out > u64::MAX / 2 - u64::MAX / 1000);
+        assert!(quote.min_amount_out <= quote.amount_out);
+
+        let drained = quote_exact_in(u64::MAX, 1, TOKEN_RESERVE, 25, 0).unwrap();
+        assert!(drained.amount_out < TOKEN_RESERVE);
+    }
+
+    #[test]
+    fn quote_pool_follows_the_input_side() {
+        let pool = pool();
+        let wsol = spl_token::native_mint::id();
+
+        let buy = quote_pool(&pool, TOKEN_RESERVE, SOL_RESERVE, &wsol, 1_000_000_000, 500);
+        let expected = quote_exact_in(1_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 25, 500);
+        assert_eq!(buy.unwrap(), expected.unwrap());
+
+        let sell = quote_pool(&pool, TOKEN_RESERVE, SOL_RESERVE, &pool.base_mint, 996_505, 500);
+        let sell = sell.unwrap();
+        assert_eq!(sell, quote_exact_in(996_505, TOKEN_RESERVE, SOL_RESERVE, 25, 500).unwrap());
+        // Selling straight back loses the fee twice plus the price impact
+        assert!(sell.amount_out < 1_000_000_000);
+    }
+
+    #[test]
+    fn quote_pool_uses_the_pool_fee() {
+        let pool = PoolInfo {
+            trade_fee_bps: 100,
+            ..pool()
+        };
+        let wsol = spl_token::native_mint::id();
+
+        let quote = quote_pool(&pool, TOKEN_RESERVE, SOL_RESERVE, &wsol, 1_000_000_000, 0);
+        let expected = quote_exact_in(1_000_000_000, SOL_RESERVE, TOKEN_RESERVE, 100, 0);
+        assert_eq!(quote.unwrap(), expected.unwrap());
+    }
+
+    #[test]
+    fn quote_pool_rejects_foreign_mints() {
+        let foreign_mint = Pubkey::new_unique();
+        let quote = quote_pool(&pool(), TOKEN_RESERVE, SOL_RESERVE, &foreign_mint, 1_000, 0);
+        assert!(quote.is_err());
+    }
+}
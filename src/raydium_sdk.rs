@@ -1,38 +1,13 @@
+use crate::utils::PoolType;
 use borsh::{ BorshDeserialize, BorshSerialize };
 use solana_sdk::pubkey::Pubkey;
-use serde::{ Serialize, Deserialize };
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LiquidityPoolKeysString {
-    id: String,
-    base_mint: String,
-    quote_mint: String,
-    lp_mint: String,
-    base_decimals: u8,
-    quote_decimals: u8,
-    lp_decimals: u8,
-    version: u8,
-    program_id: String,
-    authority: String,
-    open_orders: String,
-    target_orders: String,
-    base_vault: String,
-    quote_vault: String,
-    withdraw_queue: String,
-    lp_vault: String,
-    market_version: u8,
-    market_program_id: String,
-    market_id: String,
-    market_authority: String,
-    market_base_vault: String,
-    market_quote_vault: String,
-    market_bids: String,
-    market_asks: String,
-    market_event_queue: String,
-}
-
-#[derive(Debug, Serialize, Clone)]
+use serde::Serialize;
+
+/// Swap keys of a Raydium pool. The market fields are only set for AMM v4 pools, the
+/// config and observation accounts only for CPMM and CLMM pools.
+#[derive(Debug, Serialize, Clone, Default)]
 pub struct LiquidityPoolKeys {
+    pub pool_type: PoolType,
     pub id: Pubkey,
     pub base_mint: Pubkey,
     pub quote_mint: Pubkey,
@@ -58,45 +33,41 @@ pub struct LiquidityPoolKeys {
     pub market_bids: Pubkey,
     pub market_asks: Pubkey,
     pub market_event_queue: Pubkey,
+    pub config_id: Pubkey,
+    pub observation_id: Pubkey,
+    pub base_token_program: Pubkey,
+    pub quote_token_program: Pubkey,
 }
 
-impl From<LiquidityPoolKeys> for LiquidityPoolKeysString {
-    fn from(pool_keys: LiquidityPoolKeys) -> Self {
-        LiquidityPoolKeysString {
-            id: pool_keys.id.to_string(),
-            base_mint: pool_keys.base_mint.to_string(),
-            quote_mint: pool_keys.quote_mint.to_string(),
-            lp_mint: pool_keys.lp_mint.to_string(),
-            base_decimals: pool_keys.base_decimals,
-            quote_decimals: pool_keys.quote_decimals,
-            lp_decimals: pool_keys.lp_decimals,
-            version: pool_keys.version,
-            program_id: pool_keys.program_id.to_string(),
-            authority: pool_keys.authority.to_string(),
-            open_orders: pool_keys.open_orders.to_string(),
-            target_orders: pool_keys.target_orders.to_string(),
-            base_vault: pool_keys.base_vault.to_string(),
-            quote_vault: pool_keys.quote_vault.to_string(),
-            withdraw_queue: pool_keys.withdraw_queue.to_string(),
-            lp_vault: pool_keys.lp_vault.to_string(),
-            market_version: pool_keys.market_version,
-            market_program_id: pool_keys.market_program_id.to_string(),
-            market_id: pool_keys.market_id.to_string(),
-            market_authority: pool_keys.market_authority.to_string(),
-            market_base_vault: pool_keys.market_base_vault.to_string(),
-            market_quote_vault: pool_keys.market_quote_vault.to_string(),
-            market_bids: pool_keys.market_bids.to_string(),
-            market_asks: pool_keys.market_asks.to_string(),
-            market_event_queue: pool_keys.market_event_queue.to_string(),
-        }
-    }
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SwapInstructionData {
+    pub instruction: u8,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct SwapInstructionData {
-    instruction: u8,
-    amount_in: u64,
-    min_amount_out: u64,
+pub const INITIALIZE2_INSTRUCTION: u8 = 1;
+
+/// Instruction data of the Raydium AMM v4 `initialize2` instruction that creates a pool.
+/// `pc` is the quote side and `coin` the base side of the pool.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct Initialize2InstructionData {
+    pub instruction: u8,
+    pub nonce: u8,
+    pub open_time: u64,
+    pub init_pc_amount: u64,
+    pub init_coin_amount: u64,
+}
+
+impl Initialize2InstructionData {
+    /// Decodes the raw instruction bytes, `None` if they are not an `initialize2` call.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.instruction != INITIALIZE2_INSTRUCTION {
+            return None;
+        }
+        Some(decoded)
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -135,6 +106,273 @@ pub struct MarketStateLayoutV3 {
     _padding_end: [u8; 7],
 }
 
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+// Anchor discriminators of the CPMM and CLMM programs, sha256("<namespace>:<name>")[..8]
+pub const CPMM_INITIALIZE_DISCRIMINATOR: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
+pub const CPMM_SWAP_BASE_INPUT_DISCRIMINATOR: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+pub const CLMM_CREATE_POOL_DISCRIMINATOR: [u8; 8] = [233, 146, 209, 142, 207, 104, 64, 188];
+pub const CLMM_SWAP_V2_DISCRIMINATOR: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+pub const CLMM_POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+// Both programs call their pool account `PoolState`
+pub const CPMM_POOL_STATE_DISCRIMINATOR: [u8; 8] = CLMM_POOL_STATE_DISCRIMINATOR;
+pub const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+
+pub const CPMM_AUTH_SEED: &[u8] = b"vault_and_lp_mint_auth_seed";
+pub const CLMM_TICK_ARRAY_SEED: &[u8] = b"tick_array";
+pub const CLMM_BITMAP_EXTENSION_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+pub const CLMM_TICKS_PER_ARRAY: i32 = 60;
+
+/// Instruction data of the CPMM `initialize` instruction that creates a pool.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct CpmmInitializeInstructionData {
+    pub discriminator: [u8; 8],
+    pub init_amount_0: u64,
+    pub init_amount_1: u64,
+    pub open_time: u64,
+}
+
+impl CpmmInitializeInstructionData {
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.discriminator != CPMM_INITIALIZE_DISCRIMINATOR {
+            return None;
+        }
+        Some(decoded)
+    }
+}
+
+/// Instruction data of the CLMM `create_pool` instruction. The pool starts empty, liquidity
+/// comes with a later `open_position`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct ClmmCreatePoolInstructionData {
+    pub discriminator: [u8; 8],
+    pub sqrt_price_x64: u128,
+    pub open_time: u64,
+}
+
+impl ClmmCreatePoolInstructionData {
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.discriminator != CLMM_CREATE_POOL_DISCRIMINATOR {
+            return None;
+        }
+        Some(decoded)
+    }
+}
+
+/// Instruction data of the CPMM `swap_base_input` instruction.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CpmmSwapInstructionData {
+    pub discriminator: [u8; 8],
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+/// Instruction data of the CLMM `swap_v2` instruction. A zero `sqrt_price_limit_x64` lets
+/// the swap run to the end of the tick arrays passed.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ClmmSwapInstructionData {
+    pub discriminator: [u8; 8],
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+/// Leading fields of a CLMM `PoolState` account, enough to price and swap against it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct ClmmPoolState {
+    pub discriminator: [u8; 8],
+    pub bump: [u8; 1],
+    pub amm_config: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+impl ClmmPoolState {
+    /// Decodes the account data, `None` if it is not a CLMM pool.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.discriminator != CLMM_POOL_STATE_DISCRIMINATOR {
+            return None;
+        }
+        Some(decoded)
+    }
+
+    /// Constant product reserves `(token_0, token_1)` equivalent to the active liquidity at
+    /// the current price. Good for quoting trades that stay within the current tick range.
+    pub fn virtual_reserves(&self) -> (u64, u64) {
+        let sqrt_price = (self.sqrt_price_x64 as f64) / (2_f64).powi(64);
+        if sqrt_price == 0.0 {
+            return (0, 0);
+        }
+        let liquidity = self.liquidity as f64;
+        ((liquidity / sqrt_price) as u64, (liquidity * sqrt_price) as u64)
+    }
+
+    /// Price of token 0 in token 1, in whole units.
+    pub fn price(&self) -> f64 {
+        let sqrt_price = (self.sqrt_price_x64 as f64) / (2_f64).powi(64);
+        sqrt_price * sqrt_price *
+            (10_f64).powi((self.mint_decimals_0 as i32) - (self.mint_decimals_1 as i32))
+    }
+
+    /// Start index of the tick array holding the current tick and of the next `count - 1`
+    /// arrays in the swap direction.
+    pub fn tick_array_start_indexes(&self, zero_for_one: bool, count: usize) -> Vec<i32> {
+        let ticks_in_array = (self.tick_spacing as i32) * CLMM_TICKS_PER_ARRAY;
+        let current = self.tick_current.div_euclid(ticks_in_array) * ticks_in_array;
+        let step = if zero_for_one { -ticks_in_array } else { ticks_in_array };
+        (0..count as i32).map(|i| current + i * step).collect()
+    }
+}
+
+/// Leading fields of a CPMM `PoolState` account, enough to swap against it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+pub struct CpmmPoolState {
+    pub discriminator: [u8; 8],
+    pub amm_config: Pubkey,
+    pub pool_creator: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub token_0_program: Pubkey,
+    pub token_1_program: Pubkey,
+    pub observation_key: Pubkey,
+    pub auth_bump: u8,
+    pub status: u8,
+    pub lp_mint_decimals: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+}
+
+impl CpmmPoolState {
+    /// Decodes the account data, `None` if it is not a CPMM pool.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let decoded = Self::deserialize(&mut &data[..]).ok()?;
+        if decoded.discriminator != CPMM_POOL_STATE_DISCRIMINATOR {
+            return None;
+        }
+        Some(decoded)
+    }
+}
+
+/// Trade fee of a CPMM or CLMM `AmmConfig` account in basis points. The programs store it
+/// per million, at different offsets.
+pub fn decode_amm_config_fee_bps(pool_type: PoolType, data: &[u8]) -> Option<u64> {
+    if data.get(..8)? != AMM_CONFIG_DISCRIMINATOR {
+        return None;
+    }
+    let fee_rate = match pool_type {
+        // discriminator, bump, disable_create_pool, index: u16
+        PoolType::Cpmm => u64::from_le_bytes(data.get(12..20)?.try_into().ok()?),
+        // discriminator, bump, index: u16, owner, protocol_fee_rate: u32
+        PoolType::Clmm => u32::from_le_bytes(data.get(47..51)?.try_into().ok()?) as u64,
+        _ => {
+            return None;
+        }
+    };
+    Some(fee_rate / 100)
+}
+
+pub const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+
+/// On-chain state of a Raydium AMM v4 pool account (`LIQUIDITY_STATE_LAYOUT_V4`, 752 bytes).
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AmmInfoLayoutV4 {
+    pub status: u64,
+    pub nonce: u64,
+    pub max_order: u64,
+    pub depth: u64,
+    pub base_decimal: u64,
+    pub quote_decimal: u64,
+    pub state: u64,
+    pub reset_flag: u64,
+    pub min_size: u64,
+    pub vol_max_cut_ratio: u64,
+    pub amount_wave_ratio: u64,
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub min_price_multiplier: u64,
+    pub max_price_multiplier: u64,
+    pub system_decimal_value: u64,
+    pub min_separate_numerator: u64,
+    pub min_separate_denominator: u64,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub pnl_numerator: u64,
+    pub pnl_denominator: u64,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub base_need_take_pnl: u64,
+    pub quote_need_take_pnl: u64,
+    pub quote_total_pnl: u64,
+    pub base_total_pnl: u64,
+    pub pool_open_time: u64,
+    pub punish_pc_amount: u64,
+    pub punish_coin_amount: u64,
+    pub orderbook_to_init_time: u64,
+
+    pub swap_base_in_amount: u128,
+    pub swap_quote_out_amount: u128,
+    pub swap_base2_quote_fee: u64,
+    pub swap_quote_in_amount: u128,
+    pub swap_base_out_amount: u128,
+    pub swap_quote2_base_fee: u64,
+
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub open_orders: Pubkey,
+    pub market_id: Pubkey,
+    pub market_program_id: Pubkey,
+    pub target_orders: Pubkey,
+    pub withdraw_queue: Pubkey,
+    pub lp_vault: Pubkey,
+    pub owner: Pubkey,
+
+    pub lp_reserve: u64,
+
+    _padding: [u64; 3],
+}
+
+pub fn get_clmm_tick_array(program_id: &Pubkey, pool_id: &Pubkey, start_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[CLMM_TICK_ARRAY_SEED, pool_id.as_ref(), &start_index.to_be_bytes()],
+        program_id
+    ).0
+}
+
+pub fn get_clmm_bitmap_extension(program_id: &Pubkey, pool_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CLMM_BITMAP_EXTENSION_SEED, pool_id.as_ref()], program_id).0
+}
+
+/// The PDA that owns the vaults of every pool of the given AMM program.
+pub fn get_amm_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[AMM_AUTHORITY_SEED], program_id).0
+}
+
+/// The PDA that owns the vaults and LP mint of every pool of the given CPMM program.
+pub fn get_cpmm_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CPMM_AUTH_SEED], program_id).0
+}
+
 pub fn get_associated_authority(
     program_id: &Pubkey,
     market_id: &Pubkey
@@ -164,3 +402,54 @@ pub fn get_associated_authority(
 
     Err("Unable to find a valid program address".into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{ self, AmmV4Creation };
+    use solana_sdk::signature::Signer;
+    use std::str::FromStr;
+
+    #[test]
+    fn decodes_an_amm_v4_account() {
+        let creation = AmmV4Creation::new();
+        let account = creation.amm_account();
+        assert_eq!(account.data.len(), 752);
+
+        let amm_info = AmmInfoLayoutV4::try_from_slice(&account.data).unwrap();
+        assert_eq!(amm_info.status, 6);
+        assert_eq!(amm_info.nonce, 254);
+        assert_eq!(amm_info.base_decimal, fixtures::TOKEN_DECIMALS as u64);
+        assert_eq!(amm_info.quote_decimal, 9);
+        assert_eq!((amm_info.trade_fee_numerator, amm_info.trade_fee_denominator), (25, 10_000));
+        assert_eq!(amm_info.pool_open_time, fixtures::OPEN_TIME);
+        assert_eq!(amm_info.base_vault, creation.base_vault);
+        assert_eq!(amm_info.quote_vault, creation.quote_vault);
+        assert_eq!(amm_info.base_mint, creation.base_mint);
+        assert_eq!(amm_info.quote_mint, creation.quote_mint);
+        assert_eq!(amm_info.lp_mint, creation.lp_mint);
+        assert_eq!(amm_info.open_orders, creation.open_orders);
+        assert_eq!(amm_info.market_id, creation.market_id);
+        assert_eq!(amm_info.market_program_id, creation.market_program_id);
+        assert_eq!(amm_info.target_orders, creation.target_orders);
+        assert_eq!(amm_info.withdraw_queue, creation.withdraw_queue);
+        assert_eq!(amm_info.lp_vault, creation.temp_lp);
+        assert_eq!(amm_info.owner, creation.creator.pubkey());
+        assert_eq!(amm_info.lp_reserve, fixtures::LP_AMOUNT);
+    }
+
+    #[test]
+    fn rejects_truncated_amm_v4_accounts() {
+        let account = AmmV4Creation::new().amm_account();
+        assert!(AmmInfoLayoutV4::try_from_slice(&account.data[..751]).is_err());
+    }
+
+    #[test]
+    fn derives_the_mainnet_amm_authority() {
+        let program_id = Pubkey::from_str(fixtures::RAYDIUM_AMM_V4_PROGRAM_ID).unwrap();
+        assert_eq!(
+            get_amm_authority(&program_id).to_string(),
+            "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"
+        );
+    }
+}
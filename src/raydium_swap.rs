@@ -0,0 +1,338 @@
+use crate::raydium_sdk::{
+    self,
+    ClmmPoolState,
+    ClmmSwapInstructionData,
+    CpmmSwapInstructionData,
+    LiquidityPoolKeys,
+    SwapInstructionData,
+    CLMM_SWAP_V2_DISCRIMINATOR,
+    CPMM_SWAP_BASE_INPUT_DISCRIMINATOR,
+    MEMO_PROGRAM_ID,
+    TOKEN_2022_PROGRAM_ID,
+};
+use crate::utils::PoolType;
+use borsh::BorshSerialize;
+use crate::rpc::SolanaRpc;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{ AccountMeta, Instruction };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ Keypair, Signature, Signer };
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::error::Error;
+use std::str::FromStr;
+
+// Raydium AMM v4 `swapBaseIn` instruction discriminator
+const SWAP_BASE_IN_INSTRUCTION: u8 = 9;
+// Current tick array plus the next ones in the swap direction, enough for a sniper-sized buy
+const CLMM_TICK_ARRAY_COUNT: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct SwapParams {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    /// Transfer `amount_in` lamports into the WSOL account before swapping
+    pub wrap_sol: bool,
+    /// Close the WSOL account after swapping to get native SOL back
+    pub unwrap_sol: bool,
+}
+
+pub fn swap_base_in_instruction(
+    pool_keys: &LiquidityPoolKeys,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    amount_in: u64,
+    min_amount_out: u64
+) -> Result<Instruction, Box<dyn Error>> {
+    let data = SwapInstructionData {
+        instruction: SWAP_BASE_IN_INSTRUCTION,
+        amount_in,
+        min_amount_out,
+    }.try_to_vec()?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(pool_keys.id, false),
+        AccountMeta::new_readonly(pool_keys.authority, false),
+        AccountMeta::new(pool_keys.open_orders, false),
+        AccountMeta::new(pool_keys.target_orders, false),
+        AccountMeta::new(pool_keys.base_vault, false),
+        AccountMeta::new(pool_keys.quote_vault, false),
+        AccountMeta::new_readonly(pool_keys.market_program_id, false),
+        AccountMeta::new(pool_keys.market_id, false),
+        AccountMeta::new(pool_keys.market_bids, false),
+        AccountMeta::new(pool_keys.market_asks, false),
+        AccountMeta::new(pool_keys.market_event_queue, false),
+        AccountMeta::new(pool_keys.market_base_vault, false),
+        AccountMeta::new(pool_keys.market_quote_vault, false),
+        AccountMeta::new_readonly(pool_keys.market_authority, false),
+        AccountMeta::new(*user_source, false),
+        AccountMeta::new(*user_destination, false),
+        AccountMeta::new_readonly(*owner, true)
+    ];
+
+    Ok(Instruction {
+        program_id: pool_keys.program_id,
+        accounts,
+        data,
+    })
+}
+
+/// The mint, vault and token program of the pool side `mint` is on.
+fn pool_side(pool_keys: &LiquidityPoolKeys, mint: &Pubkey) -> (Pubkey, Pubkey) {
+    if *mint == pool_keys.base_mint {
+        (pool_keys.base_vault, pool_keys.base_token_program)
+    } else {
+        (pool_keys.quote_vault, pool_keys.quote_token_program)
+    }
+}
+
+pub fn cpmm_swap_base_input_instruction(
+    pool_keys: &LiquidityPoolKeys,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    params: &SwapParams
+) -> Result<Instruction, Box<dyn Error>> {
+    let data = CpmmSwapInstructionData {
+        discriminator: CPMM_SWAP_BASE_INPUT_DISCRIMINATOR,
+        amount_in: params.amount_in,
+        min_amount_out: params.min_amount_out,
+    }.try_to_vec()?;
+    let (input_vault, input_token_program) = pool_side(pool_keys, &params.input_mint);
+    let (output_vault, output_token_program) = pool_side(pool_keys, &params.output_mint);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(pool_keys.authority, false),
+        AccountMeta::new_readonly(pool_keys.config_id, false),
+        AccountMeta::new(pool_keys.id, false),
+        AccountMeta::new(*user_source, false),
+        AccountMeta::new(*user_destination, false),
+        AccountMeta::new(input_vault, false),
+        AccountMeta::new(output_vault, false),
+        AccountMeta::new_readonly(input_token_program, false),
+        AccountMeta::new_readonly(output_token_program, false),
+        AccountMeta::new_readonly(params.input_mint, false),
+        AccountMeta::new_readonly(params.output_mint, false),
+        AccountMeta::new(pool_keys.observation_id, false)
+    ];
+
+    Ok(Instruction {
+        program_id: pool_keys.program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `tick_arrays` are the initialized tick arrays the swap may cross, in swap order.
+pub fn clmm_swap_v2_instruction(
+    pool_keys: &LiquidityPoolKeys,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    tick_arrays: &[Pubkey],
+    params: &SwapParams
+) -> Result<Instruction, Box<dyn Error>> {
+    let data = ClmmSwapInstructionData {
+        discriminator: CLMM_SWAP_V2_DISCRIMINATOR,
+        amount: params.amount_in,
+        other_amount_threshold: params.min_amount_out,
+        sqrt_price_limit_x64: 0,
+        is_base_input: true,
+    }.try_to_vec()?;
+    let (input_vault, _) = pool_side(pool_keys, &params.input_mint);
+    let (output_vault, _) = pool_side(pool_keys, &params.output_mint);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(pool_keys.config_id, false),
+        AccountMeta::new(pool_keys.id, false),
+        AccountMeta::new(*user_source, false),
+        AccountMeta::new(*user_destination, false),
+        AccountMeta::new(input_vault, false),
+        AccountMeta::new(output_vault, false),
+        AccountMeta::new(pool_keys.observation_id, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(Pubkey::from_str(TOKEN_2022_PROGRAM_ID)?, false),
+        AccountMeta::new_readonly(Pubkey::from_str(MEMO_PROGRAM_ID)?, false),
+        AccountMeta::new_readonly(params.input_mint, false),
+        AccountMeta::new_readonly(params.output_mint, false),
+        AccountMeta::new(
+            raydium_sdk::get_clmm_bitmap_extension(&pool_keys.program_id, &pool_keys.id),
+            false
+        )
+    ];
+    accounts.extend(tick_arrays.iter().map(|tick_array| AccountMeta::new(*tick_array, false)));
+
+    Ok(Instruction {
+        program_id: pool_keys.program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `tick_arrays` is only used by CLMM pools, see `fetch_tick_arrays`.
+pub fn build_swap_instructions(
+    pool_keys: &LiquidityPoolKeys,
+    owner: &Pubkey,
+    params: &SwapParams,
+    tick_arrays: &[Pubkey]
+) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let wsol_mint = spl_token::native_mint::id();
+    let (_, input_token_program) = pool_side(pool_keys, &params.input_mint);
+    let (_, output_token_program) = pool_side(pool_keys, &params.output_mint);
+    let user_source = get_associated_token_address_with_program_id(
+        owner,
+        &params.input_mint,
+        &input_token_program
+    );
+    let user_destination = get_associated_token_address_with_program_id(
+        owner,
+        &params.output_mint,
+        &output_token_program
+    );
+
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(params.compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(params.compute_unit_price)
+    ];
+
+    if params.input_mint == wsol_mint && params.wrap_sol {
+        instructions.push(
+            create_associated_token_account_idempotent(owner, owner, &wsol_mint, &spl_token::id())
+        );
+        instructions.push(system_instruction::transfer(owner, &user_source, params.amount_in));
+        instructions.push(spl_token::instruction::sync_native(&spl_token::id(), &user_source)?);
+    }
+
+    instructions.push(
+        create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &params.output_mint,
+            &output_token_program
+        )
+    );
+
+    let swap_instruction = match pool_keys.pool_type {
+        PoolType::AmmV4 =>
+            swap_base_in_instruction(
+                pool_keys,
+                &user_source,
+                &user_destination,
+                owner,
+                params.amount_in,
+                params.min_amount_out
+            )?,
+        PoolType::Cpmm =>
+            cpmm_swap_base_input_instruction(
+                pool_keys,
+                &user_source,
+                &user_destination,
+                owner,
+                params
+            )?,
+        PoolType::Clmm =>
+            clmm_swap_v2_instruction(
+                pool_keys,
+                &user_source,
+                &user_destination,
+                owner,
+                tick_arrays,
+                params
+            )?,
+        pool_type => {
+            return Err(format!("No swap instruction for {:?} pools", pool_type).into());
+        }
+    };
+    instructions.push(swap_instruction);
+
+    if params.output_mint == wsol_mint && params.unwrap_sol {
+        instructions.push(
+            spl_token::instruction::close_account(
+                &spl_token::id(),
+                &user_destination,
+                owner,
+                owner,
+                &[]
+            )?
+        );
+    }
+
+    Ok(instructions)
+}
+
+pub async fn swap(
+    rpc_client: &dyn SolanaRpc,
+    keypair: &Keypair,
+    pool_keys: &LiquidityPoolKeys,
+    params: &SwapParams
+) -> Result<Signature, Box<dyn Error>> {
+    let tick_arrays = match pool_keys.pool_type {
+        PoolType::Clmm => fetch_tick_arrays(rpc_client, pool_keys, &params.input_mint).await?,
+        _ => Vec::new(),
+    };
+    let instructions = build_swap_instructions(
+        pool_keys,
+        &keypair.pubkey(),
+        params,
+        &tick_arrays
+    )?;
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&keypair.pubkey()),
+        &[keypair],
+        recent_blockhash
+    );
+
+    let config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        ..RpcSendTransactionConfig::default()
+    };
+    let signature = rpc_client.send_transaction_with_config(&transaction, config).await?;
+
+    Ok(signature)
+}
+
+/// The initialized tick arrays a CLMM swap of `input_mint` starts in and moves through.
+/// Arrays nobody has added liquidity to yet don't exist and are left out, the program
+/// rejects uninitialized ones.
+pub async fn fetch_tick_arrays(
+    rpc_client: &dyn SolanaRpc,
+    pool_keys: &LiquidityPoolKeys,
+    input_mint: &Pubkey
+) -> Result<Vec<Pubkey>, Box<dyn Error>> {
+    let account = rpc_client.get_account(&pool_keys.id).await?;
+    let pool_state = ClmmPoolState::decode(&account.data).ok_or_else(||
+        format!("{} is not a CLMM pool", pool_keys.id)
+    )?;
+    let zero_for_one = *input_mint == pool_state.token_mint_0;
+
+    let mut tick_arrays = Vec::new();
+    for start_index in pool_state.tick_array_start_indexes(zero_for_one, CLMM_TICK_ARRAY_COUNT) {
+        let tick_array = raydium_sdk::get_clmm_tick_array(
+            &pool_keys.program_id,
+            &pool_keys.id,
+            start_index
+        );
+        if rpc_client.get_account(&tick_array).await.is_ok() {
+            tick_arrays.push(tick_array);
+        }
+    }
+    if tick_arrays.is_empty() {
+        return Err(format!("CLMM pool {} has no liquidity around its price", pool_keys.id).into());
+    }
+
+    Ok(tick_arrays)
+}
@@ -1,9 +1,16 @@
+use redis::aio::MultiplexedConnection;
 use redis::AsyncCommands;
-use serde_json;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use serde::{ Serialize, Deserialize };
+use crate::utils::PoolType;
 
+/// Market fields are empty pubkeys for CPMM and CLMM pools, `config_id` and
+/// `observation_id` for AMM v4 pools.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LiquidityPoolKeysString {
+    #[serde(default)]
+    pub pool_type: PoolType,
     pub id: String,
     pub base_mint: String,
     pub quote_mint: String,
@@ -29,6 +36,14 @@ pub struct LiquidityPoolKeysString {
     pub market_bids: String,
     pub market_asks: String,
     pub market_event_queue: String,
+    #[serde(default)]
+    pub config_id: String,
+    #[serde(default)]
+    pub observation_id: String,
+    #[serde(default)]
+    pub base_token_program: String,
+    #[serde(default)]
+    pub quote_token_program: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,28 +56,164 @@ pub struct BuyTransaction {
     pub lp_decimals: u8,
 }
 
-// Adjust the buy function to accept BuyTransaction and LiquidityPoolKeysString
-pub async fn buy(transaction: BuyTransaction) -> Result<(), Box<dyn std::error::Error>> {
-    // Serialize the BuyTransaction object into JSON
-    let transaction_json = serde_json
-        ::to_string(&transaction)
-        .map_err(|e| format!("Failed to serialize BuyTransaction: {}", e))?;
-
-    let redis_url = std::env
-        ::var("REDIS_URL")
-        .map_err(|e| format!("You must set the REDIS_URL environment variable: {}", e))?;
-
-    let client = redis::Client
-        ::open(redis_url)
-        .map_err(|e| format!("Failed to create Redis client: {}", e))?;
-    let mut connection = client
-        .get_multiplexed_async_connection().await
-        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
-
-    // Publish the JSON payload to the "trading" channel
-    connection
-        .publish("trading", transaction_json).await
-        .map_err(|e| format!("Failed to publish message to trading channel: {}", e))?;
-
-    Ok(())
+/// Buy order on a pump.fun bonding curve, `type_` is "pump_buy".
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PumpBuyTransaction {
+    pub type_: String,
+    pub mint: String,
+    pub bonding_curve: String,
+    pub token_amount: u64,
+    pub max_sol_cost: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SellTransaction {
+    pub type_: String,
+    pub in_token: String,
+    pub out_token: String,
+    pub amount_in: f64,
+    pub min_amount_out: f64,
+    pub base_vault: String,
+    pub quote_vault: String,
+    #[serde(default)]
+    pub pool_type: PoolType,
+}
+
+/// Long-lived Redis handle shared across the process. Clones share one multiplexed
+/// connection, which is dropped on error and re-established on the next call.
+#[derive(Clone)]
+pub struct RedisHandler {
+    // `None` when REDIS_URL is unset, so Redis-free setups still start
+    client: Option<redis::Client>,
+    connection: Arc<Mutex<Option<MultiplexedConnection>>>,
+}
+
+impl RedisHandler {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let client = match std::env::var("REDIS_URL") {
+            Ok(redis_url) =>
+                Some(
+                    redis::Client
+                        ::open(redis_url)
+                        .map_err(|e| format!("Failed to create Redis client: {}", e))?
+                ),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            client,
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.client.is_some()
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, Box<dyn std::error::Error>> {
+        let client = self.client
+            .as_ref()
+            .ok_or("You must set the REDIS_URL environment variable!")?;
+
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let new_connection = client
+            .get_multiplexed_async_connection().await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+        *connection = Some(new_connection.clone());
+        Ok(new_connection)
+    }
+
+    /// Forgets the cached connection after a failure so the next call reconnects.
+    async fn reset(&self) {
+        *self.connection.lock().await = None;
+    }
+
+    pub async fn ping(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = self.connection().await?;
+        if let Err(err) = redis::cmd("PING").query_async::<_, String>(&mut connection).await {
+            self.reset().await;
+            return Err(format!("Redis ping failed: {}", err).into());
+        }
+        Ok(())
+    }
+
+    /// `SET key 1 NX EX ttl_secs`, returning whether the key was newly set.
+    pub async fn set_nx_ex(
+        &self,
+        key: &str,
+        ttl_secs: u64
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut connection = self.connection().await?;
+        let result = redis
+            ::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async::<_, Option<String>>(&mut connection).await;
+        match result {
+            Ok(set) => Ok(set.is_some()),
+            Err(err) => {
+                self.reset().await;
+                Err(err.into())
+            }
+        }
+    }
+
+    pub async fn buy(&self, transaction: BuyTransaction) -> Result<(), Box<dyn std::error::Error>> {
+        // Serialize the BuyTransaction object into JSON
+        let transaction_json = serde_json
+            ::to_string(&transaction)
+            .map_err(|e| format!("Failed to serialize BuyTransaction: {}", e))?;
+
+        self.publish_trade(transaction_json).await
+    }
+
+    pub async fn pump_buy(
+        &self,
+        transaction: PumpBuyTransaction
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let transaction_json = serde_json
+            ::to_string(&transaction)
+            .map_err(|e| format!("Failed to serialize PumpBuyTransaction: {}", e))?;
+
+        self.publish_trade(transaction_json).await
+    }
+
+    pub async fn sell(
+        &self,
+        transaction: SellTransaction
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let transaction_json = serde_json
+            ::to_string(&transaction)
+            .map_err(|e| format!("Failed to serialize SellTransaction: {}", e))?;
+
+        self.publish_trade(transaction_json).await
+    }
+
+    async fn publish_trade(
+        &self,
+        transaction_json: String
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Publish the JSON payload to the "trading" channel
+        let mut connection = self.connection().await?;
+        if connection.publish::<_, _, ()>("trading", &transaction_json).await.is_ok() {
+            return Ok(());
+        }
+
+        // The cached connection may have gone stale, retry once on a fresh one
+        self.reset().await;
+        let mut connection = self.connection().await?;
+        if let Err(err) = connection.publish::<_, _, ()>("trading", &transaction_json).await {
+            self.reset().await;
+            return Err(format!("Failed to publish message to trading channel: {}", err).into());
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,470 @@
+use async_trait::async_trait;
+use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_client::client_error::{ ClientError, ClientErrorKind, Result as ClientResult };
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{ RpcSendTransactionConfig, RpcTransactionConfig };
+use solana_client::rpc_request::{ RpcError, RpcResponseErrorData };
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_response::{
+    RpcConfirmedTransactionStatusWithSignature,
+    RpcTokenAccountBalance,
+};
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::transaction::{ Result as TransactionResult, Transaction };
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use futures::future::join_all;
+use std::error::Error;
+use std::time::{ Duration, Instant };
+
+// How often `confirm_transaction` asks for the signature status
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// JSON-RPC invalid params, what nodes answer token lookups of missing accounts with
+const JSON_RPC_INVALID_PARAMS: i64 = -32602;
+const ACCOUNT_NOT_FOUND_MESSAGE: &str = "Invalid param: could not find account";
+
+/// Connects to every WebSocket endpoint, skipping the ones that fail.
+pub async fn connect_all(ws_urls: &[String]) -> Vec<PubsubClient> {
+    let results = join_all(ws_urls.iter().map(|url| PubsubClient::new(url))).await;
+
+    results
+        .into_iter()
+        .zip(ws_urls)
+        .filter_map(|(result, url)| {
+            match result {
+                Ok(pub_subclient) => Some(pub_subclient),
+                Err(err) => {
+                    eprintln!("Failed to connect to {}: {}", url, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether a token account or mint lookup failed because the account does not exist, rather
+/// than because of the node or the connection.
+pub fn is_account_not_found(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+            *code == JSON_RPC_INVALID_PARAMS && message.contains("could not find account")
+        }
+        _ => false,
+    }
+}
+
+/// Waits until `signature` is confirmed. Fails when the transaction failed on chain, or was
+/// not confirmed within `timeout`, which usually means it was dropped.
+pub async fn confirm_transaction(
+    client: &dyn SolanaRpc,
+    signature: &Signature,
+    timeout: Duration
+) -> Result<(), Box<dyn Error>> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let status = client.get_signature_status_with_commitment(
+            signature,
+            CommitmentConfig::confirmed()
+        ).await;
+        match status {
+            Ok(Some(Ok(()))) => {
+                return Ok(());
+            }
+            Ok(Some(Err(err))) => {
+                return Err(format!("Transaction {} failed: {}", signature, err).into());
+            }
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("Failed to read the status of {}: {}", signature, err);
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(
+                format!(
+                    "Transaction {} not confirmed within {}s",
+                    signature,
+                    timeout.as_secs()
+                ).into()
+            );
+        }
+        tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+    }
+}
+
+/// The subset of the Solana JSON-RPC API the sniper pipeline depends on. Implemented by the
+/// nonblocking `RpcClient` for live trading and by `FakeRpc` for offline runs.
+#[async_trait]
+pub trait SolanaRpc: Send + Sync {
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account>;
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta>;
+
+    /// Signatures touching `address`, newest first.
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>>;
+
+    async fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount>;
+
+    async fn get_token_largest_accounts(
+        &self,
+        mint: &Pubkey
+    ) -> ClientResult<Vec<RpcTokenAccountBalance>>;
+
+    async fn get_token_supply(&self, mint: &Pubkey) -> ClientResult<UiTokenAmount>;
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64>;
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash>;
+
+    async fn send_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSendTransactionConfig
+    ) -> ClientResult<Signature>;
+
+    /// `None` while no node has seen the transaction at `commitment`.
+    async fn get_signature_status_with_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig
+    ) -> ClientResult<Option<TransactionResult<()>>>;
+}
+
+#[async_trait]
+impl SolanaRpc for RpcClient {
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        RpcClient::get_account(self, pubkey).await
+    }
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        RpcClient::get_transaction_with_config(self, signature, config).await
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        RpcClient::get_signatures_for_address_with_config(self, address, config).await
+    }
+
+    async fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount> {
+        RpcClient::get_token_account_balance(self, pubkey).await
+    }
+
+    async fn get_token_largest_accounts(
+        &self,
+        mint: &Pubkey
+    ) -> ClientResult<Vec<RpcTokenAccountBalance>> {
+        RpcClient::get_token_largest_accounts(self, mint).await
+    }
+
+    async fn get_token_supply(&self, mint: &Pubkey) -> ClientResult<UiTokenAmount> {
+        RpcClient::get_token_supply(self, mint).await
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        RpcClient::get_balance(self, pubkey).await
+    }
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        RpcClient::get_latest_blockhash(self).await
+    }
+
+    async fn send_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSendTransactionConfig
+    ) -> ClientResult<Signature> {
+        RpcClient::send_transaction_with_config(self, transaction, config).await
+    }
+
+    async fn get_signature_status_with_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig
+    ) -> ClientResult<Option<TransactionResult<()>>> {
+        RpcClient::get_signature_status_with_commitment(self, signature, commitment).await
+    }
+}
+
+/// In-memory chain used for backtests and offline runs. Lookups of anything that was not
+/// inserted fail the same way a missing account fails on a real node.
+#[derive(Default)]
+pub struct FakeRpc {
+    accounts: Mutex<HashMap<Pubkey, Account>>,
+    // Kept as JSON since the RPC transaction type is not `Clone`
+    transactions: Mutex<HashMap<Signature, String>>,
+    signatures: Mutex<HashMap<Pubkey, Vec<RpcConfirmedTransactionStatusWithSignature>>>,
+    token_balances: Mutex<HashMap<Pubkey, UiTokenAmount>>,
+    largest_accounts: Mutex<HashMap<Pubkey, Vec<RpcTokenAccountBalance>>>,
+    token_supplies: Mutex<HashMap<Pubkey, UiTokenAmount>>,
+    balances: Mutex<HashMap<Pubkey, u64>>,
+    sent_transactions: Mutex<Vec<Transaction>>,
+}
+
+impl FakeRpc {
+    pub fn new() -> Arc<Self> {
+        Arc::new(FakeRpc::default())
+    }
+
+    pub fn insert_account(&self, pubkey: Pubkey, account: Account) {
+        self.accounts.lock().unwrap().insert(pubkey, account);
+    }
+
+    #[cfg(test)]
+    pub fn insert_transaction(
+        &self,
+        signature: Signature,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta
+    ) {
+        let json = serde_json::to_string(transaction).expect("Transaction serializes to JSON");
+        self.transactions.lock().unwrap().insert(signature, json);
+    }
+
+    /// Signatures returned for `address`, expected newest first like a real node.
+    #[cfg(test)]
+    pub fn insert_signatures(
+        &self,
+        address: Pubkey,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>
+    ) {
+        self.signatures.lock().unwrap().insert(address, signatures);
+    }
+
+    pub fn insert_token_balance(&self, pubkey: Pubkey, balance: UiTokenAmount) {
+        self.token_balances.lock().unwrap().insert(pubkey, balance);
+    }
+
+    pub fn insert_largest_accounts(&self, mint: Pubkey, accounts: Vec<RpcTokenAccountBalance>) {
+        self.largest_accounts.lock().unwrap().insert(mint, accounts);
+    }
+
+    pub fn insert_token_supply(&self, mint: Pubkey, supply: UiTokenAmount) {
+        self.token_supplies.lock().unwrap().insert(mint, supply);
+    }
+
+    #[cfg(test)]
+    pub fn insert_balance(&self, pubkey: Pubkey, lamports: u64) {
+        self.balances.lock().unwrap().insert(pubkey, lamports);
+    }
+
+    /// Transactions handed to `send_transaction_with_config`, in order.
+    #[cfg(test)]
+    pub fn sent_transactions(&self) -> Vec<Transaction> {
+        self.sent_transactions.lock().unwrap().clone()
+    }
+}
+
+fn not_found(what: &str, key: &dyn std::fmt::Display) -> ClientError {
+    ClientErrorKind::Custom(format!("{} {} not found", what, key)).into()
+}
+
+/// The error nodes answer token account and mint lookups of missing accounts with.
+fn token_account_not_found() -> ClientError {
+    RpcError::RpcResponseError {
+        code: JSON_RPC_INVALID_PARAMS,
+        message: ACCOUNT_NOT_FOUND_MESSAGE.to_string(),
+        data: RpcResponseErrorData::Empty,
+    }.into()
+}
+
+#[async_trait]
+impl SolanaRpc for FakeRpc {
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.accounts
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| not_found("Account", pubkey))
+    }
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        _config: RpcTransactionConfig
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        let json = self.transactions
+            .lock()
+            .unwrap()
+            .get(signature)
+            .cloned()
+            .ok_or_else(|| not_found("Transaction", signature))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        let signatures = self.signatures.lock().unwrap().get(address).cloned().unwrap_or_default();
+        let before = config.before.map(|signature| signature.to_string());
+        let until = config.until.map(|signature| signature.to_string());
+
+        Ok(
+            signatures
+                .into_iter()
+                .skip_while(|status| before.as_ref().is_some_and(|b| *b != status.signature))
+                .skip(usize::from(before.is_some()))
+                .take_while(|status| until.as_ref() != Some(&status.signature))
+                .take(config.limit.unwrap_or(1000))
+                .collect()
+        )
+    }
+
+    async fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount> {
+        self.token_balances
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(token_account_not_found)
+    }
+
+    async fn get_token_largest_accounts(
+        &self,
+        mint: &Pubkey
+    ) -> ClientResult<Vec<RpcTokenAccountBalance>> {
+        self.largest_accounts
+            .lock()
+            .unwrap()
+            .get(mint)
+            .cloned()
+            .ok_or_else(token_account_not_found)
+    }
+
+    async fn get_token_supply(&self, mint: &Pubkey) -> ClientResult<UiTokenAmount> {
+        self.token_supplies
+            .lock()
+            .unwrap()
+            .get(mint)
+            .cloned()
+            .ok_or_else(token_account_not_found)
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        Ok(self.balances.lock().unwrap().get(pubkey).copied().unwrap_or(0))
+    }
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        Ok(Hash::default())
+    }
+
+    async fn send_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        _config: RpcSendTransactionConfig
+    ) -> ClientResult<Signature> {
+        self.sent_transactions.lock().unwrap().push(transaction.clone());
+        Ok(transaction.signatures.first().copied().unwrap_or_default())
+    }
+
+    /// Every transaction sent to the fake lands.
+    async fn get_signature_status_with_commitment(
+        &self,
+        signature: &Signature,
+        _commitment: CommitmentConfig
+    ) -> ClientResult<Option<TransactionResult<()>>> {
+        let sent = self.sent_transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|transaction| transaction.signatures.first() == Some(signature));
+        Ok(sent.then_some(Ok(())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(count: usize) -> Vec<RpcConfirmedTransactionStatusWithSignature> {
+        (0..count)
+            .map(|_| RpcConfirmedTransactionStatusWithSignature {
+                signature: Signature::new_unique().to_string(),
+                slot: 0,
+                err: None,
+                memo: None,
+                block_time: None,
+                confirmation_status: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_signatures_like_a_node() {
+        let rpc = FakeRpc::new();
+        let address = Pubkey::new_unique();
+        let newest_first = statuses(5);
+        rpc.insert_signatures(address, newest_first.clone());
+        let signature = |index: usize| newest_first[index].signature.parse().unwrap();
+
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before: Some(signature(1)),
+            until: Some(signature(4)),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = rpc.get_signatures_for_address_with_config(&address, config).await.unwrap();
+
+        let page: Vec<&String> = page.iter().map(|status| &status.signature).collect();
+        assert_eq!(page, vec![&newest_first[2].signature, &newest_first[3].signature]);
+    }
+
+    #[tokio::test]
+    async fn confirms_transactions_once_sent() {
+        let rpc = FakeRpc::new();
+        let transaction = Transaction {
+            signatures: vec![Signature::new_unique()],
+            ..Transaction::default()
+        };
+        let timeout = Duration::ZERO;
+
+        let err = confirm_transaction(rpc.as_ref(), &transaction.signatures[0], timeout).await;
+        assert!(err.is_err());
+
+        let signature = rpc.send_transaction_with_config(
+            &transaction,
+            RpcSendTransactionConfig::default()
+        ).await.unwrap();
+        assert_eq!(rpc.sent_transactions().len(), 1);
+        confirm_transaction(rpc.as_ref(), &signature, timeout).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn tells_missing_token_accounts_from_failed_lookups() {
+        let rpc = FakeRpc::new();
+        let err = rpc.get_token_account_balance(&Pubkey::new_unique()).await.unwrap_err();
+        assert!(is_account_not_found(&err));
+
+        let unhealthy: ClientError = RpcError::RpcResponseError {
+            code: -32005,
+            message: "Node is unhealthy".to_string(),
+            data: RpcResponseErrorData::Empty,
+        }.into();
+        assert!(!is_account_not_found(&unhealthy));
+        let timeout: ClientError = std::io::Error::from(std::io::ErrorKind::TimedOut).into();
+        assert!(!is_account_not_found(&timeout));
+    }
+}
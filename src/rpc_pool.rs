@@ -0,0 +1,288 @@
+use crate::config::RpcConfig;
+use crate::rpc::SolanaRpc;
+use async_trait::async_trait;
+use futures::future::{ select_ok, BoxFuture, FutureExt };
+use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_client::client_error::{ ClientError, ClientErrorKind, Result as ClientResult };
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{ RpcSendTransactionConfig, RpcTransactionConfig };
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::RpcError;
+use solana_client::rpc_response::{
+    RpcConfirmedTransactionStatusWithSignature,
+    RpcTokenAccountBalance,
+};
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::transaction::{ Result as TransactionResult, Transaction };
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+
+// Weight of the newest sample in the latency moving average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    latency_ms: f64,
+    consecutive_failures: u32,
+    cooldown_until: Option<Instant>,
+}
+
+struct Endpoint {
+    url: String,
+    client: RpcClient,
+    health: Mutex<EndpointHealth>,
+}
+
+/// `SolanaRpc` over several HTTP endpoints. Calls go to the healthy endpoint with the lowest
+/// observed latency and fail over to the next one on transport errors or rate limiting;
+/// transaction fetches are hedged across the fastest endpoints.
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    hedge_delay: Duration,
+    hedge_fanout: usize,
+    failure_cooldown: Duration,
+    rate_limit_cooldown: Duration,
+}
+
+impl RpcPool {
+    pub fn new(urls: Vec<String>, config: &RpcConfig) -> Self {
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                client: RpcClient::new(url.clone()),
+                url,
+                health: Mutex::new(EndpointHealth::default()),
+            })
+            .collect();
+
+        RpcPool {
+            endpoints,
+            hedge_delay: Duration::from_millis(config.hedge_delay_ms),
+            hedge_fanout: config.hedge_fanout,
+            failure_cooldown: Duration::from_secs(config.failure_cooldown_secs),
+            rate_limit_cooldown: Duration::from_secs(config.rate_limit_cooldown_secs),
+        }
+    }
+
+    /// Endpoint indices, best first: endpoints outside their cooldown by latency, then the
+    /// cooled down ones as a last resort. Ties keep the configured priority order.
+    fn ranked(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut ranked: Vec<(bool, f64, usize)> = self.endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                let health = endpoint.health.lock().unwrap();
+                let cooling_down = health.cooldown_until.is_some_and(|until| until > now);
+                (cooling_down, health.latency_ms, index)
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2)));
+        ranked.into_iter().map(|(_, _, index)| index).collect()
+    }
+
+    fn record_success(&self, index: usize, elapsed: Duration) {
+        let mut health = self.endpoints[index].health.lock().unwrap();
+        let sample = elapsed.as_secs_f64() * 1000.0;
+        health.latency_ms = if health.latency_ms == 0.0 {
+            sample
+        } else {
+            health.latency_ms * (1.0 - LATENCY_SMOOTHING) + sample * LATENCY_SMOOTHING
+        };
+        health.consecutive_failures = 0;
+        health.cooldown_until = None;
+    }
+
+    fn record_failure(&self, index: usize, err: &ClientError) {
+        let endpoint = &self.endpoints[index];
+        let cooldown = if is_rate_limited(err) {
+            self.rate_limit_cooldown
+        } else {
+            self.failure_cooldown
+        };
+
+        let mut health = endpoint.health.lock().unwrap();
+        health.consecutive_failures += 1;
+        health.cooldown_until = Some(Instant::now() + cooldown);
+        eprintln!(
+            "RPC endpoint {} failed ({} in a row), cooling down {}s: {}",
+            endpoint.url,
+            health.consecutive_failures,
+            cooldown.as_secs(),
+            err
+        );
+    }
+
+    async fn timed<'a, T>(
+        &'a self,
+        index: usize,
+        call: &(dyn (Fn(&'a RpcClient) -> BoxFuture<'a, ClientResult<T>>) + Send + Sync)
+    ) -> ClientResult<T> {
+        let started = Instant::now();
+        let result = call(&self.endpoints[index].client).await;
+        match &result {
+            Ok(_) => self.record_success(index, started.elapsed()),
+            Err(err) if is_endpoint_failure(err) => self.record_failure(index, err),
+            // The node answered, the request itself was bad
+            Err(_) => self.record_success(index, started.elapsed()),
+        }
+        result
+    }
+
+    /// Tries endpoints best first until one answers. Errors the node itself returns (an
+    /// unknown account, a rejected transaction) are final and not retried elsewhere.
+    async fn with_failover<'a, T>(
+        &'a self,
+        call: impl (Fn(&'a RpcClient) -> BoxFuture<'a, ClientResult<T>>) + Send + Sync
+    ) -> ClientResult<T> {
+        let mut last_err: Option<ClientError> = None;
+
+        for index in self.ranked() {
+            match self.timed(index, &call).await {
+                Ok(value) => {
+                    return Ok(value);
+                }
+                Err(err) if is_endpoint_failure(&err) => {
+                    last_err = Some(err);
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| ClientErrorKind::Custom("No RPC endpoints".into()).into()))
+    }
+
+    /// Sends the call to the best endpoint, and after every `hedge_delay` without an answer
+    /// to the next one as well, up to `hedge_fanout` endpoints. The first success wins.
+    async fn hedged<'a, T: Send + 'a>(
+        &'a self,
+        call: impl (Fn(&'a RpcClient) -> BoxFuture<'a, ClientResult<T>>) + Send + Sync + 'a
+    ) -> ClientResult<T> {
+        let call = &call;
+        let attempts: Vec<BoxFuture<'_, ClientResult<T>>> = self
+            .ranked()
+            .into_iter()
+            .take(self.hedge_fanout)
+            .enumerate()
+            .map(|(position, index)| {
+                let delay = self.hedge_delay * (position as u32);
+                (
+                    async move {
+                        if !delay.is_zero() {
+                            tokio::time::sleep(delay).await;
+                        }
+                        self.timed(index, call).await
+                    }
+                ).boxed()
+            })
+            .collect();
+
+        if attempts.is_empty() {
+            return Err(ClientErrorKind::Custom("No RPC endpoints".into()).into());
+        }
+
+        select_ok(attempts).await.map(|(value, _)| value)
+    }
+}
+
+fn is_rate_limited(err: &ClientError) -> bool {
+    let message = err.to_string();
+    message.contains("429") || message.contains("Too Many Requests")
+}
+
+/// Whether the error says something about the endpoint (unreachable, overloaded, unhealthy)
+/// rather than about the request.
+fn is_endpoint_failure(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) =>
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY || is_rate_limited(err),
+        _ => is_rate_limited(err),
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for RpcPool {
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.with_failover(|client| client.get_account(pubkey).boxed()).await
+    }
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        // A transaction that was just confirmed may not be on every node yet, so the first
+        // endpoint that has it wins
+        self.hedged(|client| client.get_transaction_with_config(signature, config).boxed()).await
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.with_failover(|client| {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before: config.before,
+                until: config.until,
+                limit: config.limit,
+                commitment: config.commitment,
+            };
+            client.get_signatures_for_address_with_config(address, config).boxed()
+        }).await
+    }
+
+    async fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount> {
+        self.with_failover(|client| client.get_token_account_balance(pubkey).boxed()).await
+    }
+
+    async fn get_token_largest_accounts(
+        &self,
+        mint: &Pubkey
+    ) -> ClientResult<Vec<RpcTokenAccountBalance>> {
+        self.with_failover(|client| client.get_token_largest_accounts(mint).boxed()).await
+    }
+
+    async fn get_token_supply(&self, mint: &Pubkey) -> ClientResult<UiTokenAmount> {
+        self.with_failover(|client| client.get_token_supply(mint).boxed()).await
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.with_failover(|client| client.get_balance(pubkey).boxed()).await
+    }
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.with_failover(|client| client.get_latest_blockhash().boxed()).await
+    }
+
+    async fn send_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSendTransactionConfig
+    ) -> ClientResult<Signature> {
+        // Resending the same signed transaction elsewhere is safe, it can only land once
+        self.with_failover(|client| {
+            client.send_transaction_with_config(transaction, config).boxed()
+        }).await
+    }
+
+    async fn get_signature_status_with_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig
+    ) -> ClientResult<Option<TransactionResult<()>>> {
+        self.with_failover(|client| {
+            client.get_signature_status_with_commitment(signature, commitment).boxed()
+        }).await
+    }
+}
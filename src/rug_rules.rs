@@ -0,0 +1,361 @@
+use crate::config::{ RugCheckConfig, RugPolicy, RugRuleKind };
+use crate::metadata::FetchedMetadata;
+use crate::mongo::MongoHandler;
+use crate::rpc::SolanaRpc;
+use crate::rugcheck::{
+    get_current_sol_price,
+    mint_risks,
+    post_rug_check,
+    wait_for_liquidity,
+    wait_for_lp_state,
+};
+use crate::utils::PoolInfo;
+use async_trait::async_trait;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use serde::{ Deserialize, Serialize };
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// What the rules get to see of the pool under evaluation.
+pub struct RuleContext<'a> {
+    pub rpc: &'a dyn SolanaRpc,
+    /// Connections the LP mint is watched on while waiting, empty only polls
+    pub pubsub_clients: &'a [Arc<PubsubClient>],
+    pub pool_info: &'a PoolInfo,
+    pub config: &'a RugCheckConfig,
+    /// Verdict history for the creator rule, `None` in backtests
+    pub mongo: Option<&'a MongoHandler>,
+    /// `None` when the mint has no metadata
+    pub metadata: Option<&'a FetchedMetadata>,
+    /// USD price of SOL, fetched from Birdeye when `None`
+    pub sol_price: Option<f64>,
+    /// Wait for the LP burn and liquidity until `lp_burn_timeout_secs` instead of reading them once
+    pub wait: bool,
+}
+
+/// One rule's reading of a pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleFinding {
+    pub rule: RugRuleKind,
+    /// From 0, nothing found, to 1, the rule's limit is crossed
+    pub risk: f64,
+    pub weight: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RugVerdict {
+    pub policy: RugPolicy,
+    /// Weight-averaged risk of the rules that ran
+    pub score: f64,
+    pub passed: bool,
+    pub findings: Vec<RuleFinding>,
+}
+
+impl fmt::Display for RugVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "risk {:.2}", self.score)?;
+        for finding in self.findings.iter().filter(|finding| finding.risk > 0.0) {
+            write!(f, "; {:?}: {}", finding.rule, finding.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// A check a pool goes through before it is bought, see `rugcheck.rules`.
+#[async_trait]
+pub trait RugRule: Send + Sync {
+    /// `(risk, reason)`, the risk from 0 to 1.
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>>;
+}
+
+pub fn rug_rule(kind: RugRuleKind) -> Box<dyn RugRule> {
+    match kind {
+        RugRuleKind::Authorities => Box::new(AuthoritiesRule),
+        RugRuleKind::LpBurn => Box::new(LpBurnRule),
+        RugRuleKind::Liquidity => Box::new(LiquidityRule),
+        RugRuleKind::Holders => Box::new(HoldersRule),
+        RugRuleKind::Metadata => Box::new(MetadataRule),
+        RugRuleKind::Socials => Box::new(SocialsRule),
+        RugRuleKind::CreatorHistory => Box::new(CreatorHistoryRule),
+    }
+}
+
+/// Runs the configured rules in order and combines their findings by the configured policy.
+pub async fn evaluate_rules(ctx: &RuleContext<'_>) -> Result<RugVerdict, Box<dyn Error>> {
+    let mut findings = Vec::with_capacity(ctx.config.rules.len());
+
+    for rule_config in &ctx.config.rules {
+        let (risk, reason) = rug_rule(rule_config.rule).evaluate(ctx).await?;
+        let risk = risk.clamp(0.0, 1.0);
+        findings.push(RuleFinding {
+            rule: rule_config.rule,
+            risk,
+            weight: rule_config.weight,
+            reason,
+        });
+
+        // The verdict is settled, the remaining rules would only cost RPC calls
+        if ctx.config.policy == RugPolicy::Strict && risk >= 1.0 {
+            break;
+        }
+    }
+
+    let total_weight: f64 = findings
+        .iter()
+        .map(|finding| finding.weight)
+        .sum();
+    let score = if total_weight > 0.0 {
+        findings
+            .iter()
+            .map(|finding| finding.risk * finding.weight)
+            .sum::<f64>() / total_weight
+    } else {
+        0.0
+    };
+    let passed = match ctx.config.policy {
+        RugPolicy::Strict => findings.iter().all(|finding| finding.risk < 1.0),
+        RugPolicy::Weighted => score <= ctx.config.max_risk_score,
+    };
+
+    Ok(RugVerdict {
+        policy: ctx.config.policy,
+        score,
+        passed,
+        findings,
+    })
+}
+
+fn verdict(failed: bool, reason: String) -> (f64, String) {
+    (if failed { 1.0 } else { 0.0 }, reason)
+}
+
+struct AuthoritiesRule;
+
+#[async_trait]
+impl RugRule for AuthoritiesRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let mint_account = ctx.rpc.get_account(&ctx.pool_info.base_mint).await?;
+        let risks = mint_risks(&mint_account)?;
+        if risks.is_empty() {
+            return Ok(verdict(false, "No authorities or dangerous extensions".to_string()));
+        }
+        Ok(verdict(true, format!("{:?}", risks)))
+    }
+}
+
+struct LpBurnRule;
+
+#[async_trait]
+impl RugRule for LpBurnRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        if !ctx.pool_info.pool_type.has_lp_mint() {
+            return Ok(verdict(false, "No LP mint".to_string()));
+        }
+        let lp_state = wait_for_lp_state(
+            ctx.rpc,
+            ctx.pubsub_clients,
+            ctx.pool_info,
+            ctx.config,
+            ctx.wait
+        ).await?;
+        let now = chrono::Utc::now().timestamp();
+        let reason = lp_state.to_string();
+        if lp_state.burnt_pct > ctx.config.min_lp_burn_pct {
+            return Ok(verdict(false, reason));
+        }
+        // Locked LP comes back to its owner once the lock ends
+        if lp_state.secured_pct(ctx.config, now) > ctx.config.min_lp_burn_pct {
+            return Ok((0.25, reason));
+        }
+        Ok(verdict(true, reason))
+    }
+}
+
+struct LiquidityRule;
+
+#[async_trait]
+impl RugRule for LiquidityRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let sol_price = match ctx.sol_price {
+            Some(sol_price) => sol_price,
+            None => get_current_sol_price().await?,
+        };
+        let liquidity = wait_for_liquidity(
+            ctx.rpc,
+            ctx.pool_info,
+            sol_price,
+            ctx.config,
+            ctx.wait
+        ).await;
+        let reason = format!("${:.0} liquidity", liquidity);
+        Ok(verdict(liquidity <= ctx.config.min_liquidity_usd, reason))
+    }
+}
+
+struct HoldersRule;
+
+#[async_trait]
+impl RugRule for HoldersRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let pool_accounts = [ctx.pool_info.base_vault, ctx.pool_info.quote_vault];
+        let concentration = post_rug_check(
+            ctx.rpc,
+            &ctx.pool_info.base_mint,
+            &pool_accounts,
+            ctx.config
+        ).await?;
+        match concentration {
+            Some(reason) => Ok(verdict(true, reason)),
+            None => Ok(verdict(false, "Supply is spread out".to_string())),
+        }
+    }
+}
+
+struct MetadataRule;
+
+#[async_trait]
+impl RugRule for MetadataRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let Some(metadata) = ctx.metadata else {
+            return Ok(verdict(false, "No metadata".to_string()));
+        };
+        let on_chain = &metadata.on_chain;
+        if !on_chain.is_mutable {
+            return Ok(verdict(false, "Metadata is immutable".to_string()));
+        }
+        // A launchpad holding the update authority is less of a risk than the creator
+        if on_chain.update_authority == ctx.pool_info.creator {
+            return Ok(verdict(true, "Metadata can be changed by the pool creator".to_string()));
+        }
+        let reason = format!("Metadata can be changed by {}", on_chain.update_authority);
+        Ok((0.5, reason))
+    }
+}
+
+struct SocialsRule;
+
+#[async_trait]
+impl RugRule for SocialsRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let Some(off_chain) = ctx.metadata.and_then(|metadata| metadata.off_chain.as_ref()) else {
+            return Ok(verdict(true, "No off-chain metadata".to_string()));
+        };
+        if !off_chain.has_socials() {
+            return Ok(verdict(true, "No twitter, telegram or website".to_string()));
+        }
+        let socials: Vec<&str> = [off_chain.twitter(), off_chain.telegram(), off_chain.website()]
+            .into_iter()
+            .filter(|social| !social.is_empty())
+            .collect();
+        Ok(verdict(false, socials.join(", ")))
+    }
+}
+
+struct CreatorHistoryRule;
+
+#[async_trait]
+impl RugRule for CreatorHistoryRule {
+    async fn evaluate(&self, ctx: &RuleContext<'_>) -> Result<(f64, String), Box<dyn Error>> {
+        let Some(mongo) = ctx.mongo else {
+            return Ok(verdict(false, "No verdict history".to_string()));
+        };
+        let creator = ctx.pool_info.creator.to_string();
+        let pools = mongo.count_pools_by_creator("solsniper", "verdicts", &creator).await?;
+        Ok(
+            verdict(
+                pools >= ctx.config.max_creator_pools,
+                format!("{} created {} pools before", creator, pools)
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::detector::pool_detectors;
+    use crate::fixtures::AmmV4Creation;
+    use crate::rpc::FakeRpc;
+    use crate::swap::parse_new_pool;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signer;
+
+    async fn evaluate(
+        creation: &AmmV4Creation,
+        rpc: &FakeRpc,
+        config: &RugCheckConfig
+    ) -> RugVerdict {
+        let pool_info = parse_new_pool(
+            creation.encoded(),
+            &pool_detectors(&Config::default())
+        ).unwrap();
+        let ctx = RuleContext {
+            rpc,
+            pubsub_clients: &[],
+            pool_info: &pool_info,
+            config,
+            mongo: None,
+            metadata: None,
+            sol_price: Some(150.0),
+            wait: false,
+        };
+        evaluate_rules(&ctx).await.unwrap()
+    }
+
+    fn risk(verdict: &RugVerdict, rule: RugRuleKind) -> f64 {
+        verdict.findings
+            .iter()
+            .find(|finding| finding.rule == rule)
+            .map(|finding| finding.risk)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn passes_a_pool_with_burnt_lp_and_spread_supply() {
+        let creation = AmmV4Creation::new();
+        let rpc = FakeRpc::new();
+        // 10% of the supply with the creator, the rest in the pool
+        creation.load_chain(&rpc, &[(creation.creator.pubkey(), 100_000_000_000_000)]);
+        creation.burn_lp(&rpc);
+
+        let verdict = evaluate(&creation, &rpc, &RugCheckConfig::default()).await;
+        assert!(verdict.passed, "{}", verdict);
+        assert_eq!(verdict.findings.len(), 4);
+        assert_eq!(verdict.score, 0.0);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_pool_whose_lp_is_not_burnt() {
+        let creation = AmmV4Creation::new();
+        let rpc = FakeRpc::new();
+        creation.load_chain(&rpc, &[]);
+
+        let verdict = evaluate(&creation, &rpc, &RugCheckConfig::default()).await;
+        assert!(!verdict.passed);
+        assert_eq!(risk(&verdict, RugRuleKind::LpBurn), 1.0);
+        // Strict verdicts stop at the first failed rule
+        assert_eq!(verdict.findings.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn weighs_a_concentrated_supply_against_the_other_rules() {
+        let creation = AmmV4Creation::new();
+        let rpc = FakeRpc::new();
+        creation.load_chain(&rpc, &[(Pubkey::new_unique(), 150_000_000_000_000)]);
+        creation.burn_lp(&rpc);
+        let config = RugCheckConfig {
+            policy: RugPolicy::Weighted,
+            max_risk_score: 0.3,
+            max_single_holder_pct: 10.0,
+            ..RugCheckConfig::default()
+        };
+
+        let verdict = evaluate(&creation, &rpc, &config).await;
+        assert_eq!(risk(&verdict, RugRuleKind::Holders), 1.0);
+        assert_eq!(verdict.score, 0.25);
+        assert!(verdict.passed);
+    }
+}
@@ -1,28 +1,36 @@
 use crate::utils;
+use crate::config::RugCheckConfig;
+use solana_sdk::account::Account;
+use solana_sdk::program_error::ProgramError;
 use solana_sdk::program_pack::Pack;
-use spl_token::state::Mint;
+use spl_token::state::{ Account as TokenAccount, Mint };
+use spl_token_2022::extension::{ BaseStateWithExtensions, StateWithExtensions };
+use spl_token_2022::extension::default_account_state::DefaultAccountState;
+use spl_token_2022::extension::non_transferable::NonTransferable;
+use spl_token_2022::extension::permanent_delegate::PermanentDelegate;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::transfer_hook::TransferHook;
+use spl_token_2022::state::AccountState;
 use solana_sdk::pubkey::Pubkey;
 use std::time::Duration;
 use std::str::FromStr;
-use solana_client::rpc_client::RpcClient;
+use crate::rpc::SolanaRpc;
+use futures::stream::{ select_all, Stream, StreamExt };
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
 use serde::Deserialize;
-use utils::PoolInfo;
+use utils::{ PoolInfo, PoolType };
 use std::error::Error;
-
-#[derive(Debug, Deserialize)]
-pub struct TokenReport {
-    pub token: TokenInfo,
-    pub top_holders: Option<Vec<TopHolder>>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct TokenInfo {
-    pub freeze_authority: Option<Pubkey>,
-    pub mint_authority: Option<Pubkey>,
-}
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 pub struct TopHolder {
+    /// The token account
+    pub address: Pubkey,
+    /// The wallet or program account that owns `address`
     pub owner: Pubkey,
     pub amount: u64,
     pub pct: f64,
@@ -34,7 +42,7 @@ pub async fn get_current_sol_price() -> Result<f64, Box<dyn Error>> {
 
     let birdeye_api_key = std::env
         ::var("BIRDEYE_API")
-        .expect("You must set the RPC_URL environment variable!");
+        .map_err(|_| "You must set the BIRDEYE_API environment variable!")?;
     // Make GET request to the API endpoint with API key in header
     let response = reqwest::Client
         ::new()
@@ -53,31 +61,8 @@ pub async fn get_current_sol_price() -> Result<f64, Box<dyn Error>> {
     }
 }
 
-async fn calculate_liquidity_usd(
-    client: &RpcClient,
-    base_token_account: Pubkey,
-    quote_token_account: Pubkey
-) -> Result<f64, Box<dyn Error>> {
-    let sol_price = get_current_sol_price().await?; // Get current SOL price
-
-    // Get base token balance
-    let base_token_info = match client.get_token_account_balance(&base_token_account) {
-        Ok(info) => info,
-        Err(err) => {
-            return Err(Box::new(err));
-        }
-    };
-    let base_balance = base_token_info.amount.parse::<f64>()?;
-
-    // Get quote token balance
-    let quote_token_info = match client.get_token_account_balance(&quote_token_account) {
-        Ok(info) => info,
-        Err(err) => {
-            return Err(Box::new(err));
-        }
-    };
-    let quote_balance = quote_token_info.amount.parse::<f64>()?;
-
+/// USD value of both sides of the pool from raw vault balances, with the quote side in lamports.
+pub fn liquidity_usd(base_balance: f64, quote_balance: f64, sol_price: f64) -> f64 {
     // Calculate base price in SOL
     let base_price_in_sol = quote_balance / base_balance;
 
@@ -93,129 +78,462 @@ async fn calculate_liquidity_usd(
     // Calculate liquidity USD value
     let liquidity_usd = value_usd_base_balance + value_usd_quote_balance;
 
-    let formatted_liquidity = liquidity_usd / 1000000000.0;
+    liquidity_usd / 1000000000.0
+}
+
+/// Percentage of the initially minted LP supply (`lp_reserve`) that has since been burned.
+pub fn lp_burn_pct(lp_reserve: u64, mint_info: &Mint) -> f64 {
+    // Calculate reserve and actual supply
+    let lp_reserve_amount = (lp_reserve as f64) / (10_f64).powi(mint_info.decimals as i32);
+    let actual_supply = (mint_info.supply as f64) / (10_f64).powi(mint_info.decimals as i32);
 
-    Ok(formatted_liquidity)
+    // Calculate burn amount and percentage
+    let burn_amt = lp_reserve_amount - actual_supply;
+    (burn_amt / lp_reserve_amount) * 100.0
 }
 
-pub async fn check_burnt_lp(
-    client: &RpcClient,
-    pool_info: &PoolInfo
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let lp_mint = pool_info.lp_mint;
-    let lp_reserve = pool_info.lp_reserve;
-    let timeout = Duration::from_secs(220); // 3 minutes
-    let retry_interval = Duration::from_secs(15); // Retry every 15 seconds
-    let start_time = tokio::time::Instant::now();
+pub fn has_mint_or_freeze_authority(mint_token: &Mint) -> bool {
+    mint_token.mint_authority.is_some() || mint_token.freeze_authority.is_some()
+}
 
-    loop {
-        // Check elapsed time
-        if tokio::time::Instant::now().duration_since(start_time) > timeout {
-            return Ok(false);
+/// Something a mint lets its creator do to holders, each one fails the rug check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MintRisk {
+    MintOrFreezeAuthority,
+    /// Highest of the current and upcoming fee in basis points. Also flagged at 0 while an
+    /// authority can still raise it
+    TransferFee(u16),
+    /// A program that can refuse transfers, or an authority that can install one
+    TransferHook,
+    /// Can transfer or burn tokens out of any account
+    PermanentDelegate(Pubkey),
+    NonTransferable,
+    /// New token accounts start frozen, so bought tokens can't be sold until thawed
+    DefaultFrozen,
+}
+
+/// The SPL token fields of a legacy or Token-2022 mint. Token-2022 mints share the
+/// layout and append their extensions after it.
+pub fn unpack_mint(data: &[u8]) -> Result<Mint, ProgramError> {
+    Mint::unpack(data.get(..Mint::LEN).ok_or(ProgramError::InvalidAccountData)?)
+}
+
+/// Everything about the mint in `mint_account` that makes its token unsafe to buy. Token-2022
+/// mints are also checked for extensions that tax, block or claw back transfers.
+pub fn mint_risks(mint_account: &Account) -> Result<Vec<MintRisk>, Box<dyn Error>> {
+    let mut risks = Vec::new();
+    if has_mint_or_freeze_authority(&unpack_mint(&mint_account.data)?) {
+        risks.push(MintRisk::MintOrFreezeAuthority);
+    }
+    if mint_account.owner != spl_token_2022::id() {
+        return Ok(risks);
+    }
+
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)?;
+    if let Ok(fee_config) = mint.get_extension::<TransferFeeConfig>() {
+        let basis_points = u16
+            ::from(fee_config.older_transfer_fee.transfer_fee_basis_points)
+            .max(u16::from(fee_config.newer_transfer_fee.transfer_fee_basis_points));
+        let authority: Option<Pubkey> = fee_config.transfer_fee_config_authority.into();
+        if basis_points > 0 || authority.is_some() {
+            risks.push(MintRisk::TransferFee(basis_points));
+        }
+    }
+    if let Ok(hook) = mint.get_extension::<TransferHook>() {
+        let program_id: Option<Pubkey> = hook.program_id.into();
+        let authority: Option<Pubkey> = hook.authority.into();
+        if program_id.is_some() || authority.is_some() {
+            risks.push(MintRisk::TransferHook);
+        }
+    }
+    if let Ok(delegate) = mint.get_extension::<PermanentDelegate>() {
+        if let Some(delegate) = Option::<Pubkey>::from(delegate.delegate) {
+            risks.push(MintRisk::PermanentDelegate(delegate));
         }
+    }
+    if mint.get_extension::<NonTransferable>().is_ok() {
+        risks.push(MintRisk::NonTransferable);
+    }
+    if let Ok(default_state) = mint.get_extension::<DefaultAccountState>() {
+        if default_state.state == (AccountState::Frozen as u8) {
+            risks.push(MintRisk::DefaultFrozen);
+        }
+    }
+
+    Ok(risks)
+}
+
+/// LP tokens held by one of `rugcheck.lp_lockers`.
+#[derive(Debug, Clone)]
+pub struct LpLock {
+    pub locker: String,
+    /// Share of the LP minted at pool creation
+    pub pct: f64,
+    /// Unix time, `None` for permanent locks and lockers without a known layout
+    pub unlocks_at: Option<i64>,
+    pub permanent: bool,
+}
 
-        // Get the mint info
-        let acc_info = match client.get_account(&lp_mint) {
-            Ok(info) => info,
-            Err(_) => {
-                tokio::time::sleep(retry_interval).await;
-                continue;
+impl LpLock {
+    /// Whether the lock lasts long enough for `rugcheck_config`.
+    pub fn is_accepted(&self, rugcheck_config: &RugCheckConfig, now: i64) -> bool {
+        if !rugcheck_config.accept_locked_lp {
+            return false;
+        }
+        if self.permanent {
+            return true;
+        }
+        match self.unlocks_at {
+            Some(unlocks_at) => {
+                unlocks_at.saturating_sub(now) >= (rugcheck_config.min_lp_lock_secs as i64)
             }
+            None => rugcheck_config.min_lp_lock_secs == 0,
+        }
+    }
+}
+
+/// Where the LP minted at pool creation went.
+#[derive(Debug, Clone, Default)]
+pub struct LpState {
+    pub burnt_pct: f64,
+    pub locks: Vec<LpLock>,
+}
+
+impl LpState {
+    /// Share of the LP burnt or in locks `rugcheck_config` accepts.
+    pub fn secured_pct(&self, rugcheck_config: &RugCheckConfig, now: i64) -> f64 {
+        let locked_pct: f64 = self.locks
+            .iter()
+            .filter(|lock| lock.is_accepted(rugcheck_config, now))
+            .map(|lock| lock.pct)
+            .sum();
+        self.burnt_pct + locked_pct
+    }
+}
+
+impl fmt::Display for LpState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}% of the LP burnt", self.burnt_pct)?;
+        for lock in &self.locks {
+            write!(f, ", {:.2}% locked in {} ", lock.pct, lock.locker)?;
+            match lock.unlocks_at {
+                _ if lock.permanent => write!(f, "permanently")?,
+                Some(unlocks_at) =>
+                    match chrono::DateTime::from_timestamp(unlocks_at, 0) {
+                        Some(time) => write!(f, "until {}", time.to_rfc3339())?,
+                        None => write!(f, "until {}", unlocks_at)?,
+                    }
+                None => write!(f, "until an unknown time")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// LP tokens among the largest LP holders that sit in `rugcheck.lp_lockers`.
+pub async fn find_lp_locks(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo,
+    rugcheck_config: &RugCheckConfig
+) -> Result<Vec<LpLock>, Box<dyn std::error::Error>> {
+    if rugcheck_config.lp_lockers.is_empty() || pool_info.lp_reserve == 0 {
+        return Ok(Vec::new());
+    }
+
+    let holders = get_top_holders(client, &pool_info.lp_mint).await?;
+    // Lock authorities are often PDAs without an account, those only match `authorities`
+    let owner_accounts = futures::future::join_all(
+        holders.iter().map(|holder| client.get_account(&holder.owner))
+    ).await;
+
+    let mut locks = Vec::new();
+    for (holder, owner_account) in holders.iter().zip(owner_accounts) {
+        let owner_account = owner_account.ok();
+        let owner = holder.owner.to_string();
+        let locker = rugcheck_config.lp_lockers.iter().find(|locker| {
+            locker.authorities.contains(&owner) ||
+                owner_account
+                    .as_ref()
+                    .is_some_and(|account| account.owner.to_string() == locker.program_id)
+        });
+        let Some(locker) = locker else {
+            continue;
         };
 
-        let mint_info = match Mint::unpack(acc_info.data.as_slice()) {
-            Ok(info) => info,
-            Err(_) => {
-                tokio::time::sleep(retry_interval).await;
-                continue;
+        let unlocks_at = match (locker.unlock_time_offset, &owner_account) {
+            (Some(offset), Some(account)) =>
+                account.data
+                    .get(offset..offset + 8)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(i64::from_le_bytes),
+            _ => None,
+        };
+        locks.push(LpLock {
+            locker: locker.name.clone(),
+            pct: ((holder.amount as f64) / (pool_info.lp_reserve as f64)) * 100.0,
+            unlocks_at,
+            permanent: locker.permanent,
+        });
+    }
+
+    Ok(locks)
+}
+
+/// Where the pool's LP went, read from the LP mint account.
+async fn read_lp_state(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo,
+    rugcheck_config: &RugCheckConfig,
+    mint_account: &Account
+) -> Result<LpState, Box<dyn std::error::Error>> {
+    let mut lp_state = LpState {
+        burnt_pct: lp_burn_pct(pool_info.lp_reserve, &unpack_mint(&mint_account.data)?),
+        locks: Vec::new(),
+    };
+    if lp_state.burnt_pct <= rugcheck_config.min_lp_burn_pct && rugcheck_config.accept_locked_lp {
+        // Unknown locks leave the LP counted as unlocked
+        lp_state.locks = find_lp_locks(client, pool_info, rugcheck_config).await.unwrap_or_else(
+            |err| {
+                eprintln!("Failed to look up LP locks of {}: {}", pool_info.lp_mint, err);
+                Vec::new()
+            }
+        );
+    }
+    Ok(lp_state)
+}
+
+/// Where the pool's LP went. With `wait`, watches the LP mint until the burnt and accepted
+/// locked LP clear `min_lp_burn_pct` or `lp_burn_timeout_secs` passes, and returns the last
+/// reading. With `lp_burn_subscribe` every supply change seen by any of `pubsub_clients`
+/// triggers a re-check right away; the mint is still polled every `lp_burn_retry_secs`,
+/// which also picks up LP moved into lockers.
+pub async fn wait_for_lp_state(
+    client: &dyn SolanaRpc,
+    pubsub_clients: &[Arc<PubsubClient>],
+    pool_info: &PoolInfo,
+    rugcheck_config: &RugCheckConfig,
+    wait: bool
+) -> Result<LpState, Box<dyn std::error::Error>> {
+    if !wait {
+        let account = client.get_account(&pool_info.lp_mint).await?;
+        return read_lp_state(client, pool_info, rugcheck_config, &account).await;
+    }
+
+    let deadline =
+        tokio::time::Instant::now() + Duration::from_secs(rugcheck_config.lp_burn_timeout_secs);
+    let retry_interval = Duration::from_secs(rugcheck_config.lp_burn_retry_secs);
+    let now = chrono::Utc::now().timestamp();
+    let mut lp_state = LpState::default();
+
+    // The LP mint may not be visible to the RPC node yet
+    if let Ok(account) = client.get_account(&pool_info.lp_mint).await {
+        lp_state = read_lp_state(client, pool_info, rugcheck_config, &account).await?;
+        if lp_state.secured_pct(rugcheck_config, now) > rugcheck_config.min_lp_burn_pct {
+            return Ok(lp_state);
+        }
+    }
+
+    let pub_subclients = if rugcheck_config.lp_burn_subscribe { pubsub_clients } else { &[] };
+    let mut streams = Vec::new();
+    let mut unsubscribes = Vec::new();
+    for pub_subclient in pub_subclients {
+        let subscription = pub_subclient.account_subscribe(
+            &pool_info.lp_mint,
+            Some(RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::processed()),
+                ..RpcAccountInfoConfig::default()
+            })
+        ).await;
+        match subscription {
+            Ok((stream, unsubscribe)) => {
+                streams.push(stream);
+                unsubscribes.push(unsubscribe);
+            }
+            Err(err) => {
+                eprintln!("Failed to subscribe to LP mint {}: {}", pool_info.lp_mint, err);
+            }
+        }
+    }
+    // Without a subscription the mint is only polled
+    let mut notifications = if streams.is_empty() { None } else { Some(select_all(streams)) };
+
+    // Read again now that the subscription is up, the burn may have landed in between
+    let mut account = client.get_account(&pool_info.lp_mint).await.ok();
+    loop {
+        if let Some(account) = account.take() {
+            lp_state = read_lp_state(client, pool_info, rugcheck_config, &account).await?;
+            let now = chrono::Utc::now().timestamp();
+            if lp_state.secured_pct(rugcheck_config, now) > rugcheck_config.min_lp_burn_pct {
+                break;
+            }
+        }
+
+        account = tokio::select! {
+            notification = next_notification(&mut notifications) => {
+                match notification {
+                    Some(response) => response.value.decode::<Account>(),
+                    None => {
+                        eprintln!(
+                            "LP mint subscriptions of {} ended, polling instead",
+                            pool_info.lp_mint
+                        );
+                        notifications = None;
+                        None
+                    }
+                }
+            }
+            _ = tokio::time::sleep(retry_interval) => {
+                client.get_account(&pool_info.lp_mint).await.ok()
+            }
+            _ = tokio::time::sleep_until(deadline) => {
+                break;
             }
         };
+    }
+
+    drop(notifications);
+    for unsubscribe in unsubscribes {
+        unsubscribe().await;
+    }
+
+    Ok(lp_state)
+}
+
+/// The next item of `stream`, or never when there is none to wait on.
+async fn next_notification<S: Stream + Unpin>(stream: &mut Option<S>) -> Option<S::Item> {
+    match stream {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Vault balances, falling back to the amounts deposited at pool creation. Dynamic pool
+/// vaults hold the tokens of every pool of the mint, so those always use the deposits.
+pub async fn pool_reserves(client: &dyn SolanaRpc, pool_info: &PoolInfo) -> (u64, u64) {
+    if pool_info.pool_type == PoolType::MeteoraDynamic {
+        return (pool_info.base_reserve, pool_info.quote_reserve);
+    }
 
-        // Calculate reserve and actual supply
-        let lp_reserve_amount = (lp_reserve as f64) / (10_f64).powi(mint_info.decimals as i32);
-        let actual_supply = (mint_info.supply as f64) / (10_f64).powi(mint_info.decimals as i32);
+    let base_reserve = match client.get_token_account_balance(&pool_info.base_vault).await {
+        Ok(balance) => balance.amount.parse().unwrap_or(pool_info.base_reserve),
+        Err(_) => pool_info.base_reserve,
+    };
+    let quote_reserve = match client.get_token_account_balance(&pool_info.quote_vault).await {
+        Ok(balance) => balance.amount.parse().unwrap_or(pool_info.quote_reserve),
+        Err(_) => pool_info.quote_reserve,
+    };
 
-        // Calculate burn amount and percentage
-        let burn_amt = lp_reserve_amount - actual_supply;
-        let burn_pct = (burn_amt / lp_reserve_amount) * 100.0;
+    (base_reserve, quote_reserve)
+}
 
-        if burn_pct > 80.0 {
-            let liquidity_usd = calculate_liquidity_usd(
-                client,
-                pool_info.base_vault,
-                pool_info.quote_vault
-            ).await?;
+/// USD value in the pool. With `wait`, pools created empty are polled until they clear
+/// `min_liquidity_usd` or `lp_burn_timeout_secs` passes, and the last reading is returned.
+pub async fn wait_for_liquidity(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo,
+    sol_price: f64,
+    rugcheck_config: &RugCheckConfig,
+    wait: bool
+) -> f64 {
+    let timeout = Duration::from_secs(rugcheck_config.lp_burn_timeout_secs);
+    let retry_interval = Duration::from_secs(rugcheck_config.lp_burn_retry_secs);
+    let start_time = tokio::time::Instant::now();
+    // Pools with an LP mint are created with their liquidity
+    let wait = wait && !pool_info.pool_type.has_lp_mint();
 
-            return Ok(liquidity_usd > 1000.0); // Return true if liquidity is greater than $1000
+    loop {
+        let (base_reserve, quote_reserve) = pool_reserves(client, pool_info).await;
+        // Empty vaults would price the base side at NaN
+        let liquidity = if base_reserve == 0 {
+            0.0
+        } else {
+            liquidity_usd(base_reserve as f64, quote_reserve as f64, sol_price)
+        };
+        if
+            !wait ||
+            liquidity > rugcheck_config.min_liquidity_usd ||
+            tokio::time::Instant::now().duration_since(start_time) > timeout
+        {
+            return liquidity;
         }
 
-        // Wait before retrying
         tokio::time::sleep(retry_interval).await;
     }
 }
 
 pub async fn get_top_holders(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     token: &Pubkey
 ) -> Result<Vec<TopHolder>, Box<dyn std::error::Error>> {
-    let token_accounts = match client.get_token_largest_accounts(token) {
+    let token_accounts = match client.get_token_largest_accounts(token).await {
         Ok(accounts) => accounts,
         Err(err) => {
             return Err(err.into());
         }
     };
 
-    let token_supply = match client.get_token_supply(token) {
-        Ok(supply) => supply.amount.parse::<f64>().unwrap(),
+    let token_supply = match client.get_token_supply(token).await {
+        Ok(supply) => supply.amount.parse::<f64>()?,
         Err(err) => {
             return Err(err.into());
         }
     };
 
-    let top_holders: Result<Vec<TopHolder>, Box<dyn std::error::Error>> = token_accounts
-        .into_iter()
-        .map(|account| {
-            let owner = Pubkey::from_str(&account.address).unwrap();
-            let amount = match account.amount.amount.parse::<u64>() {
-                Ok(amount) => amount,
-                Err(err) => {
-                    return Err(err.into());
-                }
-            };
-            let pct = match ((amount as f64) / token_supply) * 100.0 {
-                pct if pct.is_nan() => {
-                    return Err("Percentage calculation resulted in NaN".into());
-                }
-                pct => pct,
-            };
-            Ok(TopHolder {
-                owner,
-                amount,
-                pct,
-            })
-        })
-        .collect();
-    let top_holders = top_holders.map_err(|err| err)?;
+    let addresses = token_accounts
+        .iter()
+        .map(|account| Pubkey::from_str(&account.address))
+        .collect::<Result<Vec<Pubkey>, _>>()?;
+    // The largest accounts are token accounts, holders are the wallets that own them
+    let owner_accounts = futures::future::try_join_all(
+        addresses.iter().map(|address| client.get_account(address))
+    ).await?;
+
+    let mut top_holders = Vec::with_capacity(token_accounts.len());
+    for ((account, address), owner_account) in token_accounts
+        .iter()
+        .zip(addresses)
+        .zip(owner_accounts) {
+        let owner = unpack_token_account_owner(&owner_account.data)?;
+        let amount = account.amount.amount.parse::<u64>()?;
+        let pct = match ((amount as f64) / token_supply) * 100.0 {
+            pct if pct.is_nan() => {
+                return Err("Percentage calculation resulted in NaN".into());
+            }
+            pct => pct,
+        };
+        top_holders.push(TopHolder {
+            address,
+            owner,
+            amount,
+            pct,
+        });
+    }
     Ok(top_holders)
 }
 
+/// The owner of a legacy or Token-2022 token account, both start with the SPL token layout.
+fn unpack_token_account_owner(data: &[u8]) -> Result<Pubkey, ProgramError> {
+    let data = data.get(..TokenAccount::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    Ok(TokenAccount::unpack(data)?.owner)
+}
+
 pub async fn check_rug_sol(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     token: &Pubkey
 ) -> Result<bool, Box<dyn std::error::Error>> {
-    let mint_account = client.get_account(&token)?;
-    let mint_data = mint_account.data.as_slice();
+    let mint_account = client.get_account(token).await?;
 
-    let mint_token = Mint::unpack(mint_data)?;
-    let mint_authority = mint_token.mint_authority;
-    let freeze_authority = mint_token.freeze_authority;
+    let risks = mint_risks(&mint_account)?;
+    if !risks.is_empty() {
+        println!("Mint {} flagged: {:?}", token, risks);
+    }
 
-    let is_rug = mint_authority.is_some() || freeze_authority.is_some();
-    Ok(is_rug)
+    Ok(!risks.is_empty())
 }
 
 pub async fn pre_rug_check(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     token: &Pubkey
 ) -> Result<bool, Box<dyn std::error::Error>> {
     if token.to_string() == "So11111111111111111111111111111111111111112" {
@@ -224,36 +542,53 @@ pub async fn pre_rug_check(
 
     let is_freeze_and_mint_disabled = check_rug_sol(client, token).await?;
 
-    return Ok(is_freeze_and_mint_disabled);
+    Ok(is_freeze_and_mint_disabled)
 }
 
-async fn post_rug_check(
-    client: &RpcClient,
-    token: &Pubkey
-) -> Result<bool, Box<dyn std::error::Error>> {
-    // Check if Raydium is the largest holder and exclude it from the large holder check
-    let raydium_address = "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1";
+/// Why the token's supply is too concentrated, see the holder limits of `RugCheckConfig`.
+/// `pool_accounts` are the token accounts of the pool itself, which hold most of a new supply.
+pub async fn post_rug_check(
+    client: &dyn SolanaRpc,
+    token: &Pubkey,
+    pool_accounts: &[Pubkey],
+    rugcheck_config: &RugCheckConfig
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let excluded_owners = rugcheck_config.excluded_holders
+        .iter()
+        .map(|owner| Pubkey::from_str(owner))
+        .collect::<Result<Vec<Pubkey>, _>>()?;
 
     let top_holders = get_top_holders(client, token).await?;
-    // Check if Raydium is the largest holder and exclude it from the large holder check
-    let raydium_is_largest = top_holders
-        .first()
-        .map_or(false, |holder| holder.owner.to_string() == raydium_address);
+    // One wallet can own several token accounts of the mint
+    let mut holders: Vec<(Pubkey, f64)> = Vec::new();
+    for holder in top_holders
+        .iter()
+        .filter(|holder| !pool_accounts.contains(&holder.address))
+        .filter(|holder| !excluded_owners.contains(&holder.owner)) {
+        match holders.iter_mut().find(|(owner, _)| *owner == holder.owner) {
+            Some((_, pct)) => {
+                *pct += holder.pct;
+            }
+            None => holders.push((holder.owner, holder.pct)),
+        }
+    }
+    holders.sort_by(|a, b| b.1.total_cmp(&a.1));
+    holders.truncate(rugcheck_config.top_holders);
 
-    if !raydium_is_largest {
-        return Ok(true); // Consider it a rug if Raydium is not the largest holder
+    if let Some((owner, pct)) = holders.first() {
+        if *pct > rugcheck_config.max_single_holder_pct {
+            return Ok(Some(format!("{} holds {:.2}% of the supply", owner, pct)));
+        }
     }
 
-    // Check if any of the top holders (excluding Raydium) have more than 20%
-    let large_holder = top_holders
+    let top_pct: f64 = holders
         .iter()
-        .filter(|holder| holder.owner.to_string() != raydium_address)
-        .any(|holder| holder.pct > 20.0);
-
-    // If no large holder is found and both authorities are None, return false (not a rug)
-    if !large_holder {
-        Ok(false)
-    } else {
-        Ok(true) // Otherwise, consider it as a rug
+        .map(|(_, pct)| pct)
+        .sum();
+    if top_pct > rugcheck_config.max_top_holders_pct {
+        let reason = format!("Top {} holders hold {:.2}% of the supply", holders.len(), top_pct);
+        return Ok(Some(reason));
     }
+
+    Ok(None)
 }
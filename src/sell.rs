@@ -0,0 +1,519 @@
+use crate::config::{ Executor, SellConfig };
+use crate::context::AppContext;
+use crate::mongo::{ BuyTransaction, SellTransaction };
+use crate::quote::{ quote_exact_in, RAYDIUM_FEE_BPS };
+use crate::pump_sdk::{ BondingCurveAccount, PUMP_FEE_BPS, PUMP_TOKEN_DECIMALS };
+use crate::pump_swap::{ self, PumpSide, PumpTradeParams };
+use crate::raydium_sdk::ClmmPoolState;
+use crate::raydium_swap::{ self, SwapParams };
+use crate::swap::fetch_pool_keys;
+use crate::utils::PoolType;
+use crate::redis;
+use crate::rugcheck::get_current_sol_price;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use crate::rpc::{ confirm_transaction, SolanaRpc };
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SellReason {
+    TakeProfit(usize),
+    StopLoss,
+    TrailingStop,
+    MaxHoldTime,
+}
+
+/// Tracking for a single open position between polls. The ladder progress is mirrored on
+/// the Mongo position, the peak price only lives in memory.
+#[derive(Debug, Clone)]
+struct PositionState {
+    peak_price: f64,
+    levels_hit: usize,
+    remaining: f64,
+}
+
+struct PoolPrice {
+    /// SOL per token
+    price: f64,
+    token_reserve: u64,
+    sol_reserve: u64,
+    token_decimals: u8,
+    fee_bps: u64,
+}
+
+pub async fn monitor_positions(ctx: Arc<AppContext>) -> Result<(), Box<dyn Error>> {
+    let config = &ctx.config;
+    let mut states: HashMap<ObjectId, PositionState> = HashMap::new();
+
+    loop {
+        if let Err(err) = check_positions(&ctx, &config.sell, &mut states).await {
+            eprintln!("Failed to check open positions: {}", err);
+        }
+
+        tokio::time::sleep(Duration::from_secs(config.sell.poll_interval_secs)).await;
+    }
+}
+
+async fn check_positions(
+    ctx: &AppContext,
+    sell_config: &SellConfig,
+    states: &mut HashMap<ObjectId, PositionState>
+) -> Result<(), Box<dyn Error>> {
+    let rpc_client = ctx.rpc_client.as_ref();
+    let mongo_handler = &ctx.mongo;
+    let positions = mongo_handler.fetch_open_positions("solsniper", "tokens").await?;
+
+    // Forget positions that were closed elsewhere
+    states.retain(|id, _| positions.iter().any(|(open_id, _)| open_id == id));
+
+    for (id, position) in positions {
+        let pool_price = match fetch_pool_price(rpc_client, &position).await {
+            Ok(pool_price) => pool_price,
+            Err(err) => {
+                eprintln!("Failed to price position {}: {}", position.transaction_signature, err);
+                continue;
+            }
+        };
+
+        let state = states
+            .entry(id)
+            .or_insert_with(|| restore_state(&position, pool_price.price));
+        state.peak_price = state.peak_price.max(pool_price.price);
+
+        let now_ms = DateTime::now().timestamp_millis();
+        let decision = evaluate_position(sell_config, &position, state, pool_price.price, now_ms);
+        if let Some((reason, amount)) = decision {
+            println!(
+                "Selling {} tokens of position {}: {:?}",
+                amount,
+                position.transaction_signature,
+                reason
+            );
+            // A failed sell is retried on the next poll, the other positions still get checked
+            if let Err(err) = execute_sell(ctx, sell_config, &position, &pool_price, amount).await {
+                eprintln!("Failed to sell position {}: {}", position.transaction_signature, err);
+                continue;
+            }
+
+            state.remaining -= amount;
+            if let SellReason::TakeProfit(level) = reason {
+                state.levels_hit = level + 1;
+            }
+
+            if state.remaining <= position.amount * 0.0001 {
+                mongo_handler.mark_position_sold("solsniper", "tokens", id).await?;
+                states.remove(&id);
+            } else {
+                mongo_handler.update_position_progress(
+                    "solsniper",
+                    "tokens",
+                    id,
+                    state.levels_hit as u32,
+                    state.remaining
+                ).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// State of a position first seen since startup, resuming the ladder where the last run
+/// left it.
+fn restore_state(position: &BuyTransaction, price: f64) -> PositionState {
+    PositionState {
+        peak_price: price,
+        levels_hit: position.levels_hit as usize,
+        remaining: position.remaining.unwrap_or(position.amount),
+    }
+}
+
+fn entry_price(position: &BuyTransaction) -> f64 {
+    if position.entry_price > 0.0 {
+        position.entry_price
+    } else {
+        position.sol_amount / position.amount
+    }
+}
+
+/// Decides whether the position should be (partially) sold at `price`, returning the
+/// triggering rule and the token amount to sell.
+fn evaluate_position(
+    sell_config: &SellConfig,
+    position: &BuyTransaction,
+    state: &PositionState,
+    price: f64,
+    now_ms: i64
+) -> Option<(SellReason, f64)> {
+    let change_pct = (price / entry_price(position) - 1.0) * 100.0;
+
+    if change_pct <= -sell_config.stop_loss_pct {
+        return Some((SellReason::StopLoss, state.remaining));
+    }
+
+    if
+        sell_config.trailing_stop_pct > 0.0 &&
+        price <= state.peak_price * (1.0 - sell_config.trailing_stop_pct / 100.0)
+    {
+        return Some((SellReason::TrailingStop, state.remaining));
+    }
+
+    let held_ms = now_ms - position.created_at.timestamp_millis();
+    if sell_config.max_hold_secs > 0 && held_ms >= (sell_config.max_hold_secs as i64) * 1000 {
+        return Some((SellReason::MaxHoldTime, state.remaining));
+    }
+
+    // Only the highest reached level fires, earlier unfired levels are folded into it
+    let reached = sell_config.take_profit
+        .iter()
+        .enumerate()
+        .skip(state.levels_hit)
+        .rfind(|(_, level)| change_pct >= level.gain_pct);
+    if let Some((index, _)) = reached {
+        let sell_pct: f64 = sell_config.take_profit[state.levels_hit..=index]
+            .iter()
+            .map(|level| level.sell_pct)
+            .sum();
+        let amount = (position.amount * sell_pct / 100.0).min(state.remaining);
+        return Some((SellReason::TakeProfit(index), amount));
+    }
+
+    None
+}
+
+async fn fetch_pool_price(
+    rpc_client: &dyn SolanaRpc,
+    position: &BuyTransaction
+) -> Result<PoolPrice, Box<dyn Error>> {
+    let token_info = &position.token_info;
+    if let Some(bonding_curve) = &token_info.bonding_curve {
+        return fetch_bonding_curve_price(rpc_client, bonding_curve).await;
+    }
+    let fee_bps = token_info.trade_fee_bps.unwrap_or(RAYDIUM_FEE_BPS);
+    if let (PoolType::Clmm, Some(pool_id)) = (token_info.pool_type, &token_info.pool_id) {
+        return fetch_clmm_price(rpc_client, pool_id, fee_bps).await;
+    }
+
+    let base_balance = rpc_client.get_token_account_balance(
+        &Pubkey::from_str(&token_info.base_vault)?
+    ).await?;
+    let quote_balance = rpc_client.get_token_account_balance(
+        &Pubkey::from_str(&token_info.quote_vault)?
+    ).await?;
+
+    let (token_balance, sol_balance) = if token_info.base_mint == WSOL_MINT {
+        (quote_balance, base_balance)
+    } else {
+        (base_balance, quote_balance)
+    };
+
+    let token_ui = token_balance.ui_amount.unwrap_or(0.0);
+    let sol_ui = sol_balance.ui_amount.unwrap_or(0.0);
+    if token_ui == 0.0 {
+        return Err("Pool token vault is empty".into());
+    }
+
+    Ok(PoolPrice {
+        price: sol_ui / token_ui,
+        token_reserve: token_balance.amount.parse()?,
+        sol_reserve: sol_balance.amount.parse()?,
+        token_decimals: token_balance.decimals,
+        fee_bps,
+    })
+}
+
+async fn fetch_bonding_curve_price(
+    rpc_client: &dyn SolanaRpc,
+    bonding_curve: &str
+) -> Result<PoolPrice, Box<dyn Error>> {
+    let account = rpc_client.get_account(&Pubkey::from_str(bonding_curve)?).await?;
+    let curve = BondingCurveAccount::decode(&account.data).ok_or(
+        "Not a bonding curve account"
+    )?;
+    if curve.complete {
+        return Err("Bonding curve is complete, the token is migrating to Raydium".into());
+    }
+
+    Ok(PoolPrice {
+        price: curve.price(),
+        token_reserve: curve.virtual_token_reserves,
+        sol_reserve: curve.virtual_sol_reserves,
+        token_decimals: PUMP_TOKEN_DECIMALS,
+        fee_bps: PUMP_FEE_BPS,
+    })
+}
+
+/// Prices a CLMM position on the pool's current sqrt price and active liquidity.
+async fn fetch_clmm_price(
+    rpc_client: &dyn SolanaRpc,
+    pool_id: &str,
+    fee_bps: u64
+) -> Result<PoolPrice, Box<dyn Error>> {
+    let account = rpc_client.get_account(&Pubkey::from_str(pool_id)?).await?;
+    let pool_state = ClmmPoolState::decode(&account.data).ok_or("Not a CLMM pool account")?;
+    let (reserve_0, reserve_1) = pool_state.virtual_reserves();
+    let price_0 = pool_state.price();
+    if price_0 == 0.0 {
+        return Err("CLMM pool has no price".into());
+    }
+
+    let token_is_0 = pool_state.token_mint_0.to_string() != WSOL_MINT;
+    Ok(PoolPrice {
+        price: if token_is_0 { price_0 } else { 1.0 / price_0 },
+        token_reserve: if token_is_0 { reserve_0 } else { reserve_1 },
+        sol_reserve: if token_is_0 { reserve_1 } else { reserve_0 },
+        token_decimals: if token_is_0 {
+            pool_state.mint_decimals_0
+        } else {
+            pool_state.mint_decimals_1
+        },
+        fee_bps,
+    })
+}
+
+/// Profit in SOL and percent of selling `amount` of the position for `sol_out`. Positions
+/// recorded without an amount or cost have no basis to compare to, their percentage is 0.
+fn realized_profit(position: &BuyTransaction, amount: f64, sol_out: f64) -> (f64, f64) {
+    let cost_basis = if position.amount > 0.0 {
+        position.sol_amount * (amount / position.amount)
+    } else {
+        0.0
+    };
+    let profit = sol_out - cost_basis;
+    if cost_basis > 0.0 {
+        (profit, (profit / cost_basis) * 100.0)
+    } else {
+        (profit, 0.0)
+    }
+}
+
+async fn execute_sell(
+    ctx: &AppContext,
+    sell_config: &SellConfig,
+    position: &BuyTransaction,
+    pool_price: &PoolPrice,
+    amount: f64
+) -> Result<(), Box<dyn Error>> {
+    let token_info = &position.token_info;
+    let token_mint = if token_info.base_mint == WSOL_MINT {
+        &token_info.quote_mint
+    } else {
+        &token_info.base_mint
+    };
+
+    let amount_in = (amount * (10_f64).powi(pool_price.token_decimals as i32)) as u64;
+    let quote = quote_exact_in(
+        amount_in,
+        pool_price.token_reserve,
+        pool_price.sol_reserve,
+        pool_price.fee_bps,
+        sell_config.slippage_bps
+    )?;
+    let sol_out = (quote.amount_out as f64) / 1_000_000_000.0;
+    let min_sol_out = (quote.min_amount_out as f64) / 1_000_000_000.0;
+
+    let confirm_timeout = Duration::from_secs(ctx.config.buy.confirm_timeout_secs);
+    // The executor fills in the signature once the order lands
+    let mut transaction_signature = String::new();
+    match &token_info.bonding_curve {
+        Some(bonding_curve) if ctx.config.buy.executor == Executor::Native => {
+            let account = ctx.rpc_client.get_account(&Pubkey::from_str(bonding_curve)?).await?;
+            let curve = BondingCurveAccount::decode(&account.data).ok_or(
+                "Not a bonding curve account"
+            )?;
+            let params = PumpTradeParams {
+                side: PumpSide::Sell,
+                mint: Pubkey::from_str(token_mint)?,
+                creator: curve.creator,
+                token_amount: amount_in,
+                sol_limit: quote.min_amount_out,
+                compute_unit_limit: ctx.config.buy.compute_unit_limit,
+                compute_unit_price: ctx.config.buy.compute_unit_price,
+            };
+            let signature = pump_swap::trade(
+                ctx.rpc_client.as_ref(),
+                &ctx.keypair,
+                &ctx.config.pump_program_id(),
+                &params
+            ).await?;
+            confirm_transaction(ctx.rpc_client.as_ref(), &signature, confirm_timeout).await?;
+            transaction_signature = signature.to_string();
+        }
+        Some(bonding_curve) => {
+            ctx.redis.sell(redis::SellTransaction {
+                type_: "pump_sell".to_string(),
+                in_token: token_mint.clone(),
+                out_token: WSOL_MINT.to_string(),
+                amount_in: amount,
+                min_amount_out: min_sol_out,
+                base_vault: token_info.base_vault.clone(),
+                quote_vault: bonding_curve.clone(),
+                pool_type: token_info.pool_type,
+            }).await?;
+        }
+        None if ctx.config.buy.executor == Executor::Native => {
+            let pool_id = token_info.pool_id
+                .as_ref()
+                .ok_or("Position has no pool id to sell through")?;
+            let pool_keys = fetch_pool_keys(
+                Arc::clone(&ctx.rpc_client),
+                token_info.pool_type,
+                Pubkey::from_str(pool_id)?
+            ).await?;
+            let params = SwapParams {
+                input_mint: Pubkey::from_str(token_mint)?,
+                output_mint: spl_token::native_mint::id(),
+                amount_in,
+                min_amount_out: quote.min_amount_out,
+                compute_unit_limit: ctx.config.buy.compute_unit_limit,
+                compute_unit_price: ctx.config.buy.compute_unit_price,
+                wrap_sol: false,
+                // Buys wrap from native SOL, so sells hand it back the same way
+                unwrap_sol: ctx.config.buy.wrap_sol,
+            };
+            let signature = raydium_swap::swap(
+                ctx.rpc_client.as_ref(),
+                &ctx.keypair,
+                &pool_keys,
+                &params
+            ).await?;
+            confirm_transaction(ctx.rpc_client.as_ref(), &signature, confirm_timeout).await?;
+            transaction_signature = signature.to_string();
+        }
+        None => {
+            ctx.redis.sell(redis::SellTransaction {
+                type_: "sell".to_string(),
+                in_token: token_mint.clone(),
+                out_token: WSOL_MINT.to_string(),
+                amount_in: amount,
+                min_amount_out: min_sol_out,
+                base_vault: token_info.base_vault.clone(),
+                quote_vault: token_info.quote_vault.clone(),
+                pool_type: token_info.pool_type,
+            }).await?;
+        }
+    }
+
+    let (profit, profit_percentage) = realized_profit(position, amount, sol_out);
+    let sol_price = get_current_sol_price().await.unwrap_or(0.0);
+
+    let sell = SellTransaction {
+        transaction_signature,
+        token_info: token_info.clone(),
+        amount,
+        sol_amount: sol_out,
+        sol_price,
+        sell_price: pool_price.price,
+        profit,
+        profit_percentage,
+        created_at: DateTime::now(),
+    };
+    ctx.mongo.insert_sell("solsniper", "sells", &sell).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::token_amount;
+    use crate::mongo::{ TokenInfo, TokenMetadata };
+    use crate::rpc::FakeRpc;
+
+    fn position(levels_hit: u32, remaining: Option<f64>) -> BuyTransaction {
+        BuyTransaction {
+            transaction_signature: String::new(),
+            token_info: TokenInfo {
+                base_mint: Pubkey::new_unique().to_string(),
+                quote_mint: WSOL_MINT.to_string(),
+                base_vault: Pubkey::new_unique().to_string(),
+                quote_vault: Pubkey::new_unique().to_string(),
+                bonding_curve: None,
+                pool_type: PoolType::AmmV4,
+                pool_id: None,
+                trade_fee_bps: None,
+            },
+            amount: 1000.0,
+            sol_amount: 1.0,
+            sol_price: 0.0,
+            entry_price: 0.001,
+            token_metadata: TokenMetadata::default(),
+            created_at: DateTime::now(),
+            sold: false,
+            levels_hit,
+            remaining,
+        }
+    }
+
+    #[test]
+    fn fires_the_first_take_profit_level() {
+        let position = position(0, None);
+        let state = restore_state(&position, 0.0016);
+
+        let decision = evaluate_position(&SellConfig::default(), &position, &state, 0.0016, 0);
+        assert_eq!(decision, Some((SellReason::TakeProfit(0), 500.0)));
+    }
+
+    #[test]
+    fn restored_positions_do_not_repeat_sold_levels() {
+        // The first level sold half the position before a restart
+        let position = position(1, Some(500.0));
+        let state = restore_state(&position, 0.0016);
+        assert_eq!(state.remaining, 500.0);
+
+        let sell_config = SellConfig::default();
+        assert_eq!(evaluate_position(&sell_config, &position, &state, 0.0016, 0), None);
+        assert_eq!(
+            evaluate_position(&sell_config, &position, &state, 0.0021, 0),
+            Some((SellReason::TakeProfit(1), 500.0))
+        );
+    }
+
+    #[test]
+    fn stop_loss_sells_only_what_is_left() {
+        let position = position(1, Some(500.0));
+        let state = restore_state(&position, 0.0005);
+
+        let decision = evaluate_position(&SellConfig::default(), &position, &state, 0.0005, 0);
+        assert_eq!(decision, Some((SellReason::StopLoss, 500.0)));
+    }
+
+    #[tokio::test]
+    async fn prices_exits_at_the_fee_of_the_pool() {
+        let mut position = position(0, None);
+        position.token_info.pool_type = PoolType::Cpmm;
+        position.token_info.trade_fee_bps = Some(100);
+        let rpc = FakeRpc::new();
+        rpc.insert_token_balance(
+            Pubkey::from_str(&position.token_info.base_vault).unwrap(),
+            token_amount(1_000_000_000_000, 6)
+        );
+        rpc.insert_token_balance(
+            Pubkey::from_str(&position.token_info.quote_vault).unwrap(),
+            token_amount(2_000_000_000, 9)
+        );
+
+        let pool_price = fetch_pool_price(rpc.as_ref(), &position).await.unwrap();
+        assert_eq!(pool_price.fee_bps, 100);
+        assert_eq!(pool_price.token_reserve, 1_000_000_000_000);
+        assert_eq!(pool_price.sol_reserve, 2_000_000_000);
+    }
+
+    #[test]
+    fn positions_without_a_cost_store_no_profit_percentage() {
+        let mut position = position(0, None);
+        assert_eq!(realized_profit(&position, 500.0, 0.75), (0.25, 50.0));
+
+        position.sol_amount = 0.0;
+        assert_eq!(realized_profit(&position, 500.0, 0.75), (0.75, 0.0));
+        position.amount = 0.0;
+        assert_eq!(realized_profit(&position, 500.0, 0.75), (0.75, 0.0));
+    }
+}
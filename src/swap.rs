@@ -3,32 +3,67 @@ use crate::redis::LiquidityPoolKeysString;
 use crate::utils;
 use crate::redis;
 use crate::rugcheck;
-use solana_client::rpc_client::RpcClient;
-use std::str::FromStr;
+use crate::config::{ Config, Executor, PumpTrigger };
+use crate::pump_sdk::{
+    get_associated_bonding_curve,
+    get_bonding_curve,
+    BondingCurveAccount,
+    CreateEvent,
+};
+use crate::pump_swap::{ self, PumpSide, PumpTradeParams };
+use crate::raydium_swap::{ self, SwapParams };
+use crate::quote::{ fetch_pool_reserves, quote_pool };
+use crate::rpc::{ confirm_transaction, SolanaRpc };
+use crate::context::AppContext;
+use crate::detector::{
+    orient_sides,
+    parse_amount,
+    parse_pubkey,
+    pool_detectors,
+    pool_info_from_sides,
+    CreationInstruction,
+    PoolDetector,
+    PoolSide,
+    PoolTransaction,
+};
 use std::convert::From;
 use raydium_sdk::MarketStateLayoutV3;
 use raydium_sdk::get_associated_authority;
+use raydium_sdk::{ get_amm_authority, get_cpmm_authority, AmmInfoLayoutV4 };
+use raydium_sdk::{ ClmmPoolState, CpmmPoolState };
+use raydium_sdk::LiquidityPoolKeys;
+use raydium_sdk::Initialize2InstructionData;
+use raydium_sdk::{ ClmmCreatePoolInstructionData, CpmmInitializeInstructionData };
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_sdk::native_token::sol_to_lamports;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ Signature, Signer };
+use spl_associated_token_account::get_associated_token_address_with_program_id;
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
 use solana_transaction_status::EncodedTransaction;
 use solana_transaction_status::UiMessage;
 use solana_transaction_status::UiInstruction;
 use solana_transaction_status::UiTransactionTokenBalance;
 use solana_transaction_status::UiParsedInstruction;
-use solana_transaction_status::option_serializer::OptionSerializer;
 use solana_transaction_status::UiInnerInstructions;
 use solana_transaction_status::parse_instruction::ParsedInstruction;
-use rugcheck::{ check_burnt_lp, pre_rug_check };
+use rugcheck::pre_rug_check;
+use crate::rug_rules::{ evaluate_rules, RuleContext, RugVerdict };
+use crate::mongo::{ self, PoolVerdict, TokenInfo, TokenMetadata };
+use crate::metadata::{ fetch_token_metadata, FetchedMetadata, MetadataFetcher };
+use crate::mongo::MongoHandler;
+use mongodb::bson::DateTime;
+use chrono::Utc;
 use utils::fix_relaxed_json_in_lp_log_entry;
-use utils::PoolInfo;
+use utils::{ PoolInfo, PoolType };
 use utils::find_log_entry;
 use std::error::Error;
 use thiserror::Error;
 use serde_json::{ Value, Result as JsonResult };
-use redis::buy;
 use std::sync::Arc;
+use std::time::Duration;
 use borsh::BorshDeserialize;
-use redis::BuyTransaction;
+use redis::{ BuyTransaction, PumpBuyTransaction };
 
 // Define a custom error type for your application
 #[derive(Debug, Error)]
@@ -37,166 +72,593 @@ pub enum PoolError {
     BaseMintIsSOL,
     #[error("Rug detected")]
     RugDetected,
-    #[error("LP is not burnt")]
-    LPNotBurnt,
-    #[error("No pool info found")]
-    NoPoolInfoFound,
+    #[error("Rejected by rug check: {0}")] Rejected(RugVerdict),
+    #[error("Pool is not a pump.fun migration")]
+    NotPumpMigration,
+    #[error("Pool opens for trading in {0}s")] OpensTooLate(u64),
+    #[error("Failed to parse pool: {0}")] Parse(#[from] PoolParseError),
     #[error("Buy error: {0}")] BuyError(String),
     #[error("{0}")] Other(Box<dyn Error>), // Generic variant for other errors
 }
 
-pub async fn check_for_new_pool(
+/// Why a Raydium pool creation transaction could not be turned into a `PoolInfo`.
+#[derive(Debug, Error)]
+pub enum PoolParseError {
+    #[error("Transaction has no status meta")]
+    MissingMeta,
+    #[error("Transaction meta has no {0}")] MissingMetaField(&'static str),
+    #[error("Transaction is not JSON parsed")]
+    UnsupportedEncoding,
+    #[error("No pool creation instruction for programs {0:?}")] InitInstructionNotFound(
+        Vec<Pubkey>,
+    ),
+    #[error("Initialize instruction has no account #{0}")] MissingAccount(usize),
+    #[error("No initializeMint for LP mint {0}")] LpInitializeMintNotFound(String),
+    #[error("No mintTo for LP mint {0}")] LpMintToNotFound(String),
+    #[error("No transfer into vault {0}")] VaultTransferNotFound(String),
+    #[error("No init_pc_amount log entry")]
+    LogEntryNotFound,
+    #[error("Invalid init_pc_amount log entry: {0}")] InvalidLogEntry(#[from] serde_json::Error),
+    #[error("No pre token balance for mint {0}")] PreBalanceNotFound(String),
+    #[error("No token balance for mint {0}")] MintDecimalsNotFound(String),
+    #[error("No post token balance for account {0}")] TokenBalanceNotFound(String),
+    #[error("Undecodable {0:?} instruction data")] InvalidInstructionData(PoolType),
+    #[error("Missing {0} in parsed instruction")] MissingField(&'static str),
+    #[error("Invalid pubkey {0}")] InvalidPubkey(String),
+    #[error("Invalid amount {0}")] InvalidAmount(String),
+}
+
+/// Where `screen_new_pool` looks pools up. The sniper backs it with the live handles of
+/// `AppContext`, the backtest with fixtures and no Mongo.
+pub struct PoolScreen<'a> {
+    pub rpc: &'a dyn SolanaRpc,
+    pub config: &'a Config,
+    pub pubsub_clients: Vec<Arc<PubsubClient>>,
+    /// Verdicts are stored here when set
+    pub mongo: Option<&'a MongoHandler>,
+    pub metadata_fetcher: Option<&'a dyn MetadataFetcher>,
+    /// USD price of SOL, fetched from Birdeye when `None`
+    pub sol_price: Option<f64>,
+    /// See `RuleContext::wait`
+    pub wait: bool,
+}
+
+impl<'a> PoolScreen<'a> {
+    pub fn live(ctx: &'a AppContext) -> Self {
+        PoolScreen {
+            rpc: ctx.rpc_client.as_ref(),
+            config: ctx.config.as_ref(),
+            pubsub_clients: ctx.pubsub_clients(),
+            mongo: Some(&ctx.mongo),
+            metadata_fetcher: ctx.metadata_fetcher.as_deref(),
+            sol_price: None,
+            wait: true,
+        }
+    }
+}
+
+/// A pool that went through the rug check. The trade fee of CPMM and CLMM pools that
+/// passed is filled in from their config account.
+pub struct ScreenedPool {
+    pub pool_info: PoolInfo,
+    pub verdict: RugVerdict,
+    pub metadata: Option<FetchedMetadata>,
+}
+
+/// Parses a pool creation transaction and runs it through the rug check, everything
+/// `check_for_new_pool` does before buying. Rejected pools are returned with their verdict.
+pub async fn screen_new_pool(
     tx: EncodedConfirmedTransactionWithStatusMeta,
-    rpc_client: &Arc<RpcClient>,
-    sol_amount: f64
-) -> Result<String, PoolError> {
-    let inner_instructions: Vec<UiInnerInstructions> = tx.transaction.meta
-        .as_ref()
-        .and_then(|data| {
-            match &data.inner_instructions {
-                OptionSerializer::Some(inner) => Some(inner.clone()),
-                _ => None,
-            }
-        })
-        .unwrap();
-
-    let log_messages: Vec<String> = tx.transaction.meta
-        .as_ref()
-        .and_then(|data| {
-            match &data.log_messages {
-                OptionSerializer::Some(inner) => Some(inner.clone()),
-                _ => None,
-            }
-        })
-        .unwrap();
-    let pre_token_balances: Vec<UiTransactionTokenBalance> = tx.transaction.meta
-        .as_ref()
-        .and_then(|data| {
-            match &data.pre_token_balances {
-                OptionSerializer::Some(inner) => Some(inner.clone()),
-                _ => None,
-            }
+    screen: &PoolScreen<'_>
+) -> Result<ScreenedPool, PoolError> {
+    let config = screen.config;
+    let is_migration = is_signed_by(&tx, &config.pump.migration_authority);
+    if config.pump.enabled && config.pump.trigger == PumpTrigger::Migration && !is_migration {
+        return Err(PoolError::NotPumpMigration);
+    }
+    let mut pool_info: PoolInfo = parse_new_pool(tx, &pool_detectors(config))?;
+
+    if pool_info.base_mint == spl_token::native_mint::id() {
+        return Err(PoolError::BaseMintIsSOL);
+    }
+    // The migration authority creates every migrated pool, the creator rule needs the
+    // token's own creator
+    if is_migration {
+        pool_info.creator = fetch_pump_creator(
+            screen.rpc,
+            &config.pump_program_id(),
+            &pool_info.base_mint
+        ).await.map_err(PoolError::Other)?;
+    }
+
+    // Missing metadata is a finding of the metadata rules, not a reason to skip the pool
+    let metadata = if config.rugcheck.needs_metadata() {
+        fetch_token_metadata(
+            screen.rpc,
+            screen.metadata_fetcher,
+            &pool_info.base_mint
+        ).await.unwrap_or_else(|err| {
+            eprintln!("Failed to read the metadata of {}: {}", pool_info.base_mint, err);
+            None
         })
-        .unwrap();
+    } else {
+        None
+    };
 
-    let sol_pubkey: Pubkey = Pubkey::from_str(
-        "So11111111111111111111111111111111111111112"
-    ).unwrap();
-    let raydium_pubkey = Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8").unwrap();
+    let rule_context = RuleContext {
+        rpc: screen.rpc,
+        pubsub_clients: &screen.pubsub_clients,
+        pool_info: &pool_info,
+        config: &config.rugcheck,
+        mongo: screen.mongo,
+        metadata: metadata.as_ref(),
+        sol_price: screen.sol_price,
+        wait: screen.wait,
+    };
+    let verdict = evaluate_rules(&rule_context).await.map_err(PoolError::Other)?;
+    if let Some(mongo) = screen.mongo {
+        let record = PoolVerdict {
+            pool_id: pool_info.id.to_string(),
+            base_mint: pool_info.base_mint.to_string(),
+            pool_type: pool_info.pool_type,
+            creator: pool_info.creator.to_string(),
+            verdict: verdict.clone(),
+            token_metadata: metadata
+                .as_ref()
+                .map(|metadata| metadata.token_metadata(&pool_info.base_mint)),
+            created_at: DateTime::now(),
+        };
+        if let Err(err) = mongo.insert_verdict("solsniper", "verdicts", &record).await {
+            eprintln!("Failed to store the verdict of pool {}: {}", pool_info.id, err);
+        }
+    }
+    // CPMM and CLMM pools charge the fee of their config account
+    if verdict.passed && matches!(pool_info.pool_type, PoolType::Cpmm | PoolType::Clmm) {
+        pool_info.trade_fee_bps = fetch_trade_fee_bps(screen.rpc, &pool_info).await.map_err(
+            PoolError::Other
+        )?;
+    }
 
-    let info: Option<PoolInfo> = parse_pool_info_from_lp_transaction(
+    Ok(ScreenedPool {
+        pool_info,
+        verdict,
+        metadata,
+    })
+}
+
+pub async fn check_for_new_pool(
+    tx: EncodedConfirmedTransactionWithStatusMeta,
+    ctx: &AppContext
+) -> Result<String, PoolError> {
+    let rpc_client = &ctx.rpc_client;
+    let config = ctx.config.as_ref();
+    let ScreenedPool { pool_info, verdict, metadata } = screen_new_pool(
         tx,
-        &inner_instructions,
-        &raydium_pubkey,
-        &sol_pubkey,
-        &log_messages,
-        &pre_token_balances
+        &PoolScreen::live(ctx)
+    ).await?;
+    if !verdict.passed {
+        return Err(PoolError::Rejected(verdict));
+    }
+
+    // Swaps fail until the pool's open time
+    let opens_in = seconds_until_open(pool_info.open_time, Utc::now().timestamp());
+    if opens_in > config.buy.max_open_wait_secs {
+        return Err(PoolError::OpensTooLate(opens_in));
+    }
+    if opens_in > 0 {
+        println!("Pool {} opens in {}s, waiting to buy", pool_info.id, opens_in);
+        tokio::time::sleep(Duration::from_secs(opens_in)).await;
+    }
+
+    // Finally, fetch market info and perform the swap. CPMM and CLMM pools trade
+    // without a market.
+    let market_info = match pool_info.pool_type {
+        PoolType::AmmV4 =>
+            Some(
+                fetch_market_info(Arc::clone(rpc_client), pool_info.market_id).await.map_err(
+                    PoolError::Other
+                )?
+            ),
+        // Published as is, the executor resolves the rest of the pool
+        _ => None,
+    };
+
+    let pool_keys = create_liquidity_pool_keys(&pool_info, market_info.as_ref());
+
+    dbg!("Købeer");
+
+    if config.buy.executor == Executor::Native {
+        if !pool_info.pool_type.is_raydium() {
+            let message = format!(
+                "{:?} pools can only be bought by the Redis executor",
+                pool_info.pool_type
+            );
+            return Err(PoolError::BuyError(message));
+        }
+        let wsol_mint = spl_token::native_mint::id();
+        let output_mint = if pool_info.base_mint == wsol_mint {
+            pool_info.quote_mint
+        } else {
+            pool_info.base_mint
+        };
+        let amount_in = sol_to_lamports(config.buy.amount_sol);
+        let (base_reserve, quote_reserve) = fetch_pool_reserves(
+            rpc_client.as_ref(),
+            &pool_info
+        ).await.unwrap_or((pool_info.base_reserve, pool_info.quote_reserve));
+        let quote = quote_pool(
+            &pool_info,
+            base_reserve,
+            quote_reserve,
+            &wsol_mint,
+            amount_in,
+            config.buy.slippage_bps
+        ).map_err(|e| PoolError::BuyError(e.to_string()))?;
+
+        let params = SwapParams {
+            input_mint: wsol_mint,
+            output_mint,
+            amount_in,
+            min_amount_out: quote.min_amount_out,
+            compute_unit_limit: config.buy.compute_unit_limit,
+            compute_unit_price: config.buy.compute_unit_price,
+            wrap_sol: config.buy.wrap_sol,
+            unwrap_sol: false,
+        };
+
+        let signature = raydium_swap
+            ::swap(rpc_client.as_ref(), &ctx.keypair, &pool_keys, &params).await
+            .map_err(|e| PoolError::BuyError(e.to_string()))?;
+        confirm_transaction(
+            rpc_client.as_ref(),
+            &signature,
+            Duration::from_secs(config.buy.confirm_timeout_secs)
+        ).await.map_err(|e| PoolError::BuyError(e.to_string()))?;
+
+        let token_info = TokenInfo {
+            base_mint: pool_info.base_mint.to_string(),
+            quote_mint: pool_info.quote_mint.to_string(),
+            base_vault: pool_info.base_vault.to_string(),
+            quote_vault: pool_info.quote_vault.to_string(),
+            bonding_curve: None,
+            pool_type: pool_info.pool_type,
+            pool_id: Some(pool_info.id.to_string()),
+            trade_fee_bps: Some(pool_info.trade_fee_bps),
+        };
+        let token_program = if output_mint == pool_info.base_mint {
+            pool_info.base_token_program
+        } else {
+            pool_info.quote_token_program
+        };
+        // The tokens are bought either way, a missing position only hides them from the
+        // sell monitor
+        if
+            let Err(err) = record_position(
+                ctx,
+                &signature,
+                token_info,
+                &output_mint,
+                &token_program,
+                metadata.as_ref()
+            ).await
+        {
+            eprintln!("Failed to record the position bought in {}: {}", signature, err);
+        }
+
+        return Ok(signature.to_string());
+    }
+
+    let buy_transaction = BuyTransaction {
+        in_token: pool_info.base_mint.to_string(),
+        out_token: pool_info.quote_mint.to_string(),
+        amount_in: config.buy.amount_sol,
+        key_z: create_pool_key(&pool_keys),
+        type_: "buy".to_string(),
+        lp_decimals: pool_info.lp_decimals,
+    };
+
+    if let Err(e) = ctx.redis.buy(buy_transaction).await {
+        return Err(PoolError::BuyError(e.to_string()));
+    }
+
+    Ok("Success".to_string())
+}
+
+/// Seconds from `now` until a pool with `open_time` takes swaps, 0 once it is open.
+fn seconds_until_open(open_time: u64, now: i64) -> u64 {
+    open_time.saturating_sub(now.max(0) as u64)
+}
+
+/// Stores a confirmed native buy in the `tokens` collection, where the sell monitor and the
+/// token threshold pick it up. The amount is read back from the wallet's token account.
+async fn record_position(
+    ctx: &AppContext,
+    signature: &Signature,
+    token_info: TokenInfo,
+    token_mint: &Pubkey,
+    token_program: &Pubkey,
+    metadata: Option<&FetchedMetadata>
+) -> Result<(), Box<dyn Error>> {
+    let token_account = get_associated_token_address_with_program_id(
+        &ctx.keypair.pubkey(),
+        token_mint,
+        token_program
     );
+    let balance = ctx.rpc_client.get_token_account_balance(&token_account).await?;
+    let amount = balance.ui_amount.unwrap_or(0.0);
+    if amount <= 0.0 {
+        return Err(format!("{} holds no {} after the buy", token_account, token_mint).into());
+    }
+
+    let sol_amount = ctx.config.buy.amount_sol;
+    let token_metadata = match metadata {
+        Some(metadata) => metadata.token_metadata(token_mint),
+        None =>
+            TokenMetadata {
+                mint: token_mint.to_string(),
+                ..TokenMetadata::default()
+            },
+    };
+    let position = mongo::BuyTransaction {
+        transaction_signature: signature.to_string(),
+        token_info,
+        amount,
+        sol_amount,
+        sol_price: rugcheck::get_current_sol_price().await.unwrap_or(0.0),
+        entry_price: sol_amount / amount,
+        token_metadata: TokenMetadata {
+            balance: amount,
+            ..token_metadata
+        },
+        created_at: DateTime::now(),
+        sold: false,
+        levels_hit: 0,
+        remaining: None,
+    };
+    ctx.mongo.insert_position("solsniper", "tokens", &position).await?;
 
-    if let Some(pool_info) = info {
-        if pool_info.base_mint.to_string() == "So11111111111111111111111111111111111111112" {
-            return Err(PoolError::BaseMintIsSOL);
+    Ok(())
+}
+
+/// Buys a freshly created pump.fun token on its bonding curve, after the checks of the
+/// `authorities` rug rule. There is no LP to burn or pool to evaluate yet.
+pub async fn check_for_pump_launch(
+    event: &CreateEvent,
+    ctx: &AppContext
+) -> Result<String, PoolError> {
+    let rpc_client = &ctx.rpc_client;
+    let config = ctx.config.as_ref();
+
+    let is_rug = pre_rug_check(rpc_client.as_ref(), &event.mint).await.map_err(PoolError::Other)?;
+    if is_rug {
+        eprintln!("Skipping pump.fun launch of {}, rug detected", event.mint);
+        return Err(PoolError::RugDetected);
+    }
+
+    let account = rpc_client
+        .get_account(&event.bonding_curve).await
+        .map_err(|err| PoolError::Other(err.into()))?;
+    let curve = BondingCurveAccount::decode(&account.data).ok_or_else(||
+        PoolError::BuyError(format!("{} is not a bonding curve", event.bonding_curve))
+    )?;
+    let amount_in = sol_to_lamports(config.buy.amount_sol);
+    let quote = curve
+        .quote_buy(amount_in, config.buy.slippage_bps)
+        .map_err(|e| PoolError::BuyError(e.to_string()))?;
+
+    // Asking for the slippage-adjusted amount with the full budget as the cost cap leaves
+    // room for the curve to move before the buy lands
+    if config.buy.executor == Executor::Native {
+        let params = PumpTradeParams {
+            side: PumpSide::Buy,
+            mint: event.mint,
+            creator: curve.creator,
+            token_amount: quote.min_amount_out,
+            sol_limit: amount_in,
+            compute_unit_limit: config.buy.compute_unit_limit,
+            compute_unit_price: config.buy.compute_unit_price,
+        };
+
+        let signature = pump_swap
+            ::trade(rpc_client.as_ref(), &ctx.keypair, &config.pump_program_id(), &params).await
+            .map_err(|e| PoolError::BuyError(e.to_string()))?;
+        confirm_transaction(
+            rpc_client.as_ref(),
+            &signature,
+            Duration::from_secs(config.buy.confirm_timeout_secs)
+        ).await.map_err(|e| PoolError::BuyError(e.to_string()))?;
+
+        let token_info = TokenInfo {
+            base_mint: event.mint.to_string(),
+            quote_mint: spl_token::native_mint::id().to_string(),
+            base_vault: get_associated_bonding_curve(&event.bonding_curve, &event.mint).to_string(),
+            quote_vault: event.bonding_curve.to_string(),
+            bonding_curve: Some(event.bonding_curve.to_string()),
+            pool_type: PoolType::default(),
+            pool_id: None,
+            trade_fee_bps: None,
+        };
+        if
+            let Err(err) = record_position(
+                ctx,
+                &signature,
+                token_info,
+                &event.mint,
+                &spl_token::id(),
+                None
+            ).await
+        {
+            eprintln!("Failed to record the position bought in {}: {}", signature, err);
         }
 
-        // First, check if it's a rug
-        match pre_rug_check(&rpc_client, &pool_info.base_mint).await {
-            Ok(is_rug) => {
-                if is_rug {
-                    dbg!("Rug detected");
-                    return Err(PoolError::RugDetected);
-                } else {
-                    let is_lp_burnt = match check_burnt_lp(&rpc_client, &pool_info).await {
-                        Ok(burnt) => burnt,
-                        Err(err) => {
-                            return Err(PoolError::Other(err.into()));
-                        }
-                    };
-                    dbg!("Not rug checking LP");
-                    if is_lp_burnt {
-                        // Finally, fetch market info and perform the swap
-                        let market_info = match
-                            fetch_market_info(Arc::clone(&rpc_client), pool_info.market_id).await
-                        {
-                            Ok(market_info) => market_info,
-                            Err(err) => {
-                                return Err(PoolError::Other(err.into()));
-                            }
-                        };
-
-                        let keyz: LiquidityPoolKeysString = create_pool_key(
-                            &pool_info,
-                            &market_info
-                        );
-
-                        dbg!("Købeer");
-
-                        let buy_transaction = BuyTransaction {
-                            in_token: pool_info.base_mint.to_string(),
-                            out_token: pool_info.quote_mint.to_string(),
-                            amount_in: sol_amount,
-                            key_z: keyz,
-                            type_: "buy".to_string(),
-                            lp_decimals: pool_info.lp_decimals,
-                        };
-
-                        if let Err(e) = buy(buy_transaction).await {
-                            return Err(PoolError::BuyError(e.to_string()));
-                        }
-
-                        return Ok("Success".to_string());
-                    } else {
-                        return Err(PoolError::LPNotBurnt);
-                    }
-                }
-            }
-            Err(err) => {
-                return Err(PoolError::Other(err.into()));
-            }
+        return Ok(signature.to_string());
+    }
+
+    let buy_transaction = PumpBuyTransaction {
+        type_: "pump_buy".to_string(),
+        mint: event.mint.to_string(),
+        bonding_curve: event.bonding_curve.to_string(),
+        token_amount: quote.min_amount_out,
+        max_sol_cost: amount_in,
+    };
+
+    if let Err(e) = ctx.redis.pump_buy(buy_transaction).await {
+        return Err(PoolError::BuyError(e.to_string()));
+    }
+
+    Ok("Success".to_string())
+}
+
+/// Creator recorded on the bonding curve the migrated `mint` sold out on.
+async fn fetch_pump_creator(
+    client: &dyn SolanaRpc,
+    program_id: &Pubkey,
+    mint: &Pubkey
+) -> Result<Pubkey, Box<dyn Error>> {
+    let bonding_curve = get_bonding_curve(program_id, mint);
+    let account = client.get_account(&bonding_curve).await?;
+    let curve = BondingCurveAccount::decode(&account.data).ok_or_else(||
+        format!("{} is not a bonding curve", bonding_curve)
+    )?;
+    Ok(curve.creator)
+}
+
+/// Whether `signer` signed the transaction, used to tell pump.fun migrations apart from
+/// other pool creations.
+fn is_signed_by(tx: &EncodedConfirmedTransactionWithStatusMeta, signer: &str) -> bool {
+    let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction else {
+        return false;
+    };
+    match &ui_tx.message {
+        UiMessage::Parsed(message) => {
+            message.account_keys.iter().any(|key| key.signer && key.pubkey == signer)
+        }
+        UiMessage::Raw(message) => {
+            message.account_keys
+                .iter()
+                .take(message.header.num_required_signatures as usize)
+                .any(|key| key == signer)
         }
-    } else {
-        return Err(PoolError::NoPoolInfoFound);
     }
 }
 
-fn create_pool_key(info: &PoolInfo, market_info: &MarketStateLayoutV3) -> LiquidityPoolKeysString {
-    let market_auth = get_associated_authority(&info.market_program_id, &info.market_id);
+/// Extracts the new pool from a pool creation transaction without touching the network.
+/// The first of `detectors` whose program created a pool parses it.
+pub fn parse_new_pool(
+    tx: EncodedConfirmedTransactionWithStatusMeta,
+    detectors: &[Box<dyn PoolDetector>]
+) -> Result<PoolInfo, PoolParseError> {
+    let tx = PoolTransaction::try_from(tx)?;
 
-    let pool_key: LiquidityPoolKeysString = LiquidityPoolKeysString {
-        id: info.id.to_string(),
-        base_mint: info.base_mint.to_string(),
-        quote_mint: info.quote_mint.to_string(),
-        lp_mint: info.lp_mint.to_string(),
+    for detector in detectors {
+        if let Some(instruction) = tx.find_creation(detector.as_ref()) {
+            let pool_info = detector.parse(&instruction, &tx)?;
+            let creator = tx.account_keys.first().ok_or(PoolParseError::MissingAccount(0))?;
+            return Ok(PoolInfo {
+                creator: parse_pubkey(creator)?,
+                ..pool_info
+            });
+        }
+    }
+
+    Err(
+        PoolParseError::InitInstructionNotFound(
+            detectors
+                .iter()
+                .map(|detector| detector.program_id())
+                .collect()
+        )
+    )
+}
+
+/// `market_info` is the OpenBook market of AMM v4 pools, `None` for pool types without one.
+fn create_liquidity_pool_keys(
+    info: &PoolInfo,
+    market_info: Option<&MarketStateLayoutV3>
+) -> LiquidityPoolKeys {
+    let mut pool_keys = LiquidityPoolKeys {
+        pool_type: info.pool_type,
+        id: info.id,
+        base_mint: info.base_mint,
+        quote_mint: info.quote_mint,
+        lp_mint: info.lp_mint,
         base_decimals: info.base_decimals,
         quote_decimals: info.quote_decimals,
         lp_decimals: info.lp_decimals,
         version: info.version,
-        program_id: info.program_id.to_string(),
-        authority: info.authority.to_string(),
-        open_orders: info.open_orders.to_string(),
-        target_orders: info.target_orders.to_string(),
-        base_vault: info.base_vault.to_string(),
-        quote_vault: info.quote_vault.to_string(),
-        withdraw_queue: info.withdraw_queue.to_string(),
-        lp_vault: info.lp_vault.to_string(),
+        program_id: info.program_id,
+        authority: info.authority,
+        open_orders: info.open_orders,
+        target_orders: info.target_orders,
+        base_vault: info.base_vault,
+        quote_vault: info.quote_vault,
+        withdraw_queue: info.withdraw_queue,
+        lp_vault: info.lp_vault,
         market_version: info.market_version,
-        market_program_id: info.market_program_id.to_string(),
-        market_id: info.market_id.to_string(),
-        market_authority: market_auth.expect("Market_Auth").to_string(),
-        market_base_vault: market_info.base_vault.to_string(),
-        market_quote_vault: market_info.quote_vault.to_string(),
-        market_bids: market_info.bids.to_string(),
-        market_asks: market_info.asks.to_string(),
-        market_event_queue: market_info.event_queue.to_string(),
+        market_program_id: info.market_program_id,
+        market_id: info.market_id,
+        config_id: info.config_id,
+        observation_id: info.observation_id,
+        base_token_program: info.base_token_program,
+        quote_token_program: info.quote_token_program,
+        ..LiquidityPoolKeys::default()
+    };
+
+    if let Some(market_info) = market_info {
+        let market_auth = get_associated_authority(&info.market_program_id, &info.market_id);
+        pool_keys.market_authority = market_auth.expect("Market_Auth");
+        pool_keys.market_base_vault = market_info.base_vault;
+        pool_keys.market_quote_vault = market_info.quote_vault;
+        pool_keys.market_bids = market_info.bids;
+        pool_keys.market_asks = market_info.asks;
+        pool_keys.market_event_queue = market_info.event_queue;
+    }
+
+    pool_keys
+}
+
+fn create_pool_key(keys: &LiquidityPoolKeys) -> LiquidityPoolKeysString {
+    let pool_key: LiquidityPoolKeysString = LiquidityPoolKeysString {
+        pool_type: keys.pool_type,
+        id: keys.id.to_string(),
+        base_mint: keys.base_mint.to_string(),
+        quote_mint: keys.quote_mint.to_string(),
+        lp_mint: keys.lp_mint.to_string(),
+        base_decimals: keys.base_decimals,
+        quote_decimals: keys.quote_decimals,
+        lp_decimals: keys.lp_decimals,
+        version: keys.version,
+        program_id: keys.program_id.to_string(),
+        authority: keys.authority.to_string(),
+        open_orders: keys.open_orders.to_string(),
+        target_orders: keys.target_orders.to_string(),
+        base_vault: keys.base_vault.to_string(),
+        quote_vault: keys.quote_vault.to_string(),
+        withdraw_queue: keys.withdraw_queue.to_string(),
+        lp_vault: keys.lp_vault.to_string(),
+        market_version: keys.market_version,
+        market_program_id: keys.market_program_id.to_string(),
+        market_id: keys.market_id.to_string(),
+        market_authority: keys.market_authority.to_string(),
+        market_base_vault: keys.market_base_vault.to_string(),
+        market_quote_vault: keys.market_quote_vault.to_string(),
+        market_bids: keys.market_bids.to_string(),
+        market_asks: keys.market_asks.to_string(),
+        market_event_queue: keys.market_event_queue.to_string(),
+        config_id: keys.config_id.to_string(),
+        observation_id: keys.observation_id.to_string(),
+        base_token_program: keys.base_token_program.to_string(),
+        quote_token_program: keys.quote_token_program.to_string(),
     };
 
     pool_key
 }
 
 async fn fetch_market_info(
-    client: Arc<RpcClient>,
+    client: Arc<dyn SolanaRpc>,
     market_id: Pubkey
 ) -> Result<MarketStateLayoutV3, Box<dyn Error>> {
-    let market_account_info = match client.get_account(&market_id) {
+    let market_account_info = match client.get_account(&market_id).await {
         Ok(account) => account,
         Err(err) => {
             return Err(err.into());
@@ -215,184 +677,348 @@ async fn fetch_market_info(
     Ok(market_state)
 }
 
-fn parse_pool_info_from_lp_transaction(
-    tx: EncodedConfirmedTransactionWithStatusMeta,
-    inner_instructions: &Vec<UiInnerInstructions>,
-    raydium_program_id: &Pubkey,
-    wrapped_sol: &Pubkey,
-    log_msg: &Vec<String>,
-    pre_token_balances: &Vec<UiTransactionTokenBalance>
-) -> Option<PoolInfo> {
-    match tx.transaction.transaction {
-        EncodedTransaction::Json(ui_tx) => {
-            let message = ui_tx.message;
-
-            match message {
-                UiMessage::Parsed(ins) => {
-                    let instructions: Vec<UiInstruction> = ins.instructions;
-                    let init_instructions = find_instruction_by_program_id(
-                        &instructions,
-                        &raydium_program_id
-                    );
-
-                    let init_instructions = match init_instructions {
-                        Some(instructions) => instructions,
-                        None => {
-                            return None;
-                        }
-                    };
-
-                    match init_instructions {
-                        UiInstruction::Parsed(inside) =>
-                            match inside {
-                                UiParsedInstruction::PartiallyDecoded(parsed) => {
-                                    let token_program_id: &'static str =
-                                        "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
-                                    let sol_decimals: u8 = 9;
-                                    let base_mint = &parsed.accounts[8];
-                                    let base_vault = &parsed.accounts[10];
-                                    let quote_mint = &parsed.accounts[9];
-                                    let quote_vault = &parsed.accounts[11];
-                                    let lp_mint = &parsed.accounts[7];
-                                    let base_and_quote_swapped =
-                                        base_mint.to_string() == wrapped_sol.to_string();
-                                    let lp_init_mint_instruction =
-                                        find_initialize_mint_in_inner_instructions_by_mint_address(
-                                            &inner_instructions,
-                                            lp_mint
-                                        );
-                                    let lp_mint_mint_instruction =
-                                        find_mint_in_inner_instructions_by_mint_address(
-                                            &inner_instructions,
-                                            lp_mint
-                                        );
-
-                                    match lp_mint_mint_instruction {
-                                        Some(_) => {
-                                            let base_transfer_instruction: Option<&ParsedInstruction> =
-                                                find_transfer_instruction_in_inner_instructions_by_destination(
-                                                    &inner_instructions,
-                                                    base_vault,
-                                                    Some(token_program_id)
-                                                );
-                                            let quote_transfer_instruction =
-                                                find_transfer_instruction_in_inner_instructions_by_destination(
-                                                    &inner_instructions,
-                                                    quote_vault,
-                                                    Some(token_program_id)
-                                                );
-                                            let lp_initialization_log_entry_info: Value =
-                                                extract_lp_initialization_log_entry_info_from_log_entry(
-                                                    find_log_entry("init_pc_amount", log_msg)
-                                                        .unwrap()
-                                                        .to_string()
-                                                ).expect("error_lp_initialization_log_entry_info");
-                                            let lp_decimals: u8 = get_decimals(
-                                                &lp_init_mint_instruction
-                                            ).expect("wrong_lp_decimals");
-                                            let lp_ac: String = get_info_ac(
-                                                &lp_mint_mint_instruction
-                                            ).expect("lp_ac error");
-                                            let open_time: u64 = extract_open_time(
-                                                &lp_initialization_log_entry_info
-                                            ).expect("open_time err");
-                                            let base_pre_balance = find_base_pre_balance(
-                                                pre_token_balances,
-                                                &base_mint.to_string()
-                                            );
-                                            let base_decimals: u8 = get_base_decimals(
-                                                &base_pre_balance
-                                            );
-                                            let base_reserves: String = get_info_amount(
-                                                &base_transfer_instruction
-                                            ).expect("err_base_reserves");
-                                            let quote_reserves: String = get_info_amount(
-                                                &quote_transfer_instruction
-                                            ).expect("err_quote_reserves");
-                                            let lp_reserves: String = get_info_amount(
-                                                &lp_mint_mint_instruction
-                                            ).expect("reserves_err");
-                                            let pool_info = PoolInfo::new(
-                                                Pubkey::from_str(
-                                                    &parsed.accounts[4].clone()
-                                                ).unwrap(),
-                                                Pubkey::from_str(&base_mint).unwrap(),
-                                                Pubkey::from_str(&quote_mint).unwrap(),
-                                                Pubkey::from_str(&lp_mint).unwrap(),
-                                                if base_and_quote_swapped {
-                                                    sol_decimals
-                                                } else {
-                                                    base_decimals
-                                                },
-                                                if base_and_quote_swapped {
-                                                    base_decimals
-                                                } else {
-                                                    sol_decimals
-                                                },
-                                                lp_decimals,
-                                                4, // version
-                                                Pubkey::from_str(
-                                                    "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"
-                                                ).unwrap(),
-                                                Pubkey::from_str(&parsed.accounts[5]).unwrap(),
-                                                Pubkey::from_str(&parsed.accounts[6]).unwrap(),
-                                                Pubkey::from_str(&parsed.accounts[13]).unwrap(),
-                                                Pubkey::from_str(&base_vault).unwrap(),
-                                                Pubkey::from_str(&quote_vault).unwrap(),
-                                                Pubkey::from_str(
-                                                    "11111111111111111111111111111111"
-                                                ).unwrap(), // withdraw_queue
-                                                Pubkey::from_str(lp_ac.as_str()).unwrap(), // lp_vault
-                                                3, // market_version
-                                                Pubkey::from_str(&parsed.accounts[15]).unwrap(),
-                                                Pubkey::from_str(&parsed.accounts[16]).unwrap(),
-                                                parse_amount(base_reserves.as_str()).unwrap(),
-                                                parse_amount(quote_reserves.as_str()).unwrap(),
-                                                parse_amount(&lp_reserves).unwrap(),
-                                                open_time
-                                            );
-                                            Some(pool_info)
-                                        }
-                                        None => { None }
-                                    }
-                                }
-                                _ => { None }
-                            }
-
-                        _ => { None }
-                    }
-                }
-                _ => { None }
-            }
-        }
-        _ => { None }
+/// Trade fee of a CPMM or CLMM pool, set by the fee tier config it was created with.
+async fn fetch_trade_fee_bps(
+    client: &dyn SolanaRpc,
+    pool_info: &PoolInfo
+) -> Result<u64, Box<dyn Error>> {
+    let config_account = client.get_account(&pool_info.config_id).await?;
+    raydium_sdk
+        ::decode_amm_config_fee_bps(pool_info.pool_type, &config_account.data)
+        .ok_or_else(|| format!("{} is not an AMM config account", pool_info.config_id).into())
+}
+
+/// Builds the swap keys of an existing Raydium pool from its on-chain accounts, for trading
+/// a pool we didn't see created.
+pub async fn fetch_pool_keys(
+    client: Arc<dyn SolanaRpc>,
+    pool_type: PoolType,
+    pool_id: Pubkey
+) -> Result<LiquidityPoolKeys, Box<dyn Error>> {
+    match pool_type {
+        PoolType::AmmV4 => fetch_amm_v4_pool_keys(client, pool_id).await,
+        PoolType::Cpmm => fetch_cpmm_pool_keys(client.as_ref(), pool_id).await,
+        PoolType::Clmm => fetch_clmm_pool_keys(client.as_ref(), pool_id).await,
+        pool_type => Err(format!("{:?} pools have no Raydium pool keys", pool_type).into()),
     }
 }
 
-fn find_instruction_by_program_id<'a>(
-    instructions: &'a Vec<UiInstruction>,
-    program_id: &'a Pubkey
-) -> Option<&'a UiInstruction> {
-    instructions.iter().find(|instr| {
-        match instr {
-            UiInstruction::Parsed(parsed_instr) =>
-                match parsed_instr {
-                    UiParsedInstruction::PartiallyDecoded(decoded_instr) => {
-                        let decoded_program_id = Pubkey::from_str(&decoded_instr.program_id)
-                            .ok()
-                            .unwrap();
-                        &decoded_program_id == program_id
-                    }
-                    &UiParsedInstruction::Parsed(_) => { false }
-                }
-            &&UiInstruction::Compiled(_) => todo!(),
+/// AMM v4 keys come from the AMM account and its OpenBook market.
+async fn fetch_amm_v4_pool_keys(
+    client: Arc<dyn SolanaRpc>,
+    pool_id: Pubkey
+) -> Result<LiquidityPoolKeys, Box<dyn Error>> {
+    let pool_account = client.get_account(&pool_id).await?;
+    let amm_info = AmmInfoLayoutV4::try_from_slice(&pool_account.data).map_err(|e|
+        format!("Failed to decode AMM state of {}: {}", pool_id, e)
+    )?;
+
+    let market_info = fetch_market_info(Arc::clone(&client), amm_info.market_id).await?;
+    let market_authority = get_associated_authority(
+        &amm_info.market_program_id,
+        &amm_info.market_id
+    )?;
+
+    Ok(LiquidityPoolKeys {
+        pool_type: PoolType::AmmV4,
+        id: pool_id,
+        base_mint: amm_info.base_mint,
+        quote_mint: amm_info.quote_mint,
+        lp_mint: amm_info.lp_mint,
+        base_decimals: amm_info.base_decimal as u8,
+        quote_decimals: amm_info.quote_decimal as u8,
+        // The AMM creates the LP mint with the base token's decimals
+        lp_decimals: amm_info.base_decimal as u8,
+        version: 4,
+        program_id: pool_account.owner,
+        authority: get_amm_authority(&pool_account.owner),
+        open_orders: amm_info.open_orders,
+        target_orders: amm_info.target_orders,
+        base_vault: amm_info.base_vault,
+        quote_vault: amm_info.quote_vault,
+        withdraw_queue: amm_info.withdraw_queue,
+        lp_vault: amm_info.lp_vault,
+        market_version: 3,
+        market_program_id: amm_info.market_program_id,
+        market_id: amm_info.market_id,
+        market_authority,
+        market_base_vault: market_info.base_vault,
+        market_quote_vault: market_info.quote_vault,
+        market_bids: market_info.bids,
+        market_asks: market_info.asks,
+        market_event_queue: market_info.event_queue,
+        base_token_program: spl_token::id(),
+        quote_token_program: spl_token::id(),
+        ..LiquidityPoolKeys::default()
+    })
+}
+
+async fn fetch_cpmm_pool_keys(
+    client: &dyn SolanaRpc,
+    pool_id: Pubkey
+) -> Result<LiquidityPoolKeys, Box<dyn Error>> {
+    let pool_account = client.get_account(&pool_id).await?;
+    let pool_state = CpmmPoolState::decode(&pool_account.data).ok_or_else(||
+        format!("{} is not a CPMM pool", pool_id)
+    )?;
+
+    Ok(LiquidityPoolKeys {
+        pool_type: PoolType::Cpmm,
+        id: pool_id,
+        base_mint: pool_state.token_0_mint,
+        quote_mint: pool_state.token_1_mint,
+        lp_mint: pool_state.lp_mint,
+        base_decimals: pool_state.mint_0_decimals,
+        quote_decimals: pool_state.mint_1_decimals,
+        lp_decimals: pool_state.lp_mint_decimals,
+        program_id: pool_account.owner,
+        authority: get_cpmm_authority(&pool_account.owner),
+        base_vault: pool_state.token_0_vault,
+        quote_vault: pool_state.token_1_vault,
+        config_id: pool_state.amm_config,
+        observation_id: pool_state.observation_key,
+        base_token_program: pool_state.token_0_program,
+        quote_token_program: pool_state.token_1_program,
+        ..LiquidityPoolKeys::default()
+    })
+}
+
+/// The CLMM pool doesn't store its token programs, they are read off the mint owners.
+async fn fetch_clmm_pool_keys(
+    client: &dyn SolanaRpc,
+    pool_id: Pubkey
+) -> Result<LiquidityPoolKeys, Box<dyn Error>> {
+    let pool_account = client.get_account(&pool_id).await?;
+    let pool_state = ClmmPoolState::decode(&pool_account.data).ok_or_else(||
+        format!("{} is not a CLMM pool", pool_id)
+    )?;
+    let mint_0 = client.get_account(&pool_state.token_mint_0).await?;
+    let mint_1 = client.get_account(&pool_state.token_mint_1).await?;
+
+    Ok(LiquidityPoolKeys {
+        pool_type: PoolType::Clmm,
+        id: pool_id,
+        base_mint: pool_state.token_mint_0,
+        quote_mint: pool_state.token_mint_1,
+        base_decimals: pool_state.mint_decimals_0,
+        quote_decimals: pool_state.mint_decimals_1,
+        program_id: pool_account.owner,
+        base_vault: pool_state.token_vault_0,
+        quote_vault: pool_state.token_vault_1,
+        config_id: pool_state.amm_config,
+        observation_id: pool_state.observation_key,
+        base_token_program: mint_0.owner,
+        quote_token_program: mint_1.owner,
+        ..LiquidityPoolKeys::default()
+    })
+}
+
+/// Parses a Raydium AMM v4 `initialize2` transaction. `init2` is the decoded instruction
+/// data, `None` when it couldn't be decoded.
+pub fn parse_amm_v4_pool(
+    init_instruction: &CreationInstruction,
+    init2: Option<Initialize2InstructionData>,
+    tx: &PoolTransaction,
+    raydium_program_id: &Pubkey
+) -> Result<PoolInfo, PoolParseError> {
+    let inner_instructions = &tx.inner_instructions;
+    let wrapped_sol = &spl_token::native_mint::id();
+    let account = |index: usize| -> Result<&String, PoolParseError> {
+        init_instruction.accounts.get(index).ok_or(PoolParseError::MissingAccount(index))
+    };
+
+    let token_program_id = account(0)?;
+    let sol_decimals: u8 = 9;
+    let base_mint = account(8)?;
+    let base_vault = account(10)?;
+    let quote_mint = account(9)?;
+    let quote_vault = account(11)?;
+    let lp_mint = account(7)?;
+    let base_and_quote_swapped = *base_mint == wrapped_sol.to_string();
+
+    let lp_init_mint_instruction = find_initialize_mint_in_inner_instructions_by_mint_address(
+        inner_instructions,
+        lp_mint
+    ).ok_or_else(|| PoolParseError::LpInitializeMintNotFound(lp_mint.clone()))?;
+    let lp_mint_mint_instruction = find_mint_in_inner_instructions_by_mint_address(
+        inner_instructions,
+        lp_mint
+    ).ok_or_else(|| PoolParseError::LpMintToNotFound(lp_mint.clone()))?;
+    let lp_decimals: u8 = get_decimals(lp_init_mint_instruction)?;
+    let lp_ac: String = get_info_ac(lp_mint_mint_instruction)?;
+    let base_pre_balance = find_base_pre_balance(&tx.pre_token_balances, base_mint).ok_or_else(||
+        PoolParseError::PreBalanceNotFound(base_mint.clone())
+    )?;
+    let base_decimals: u8 = base_pre_balance.ui_token_amount.decimals;
+    let lp_reserves: String = get_info_amount(lp_mint_mint_instruction)?;
+
+    // The instruction data is authoritative, the logs and vault transfers are only
+    // consulted when it can't be decoded
+    let (open_time, base_reserve, quote_reserve) = match init2 {
+        Some(data) => (data.open_time, data.init_coin_amount, data.init_pc_amount),
+        None => {
+            let base_transfer_instruction =
+                find_transfer_instruction_in_inner_instructions_by_destination(
+                    inner_instructions,
+                    base_vault,
+                    Some(token_program_id.as_str())
+                ).ok_or_else(|| PoolParseError::VaultTransferNotFound(base_vault.clone()))?;
+            let quote_transfer_instruction =
+                find_transfer_instruction_in_inner_instructions_by_destination(
+                    inner_instructions,
+                    quote_vault,
+                    Some(token_program_id.as_str())
+                ).ok_or_else(|| PoolParseError::VaultTransferNotFound(quote_vault.clone()))?;
+
+            let lp_log_entry = find_log_entry("init_pc_amount", &tx.log_messages).ok_or(
+                PoolParseError::LogEntryNotFound
+            )?;
+            let lp_initialization_log_entry_info: Value =
+                extract_lp_initialization_log_entry_info_from_log_entry(lp_log_entry)?;
+            let open_time: u64 = extract_open_time(&lp_initialization_log_entry_info).ok_or(
+                PoolParseError::MissingField("open_time")
+            )?;
+
+            (
+                open_time,
+                parse_amount(&get_info_amount(base_transfer_instruction)?)?,
+                parse_amount(&get_info_amount(quote_transfer_instruction)?)?,
+            )
         }
+    };
+
+    let pool_info = PoolInfo::new(
+        parse_pubkey(account(4)?)?,
+        parse_pubkey(base_mint)?,
+        parse_pubkey(quote_mint)?,
+        parse_pubkey(lp_mint)?,
+        if base_and_quote_swapped {
+            sol_decimals
+        } else {
+            base_decimals
+        },
+        if base_and_quote_swapped {
+            base_decimals
+        } else {
+            sol_decimals
+        },
+        lp_decimals,
+        4, // version
+        *raydium_program_id,
+        parse_pubkey(account(5)?)?,
+        parse_pubkey(account(6)?)?,
+        parse_pubkey(account(13)?)?,
+        parse_pubkey(base_vault)?,
+        parse_pubkey(quote_vault)?,
+        Pubkey::default(), // withdraw_queue
+        parse_pubkey(&lp_ac)?, // lp_vault
+        3, // market_version
+        parse_pubkey(account(15)?)?,
+        parse_pubkey(account(16)?)?,
+        base_reserve,
+        quote_reserve,
+        parse_amount(&lp_reserves)?,
+        open_time
+    );
+    let token_program = parse_pubkey(token_program_id)?;
+
+    Ok(PoolInfo {
+        base_token_program: token_program,
+        quote_token_program: token_program,
+        ..pool_info
+    })
+}
+
+/// Parses a CPMM `initialize` transaction. The pool is oriented so WSOL is the quote side.
+pub fn parse_cpmm_pool(
+    instruction: &CreationInstruction,
+    tx: &PoolTransaction,
+    program_id: &Pubkey
+) -> Result<PoolInfo, PoolParseError> {
+    let data = CpmmInitializeInstructionData::decode(&instruction.data).ok_or(
+        PoolParseError::InvalidInstructionData(PoolType::Cpmm)
+    )?;
+
+    let lp_mint = instruction.account(6)?;
+    let lp_mint_instruction = find_mint_in_inner_instructions_by_mint_address(
+        &tx.inner_instructions,
+        &lp_mint.to_string()
+    ).ok_or_else(|| PoolParseError::LpMintToNotFound(lp_mint.to_string()))?;
+
+    let side_0 = PoolSide {
+        mint: instruction.account(4)?,
+        vault: instruction.account(10)?,
+        token_program: instruction.account(15)?,
+        decimals: tx.mint_decimals(&instruction.account(4)?)?,
+        reserve: data.init_amount_0,
+    };
+    let side_1 = PoolSide {
+        mint: instruction.account(5)?,
+        vault: instruction.account(11)?,
+        token_program: instruction.account(16)?,
+        decimals: tx.mint_decimals(&instruction.account(5)?)?,
+        reserve: data.init_amount_1,
+    };
+    let (base, quote) = orient_sides(side_0, side_1);
+
+    Ok(PoolInfo {
+        id: instruction.account(3)?,
+        lp_mint,
+        lp_decimals: CPMM_LP_DECIMALS,
+        program_id: *program_id,
+        authority: instruction.account(2)?,
+        lp_vault: instruction.account(9)?,
+        lp_reserve: parse_amount(&get_info_amount(lp_mint_instruction)?)?,
+        open_time: data.open_time,
+        config_id: instruction.account(1)?,
+        observation_id: instruction.account(13)?,
+        ..pool_info_from_sides(PoolType::Cpmm, &base, &quote)
     })
 }
 
-fn find_transfer_instruction_in_inner_instructions_by_destination<'a>(
-    inner_instructions: &'a Vec<UiInnerInstructions>,
-    destination_account: &'a str,
+/// Parses a CLMM `create_pool` transaction. The pool starts without liquidity, so the
+/// reserves are left at zero.
+pub fn parse_clmm_pool(
+    instruction: &CreationInstruction,
+    tx: &PoolTransaction,
+    program_id: &Pubkey
+) -> Result<PoolInfo, PoolParseError> {
+    let data = ClmmCreatePoolInstructionData::decode(&instruction.data).ok_or(
+        PoolParseError::InvalidInstructionData(PoolType::Clmm)
+    )?;
+
+    let side_0 = PoolSide {
+        mint: instruction.account(3)?,
+        vault: instruction.account(5)?,
+        token_program: instruction.account(9)?,
+        decimals: tx.mint_decimals(&instruction.account(3)?)?,
+        reserve: 0,
+    };
+    let side_1 = PoolSide {
+        mint: instruction.account(4)?,
+        vault: instruction.account(6)?,
+        token_program: instruction.account(10)?,
+        decimals: tx.mint_decimals(&instruction.account(4)?)?,
+        reserve: 0,
+    };
+    let (base, quote) = orient_sides(side_0, side_1);
+
+    Ok(PoolInfo {
+        id: instruction.account(2)?,
+        program_id: *program_id,
+        open_time: data.open_time,
+        config_id: instruction.account(1)?,
+        observation_id: instruction.account(7)?,
+        ..pool_info_from_sides(PoolType::Clmm, &base, &quote)
+    })
+}
+
+// The CPMM program creates every LP mint with 9 decimals
+const CPMM_LP_DECIMALS: u8 = 9;
+
+pub fn find_transfer_instruction_in_inner_instructions_by_destination<'a>(
+    inner_instructions: &'a [UiInnerInstructions],
+    destination_account: &str,
     program_id: Option<&str>
 ) -> Option<&'a ParsedInstruction> {
     for inner in inner_instructions {
@@ -410,7 +1036,7 @@ fn find_transfer_instruction_in_inner_instructions_by_destination<'a>(
                     if
                         type_field == "transfer" &&
                         destination_field == destination_account &&
-                        (program_id.is_none() || program_ide == program_id.unwrap())
+                        program_id.is_none_or(|id| program_ide == id)
                     {
                         return Some(instruct);
                     }
@@ -422,33 +1048,23 @@ fn find_transfer_instruction_in_inner_instructions_by_destination<'a>(
 }
 
 fn find_initialize_mint_in_inner_instructions_by_mint_address<'a>(
-    inner_instructions: &'a Vec<UiInnerInstructions>,
-    mint_address: &'a str
+    inner_instructions: &'a [UiInnerInstructions],
+    mint_address: &str
 ) -> Option<&'a ParsedInstruction> {
-    for inner in inner_instructions {
-        for instruction in &inner.instructions {
-            if let UiInstruction::Parsed(UiParsedInstruction::Parsed(instruct)) = instruction {
-                let data = &instruct.parsed;
+    find_mint_instruction(inner_instructions, mint_address, "initializeMint")
+}
 
-                if
-                    let (Some(type_field), Some(mint_field)) = (
-                        extract_type_field(data),
-                        extract_mint_from_info(data),
-                    )
-                {
-                    if type_field == "initializeMint" && mint_field == mint_address {
-                        return Some(instruct);
-                    }
-                }
-            }
-        }
-    }
-    None
+pub fn find_mint_in_inner_instructions_by_mint_address<'a>(
+    inner_instructions: &'a [UiInnerInstructions],
+    mint_address: &str
+) -> Option<&'a ParsedInstruction> {
+    find_mint_instruction(inner_instructions, mint_address, "mintTo")
 }
 
-fn find_mint_in_inner_instructions_by_mint_address<'a>(
-    inner_instructions: &'a Vec<UiInnerInstructions>,
-    mint_address: &'a str
+fn find_mint_instruction<'a>(
+    inner_instructions: &'a [UiInnerInstructions],
+    mint_address: &str,
+    instruction_type: &str
 ) -> Option<&'a ParsedInstruction> {
     for inner in inner_instructions {
         for instruction in &inner.instructions {
@@ -461,7 +1077,7 @@ fn find_mint_in_inner_instructions_by_mint_address<'a>(
                         extract_mint_from_info(data),
                     )
                 {
-                    if type_field == "mintTo" && mint_field == mint_address {
+                    if type_field == instruction_type && mint_field == mint_address {
                         return Some(instruct);
                     }
                 }
@@ -476,43 +1092,25 @@ fn extract_lp_initialization_log_entry_info_from_log_entry(
 ) -> JsonResult<Value> {
     let lp_initialization_log_entry_info_start = lp_log_entry.find('{').unwrap_or(0);
     let json_str = &lp_log_entry[lp_initialization_log_entry_info_start..];
-    // Assuming `fix_relaxed_json_in_lp_log_entry` is another function you have.
-    // Replace it with the correct logic to fix the JSON string.
     let fixed_json_str = fix_relaxed_json_in_lp_log_entry(json_str);
     serde_json::from_str(&fixed_json_str)
 }
 
-fn get_decimals(lp_instruction: &Option<&ParsedInstruction>) -> Option<u8> {
-    let ptx: &ParsedInstruction = lp_instruction.unwrap();
-    let data: &Value = &ptx.parsed;
-
-    return extract_decimals(&data);
+fn get_decimals(lp_instruction: &ParsedInstruction) -> Result<u8, PoolParseError> {
+    extract_decimals(&lp_instruction.parsed).ok_or(PoolParseError::MissingField("decimals"))
 }
-fn get_info_ac(lp_instruction: &Option<&ParsedInstruction>) -> Option<String> {
-    let ptx: &ParsedInstruction = lp_instruction.unwrap();
-    let data: &Value = &ptx.parsed;
-    return extract_ac_from_info(&data);
+fn get_info_ac(lp_instruction: &ParsedInstruction) -> Result<String, PoolParseError> {
+    extract_ac_from_info(&lp_instruction.parsed).ok_or(PoolParseError::MissingField("account"))
 }
-fn get_info_amount(base_instruction: &Option<&ParsedInstruction>) -> Option<String> {
-    let ptx: &ParsedInstruction = base_instruction.unwrap();
-    let data: &Value = &ptx.parsed;
-    return extract_amount_from_info(&data);
+pub fn get_info_amount(base_instruction: &ParsedInstruction) -> Result<String, PoolParseError> {
+    extract_amount_from_info(&base_instruction.parsed).ok_or(PoolParseError::MissingField("amount"))
 }
 
-fn get_base_decimals(base_pre_balance: &Option<UiTransactionTokenBalance>) -> u8 {
-    let ptx: &UiTransactionTokenBalance = base_pre_balance.as_ref().unwrap();
-    let data: u8 = ptx.ui_token_amount.decimals;
-    return data;
-}
-
-fn find_base_pre_balance(
-    pre_token_balances: &Vec<UiTransactionTokenBalance>,
+fn find_base_pre_balance<'a>(
+    pre_token_balances: &'a [UiTransactionTokenBalance],
     base_mint: &str
-) -> Option<UiTransactionTokenBalance> {
-    pre_token_balances
-        .iter()
-        .find(|balance| balance.mint == base_mint)
-        .cloned()
+) -> Option<&'a UiTransactionTokenBalance> {
+    pre_token_balances.iter().find(|balance| balance.mint == base_mint)
 }
 
 fn extract_type_field(data: &Value) -> Option<String> {
@@ -543,7 +1141,7 @@ fn extract_ac_from_info(data: &Value) -> Option<String> {
     let info = data.get("info")?.as_object()?;
 
     let account = info.get("account")?.as_str()?;
-    return Some(account.to_string());
+    Some(account.to_string())
 }
 fn extract_amount_from_info(data: &Value) -> Option<String> {
     data.get("info")
@@ -551,6 +1149,146 @@ fn extract_amount_from_info(data: &Value) -> Option<String> {
         .and_then(Value::as_str)
         .map(String::from)
 }
-fn parse_amount(amount_str: &str) -> Option<u64> {
-    u64::from_str(amount_str).ok()
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{ self, AmmV4Creation };
+    use crate::rpc::FakeRpc;
+    use crate::pump_sdk::BONDING_CURVE_DISCRIMINATOR;
+    use borsh::BorshSerialize;
+    use solana_client::rpc_config::RpcTransactionConfig;
+    use solana_sdk::account::Account;
+
+    async fn fetch_from_rpc(creation: &AmmV4Creation) -> EncodedConfirmedTransactionWithStatusMeta {
+        let rpc = FakeRpc::new();
+        rpc.insert_transaction(creation.signature(), &creation.encoded());
+        rpc.get_transaction_with_config(
+            &creation.signature(),
+            RpcTransactionConfig::default()
+        ).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn parses_an_amm_v4_pool_fetched_from_the_rpc() {
+        let creation = AmmV4Creation::new();
+        let tx = fetch_from_rpc(&creation).await;
+
+        let pool_info = parse_new_pool(tx, &pool_detectors(&Config::default())).unwrap();
+        assert_eq!(pool_info.pool_type, PoolType::AmmV4);
+        assert_eq!(pool_info.id, creation.pool_id);
+        assert_eq!(pool_info.base_mint, creation.base_mint);
+        assert_eq!(pool_info.quote_mint, spl_token::native_mint::id());
+        assert_eq!(pool_info.lp_mint, creation.lp_mint);
+        assert_eq!(pool_info.base_decimals, fixtures::TOKEN_DECIMALS);
+        assert_eq!(pool_info.quote_decimals, 9);
+        assert_eq!(pool_info.lp_decimals, fixtures::LP_DECIMALS);
+        assert_eq!(pool_info.authority, creation.authority);
+        assert_eq!(pool_info.open_orders, creation.open_orders);
+        assert_eq!(pool_info.target_orders, creation.target_orders);
+        assert_eq!(pool_info.base_vault, creation.base_vault);
+        assert_eq!(pool_info.quote_vault, creation.quote_vault);
+        assert_eq!(pool_info.lp_vault, creation.creator_lp_account);
+        assert_eq!(pool_info.market_program_id, creation.market_program_id);
+        assert_eq!(pool_info.market_id, creation.market_id);
+        assert_eq!(pool_info.base_reserve, fixtures::INIT_COIN_AMOUNT);
+        assert_eq!(pool_info.quote_reserve, fixtures::INIT_PC_AMOUNT);
+        assert_eq!(pool_info.lp_reserve, fixtures::LP_AMOUNT);
+        assert_eq!(pool_info.open_time, fixtures::OPEN_TIME);
+        assert_eq!(pool_info.creator, creation.creator.pubkey());
+    }
+
+    #[tokio::test]
+    async fn fetches_the_keys_of_an_amm_v4_pool() {
+        let creation = AmmV4Creation::new();
+        let rpc = FakeRpc::new();
+        rpc.insert_account(creation.pool_id, creation.amm_account());
+        rpc.insert_account(creation.market_id, creation.market_account());
+
+        let pool_keys = fetch_pool_keys(rpc, PoolType::AmmV4, creation.pool_id).await.unwrap();
+        assert_eq!(pool_keys.id, creation.pool_id);
+        assert_eq!(pool_keys.program_id, creation.program_id);
+        assert_eq!(pool_keys.authority, creation.authority);
+        assert_eq!(pool_keys.base_mint, creation.base_mint);
+        assert_eq!(pool_keys.quote_mint, creation.quote_mint);
+        assert_eq!(pool_keys.base_decimals, fixtures::TOKEN_DECIMALS);
+        assert_eq!(pool_keys.base_vault, creation.base_vault);
+        assert_eq!(pool_keys.quote_vault, creation.quote_vault);
+        assert_eq!(pool_keys.open_orders, creation.open_orders);
+        assert_eq!(pool_keys.target_orders, creation.target_orders);
+        assert_eq!(pool_keys.market_id, creation.market_id);
+        assert_eq!(pool_keys.market_base_vault, creation.market_base_vault);
+        assert_eq!(pool_keys.market_quote_vault, creation.market_quote_vault);
+        assert_eq!(pool_keys.market_event_queue, creation.market_event_queue);
+        assert_eq!(pool_keys.market_bids, creation.market_bids);
+        assert_eq!(pool_keys.market_asks, creation.market_asks);
+        assert_eq!(
+            pool_keys.market_authority,
+            get_associated_authority(&creation.market_program_id, &creation.market_id).unwrap()
+        );
+    }
+
+    #[test]
+    fn counts_down_to_the_open_time() {
+        assert_eq!(seconds_until_open(1_000, 940), 60);
+        assert_eq!(seconds_until_open(1_000, 1_000), 0);
+        // Pools open right away when created with an open time in the past, or none at all
+        assert_eq!(seconds_until_open(1_000, 5_000), 0);
+        assert_eq!(seconds_until_open(0, 5_000), 0);
+    }
+
+    #[tokio::test]
+    async fn skips_transactions_of_disabled_pool_types() {
+        let creation = AmmV4Creation::new();
+        let tx = fetch_from_rpc(&creation).await;
+        let mut config = Config::default();
+        config.listener.pool_types = vec![PoolType::Cpmm];
+
+        let err = parse_new_pool(tx, &pool_detectors(&config)).unwrap_err();
+        assert!(matches!(err, PoolParseError::InitInstructionNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn screens_pump_migrations_against_the_bonding_curve_creator() {
+        let creation = AmmV4Creation::new();
+        let rpc = FakeRpc::new();
+        creation.load_chain(&rpc, &[(creation.creator.pubkey(), 100_000_000_000_000)]);
+        let mut config = Config::default();
+        // Makes the fixture pool a migration
+        config.pump.migration_authority = creation.creator.pubkey().to_string();
+
+        let creator = Pubkey::new_unique();
+        let curve = BondingCurveAccount {
+            discriminator: BONDING_CURVE_DISCRIMINATOR,
+            virtual_token_reserves: 0,
+            virtual_sol_reserves: 0,
+            real_token_reserves: 0,
+            real_sol_reserves: 0,
+            token_total_supply: fixtures::TOKEN_SUPPLY,
+            complete: true,
+            creator,
+        };
+        rpc.insert_account(
+            get_bonding_curve(&config.pump_program_id(), &creation.base_mint),
+            Account {
+                lamports: 1_000_000,
+                data: curve.try_to_vec().unwrap(),
+                owner: config.pump_program_id(),
+                executable: false,
+                rent_epoch: 0,
+            }
+        );
+
+        let screen = PoolScreen {
+            rpc: rpc.as_ref(),
+            config: &config,
+            pubsub_clients: Vec::new(),
+            mongo: None,
+            metadata_fetcher: None,
+            sol_price: Some(150.0),
+            wait: false,
+        };
+        let screened = screen_new_pool(creation.encoded(), &screen).await.unwrap();
+        assert_eq!(screened.pool_info.creator, creator);
+    }
 }
@@ -1,5 +1,8 @@
+use crate::quote::RAYDIUM_FEE_BPS;
 use regex::Regex;
+use serde::{ Deserialize, Serialize };
 use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 
 pub fn find_log_entry(needle: &str, log_entries: &[String]) -> Option<String> {
     for entry in log_entries {
@@ -15,8 +18,60 @@ pub fn fix_relaxed_json_in_lp_log_entry(relaxed_json: &str) -> String {
     re.replace_all(relaxed_json, "$1\"$2\":").into_owned()
 }
 
-#[derive(Debug, Clone)]
+/// Which AMM program a pool belongs to, and so which swap instruction it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolType {
+    /// Legacy AMM v4 backed by an OpenBook market
+    #[default]
+    AmmV4,
+    /// Constant product AMM without a market, supports Token-2022 mints
+    Cpmm,
+    /// Concentrated liquidity, liquidity is added after the pool is created
+    Clmm,
+    /// Meteora liquidity book, liquidity is added to bins after the pool is created
+    MeteoraDlmm,
+    /// Meteora constant product pool whose tokens sit in vaults shared with other pools
+    MeteoraDynamic,
+    /// Orca concentrated liquidity, liquidity is added after the pool is created
+    OrcaWhirlpool,
+}
+
+impl PoolType {
+    /// Whether liquidity is deposited for an LP mint at creation, which the LP burn check
+    /// relies on. The other types are created empty and checked for added liquidity.
+    pub fn has_lp_mint(&self) -> bool {
+        matches!(self, PoolType::AmmV4 | PoolType::Cpmm | PoolType::MeteoraDynamic)
+    }
+
+    /// Whether `raydium_swap` can build the swap, the rest only go to the Redis executor.
+    pub fn is_raydium(&self) -> bool {
+        matches!(self, PoolType::AmmV4 | PoolType::Cpmm | PoolType::Clmm)
+    }
+}
+
+impl FromStr for PoolType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "amm_v4" => Ok(PoolType::AmmV4),
+            "cpmm" => Ok(PoolType::Cpmm),
+            "clmm" => Ok(PoolType::Clmm),
+            "meteora_dlmm" => Ok(PoolType::MeteoraDlmm),
+            "meteora_dynamic" => Ok(PoolType::MeteoraDynamic),
+            "orca_whirlpool" => Ok(PoolType::OrcaWhirlpool),
+            other => Err(format!("unknown pool type {}", other)),
+        }
+    }
+}
+
+/// Fields that only exist for some pool types are left at `Pubkey::default()`: the market
+/// for every pool type but AMM v4, the config and observation accounts for AMM v4 and the
+/// LP mint and vault for pools without one.
+#[derive(Debug, Clone, Default)]
 pub struct PoolInfo {
+    pub pool_type: PoolType,
     pub id: Pubkey,
     pub base_mint: Pubkey,
     pub quote_mint: Pubkey,
@@ -24,6 +79,7 @@ pub struct PoolInfo {
     pub base_decimals: u8,
     pub quote_decimals: u8,
     pub lp_decimals: u8,
+    /// AMM version, 4 for AMM v4 pools and 0 for pool types without one
     pub version: u8,
     pub program_id: Pubkey,
     pub authority: Pubkey,
@@ -40,8 +96,20 @@ pub struct PoolInfo {
     pub quote_reserve: u64,
     pub lp_reserve: u64,
     pub open_time: u64,
+    pub trade_fee_bps: u64,
+    /// Fee tier account of CPMM and CLMM pools
+    pub config_id: Pubkey,
+    /// Price oracle account of CPMM, CLMM and DLMM pools, written by every swap
+    pub observation_id: Pubkey,
+    pub base_token_program: Pubkey,
+    pub quote_token_program: Pubkey,
+    /// Fee payer of the creating transaction, or for pump.fun migrations the creator on
+    /// the bonding curve
+    pub creator: Pubkey,
 }
 impl PoolInfo {
+    /// An AMM v4 pool.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: Pubkey,
         base_mint: Pubkey,
@@ -68,6 +136,7 @@ impl PoolInfo {
         open_time: u64
     ) -> Self {
         PoolInfo {
+            pool_type: PoolType::AmmV4,
             id,
             base_mint,
             quote_mint,
@@ -91,6 +160,12 @@ impl PoolInfo {
             quote_reserve,
             lp_reserve,
             open_time,
+            trade_fee_bps: RAYDIUM_FEE_BPS,
+            config_id: Pubkey::default(),
+            observation_id: Pubkey::default(),
+            base_token_program: spl_token::id(),
+            quote_token_program: spl_token::id(),
+            creator: Pubkey::default(),
         }
     }
 }
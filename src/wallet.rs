@@ -0,0 +1,97 @@
+use crate::rpc::{ is_account_not_found, SolanaRpc };
+use solana_sdk::bs58;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ read_keypair_file, Keypair, Signer };
+use spl_associated_token_account::get_associated_token_address;
+use std::error::Error;
+
+#[derive(Debug, Clone)]
+pub struct WalletBalance {
+    pub owner: Pubkey,
+    pub wsol_account: Pubkey,
+    pub wsol_balance: f64,
+    pub sol_balance: f64,
+}
+
+/// Loads the operator keypair from `keypair_path` (a Solana CLI JSON keypair file),
+/// or from the base58 encoded `PRIVATE_KEY` environment variable when no path is set.
+pub fn load_keypair(keypair_path: &str) -> Result<Keypair, Box<dyn Error>> {
+    if !keypair_path.is_empty() {
+        return read_keypair_file(keypair_path).map_err(|e|
+            format!("Failed to read keypair file {}: {}", keypair_path, e).into()
+        );
+    }
+
+    let private_key = std::env
+        ::var("PRIVATE_KEY")
+        .map_err(|_| "You must set wallet.keypair_path or the PRIVATE_KEY environment variable!")?;
+    let bytes = bs58
+        ::decode(private_key.trim())
+        .into_vec()
+        .map_err(|e| format!("PRIVATE_KEY is not valid base58: {}", e))?;
+    let keypair = Keypair::from_bytes(&bytes).map_err(|e|
+        format!("PRIVATE_KEY is not a valid keypair: {}", e)
+    )?;
+
+    Ok(keypair)
+}
+
+pub fn wsol_account(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &spl_token::native_mint::id())
+}
+
+pub async fn get_wallet_balance(
+    rpc_client: &dyn SolanaRpc,
+    keypair: &Keypair
+) -> Result<WalletBalance, Box<dyn Error>> {
+    let owner = keypair.pubkey();
+    let wsol_account = wsol_account(&owner);
+
+    let lamports = rpc_client.get_balance(&owner).await?;
+
+    // A missing associated token account simply means the wallet holds no WSOL yet
+    let wsol_balance = match rpc_client.get_token_account_balance(&wsol_account).await {
+        Ok(balance) => balance.ui_amount.unwrap_or(0.0),
+        Err(err) if is_account_not_found(&err) => 0.0,
+        Err(err) => {
+            return Err(format!("Failed to read the WSOL balance of {}: {}", owner, err).into());
+        }
+    };
+
+    Ok(WalletBalance {
+        owner,
+        wsol_account,
+        wsol_balance,
+        sol_balance: lamports_to_sol(lamports),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::token_amount;
+    use crate::rpc::FakeRpc;
+
+    #[tokio::test]
+    async fn reads_sol_and_wsol_balances() {
+        let rpc = FakeRpc::new();
+        let keypair = Keypair::new();
+        rpc.insert_balance(keypair.pubkey(), 2_500_000_000);
+        rpc.insert_token_balance(wsol_account(&keypair.pubkey()), token_amount(1_000_000_000, 9));
+
+        let balance = get_wallet_balance(rpc.as_ref(), &keypair).await.unwrap();
+        assert_eq!(balance.sol_balance, 2.5);
+        assert_eq!(balance.wsol_balance, 1.0);
+    }
+
+    #[tokio::test]
+    async fn missing_wsol_account_is_an_empty_balance() {
+        let rpc = FakeRpc::new();
+        let keypair = Keypair::new();
+
+        let balance = get_wallet_balance(rpc.as_ref(), &keypair).await.unwrap();
+        assert_eq!(balance.sol_balance, 0.0);
+        assert_eq!(balance.wsol_balance, 0.0);
+    }
+}